@@ -1,16 +1,18 @@
 import React, { useEffect } from "react";
-import { Routes, Route, Navigate } from "react-router-dom";
+import { Routes, Route, Navigate, useLocation } from "react-router-dom";
 import { useSelector } from "react-redux";
 import { Lobby } from "./pages/Lobby.jsx";
 import { EditorWorkspace } from "./pages/EditorWorkspace.jsx";
 import { AuthPage } from "./pages/AuthPage.jsx";
+import { InviteAccept } from "./pages/InviteAccept.jsx";
 import { ToastProvider } from "./components/Toast.jsx";
 import "./styles/main.css";
 
 /** Захищений маршрут — якщо токена немає, редиректить на /login */
 function ProtectedRoute({ children }) {
     const token = useSelector((state) => state.auth.token);
-    if (!token) return <Navigate to="/login" replace />;
+    const location = useLocation();
+    if (!token) return <Navigate to="/login" replace state={{ from: location.pathname }} />;
     return children;
 }
 
@@ -28,6 +30,7 @@ export function App() {
                     <Route path="/login" element={<AuthPage />} />
                     <Route path="/" element={<ProtectedRoute><Lobby /></ProtectedRoute>} />
                     <Route path="/editor" element={<ProtectedRoute><EditorWorkspace isDark={true} /></ProtectedRoute>} />
+                    <Route path="/invite/:token" element={<ProtectedRoute><InviteAccept /></ProtectedRoute>} />
                     <Route path="*" element={<Navigate to="/" replace />} />
                 </Routes>
             </div>
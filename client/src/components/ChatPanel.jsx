@@ -0,0 +1,116 @@
+import React, { useEffect, useRef, useState } from "react";
+import { useSelector } from "react-redux";
+
+function formatTime(iso) {
+    const date = new Date(iso);
+    return date.toLocaleTimeString([], { hour: "2-digit", minute: "2-digit" });
+}
+
+/** Підсвічує згадки @username у тексті повідомлення. */
+function MessageBody({ body }) {
+    const parts = body.split(/(\s+)/);
+    return (
+        <p className="chat-body">
+            {parts.map((part, i) =>
+                part.startsWith("@") && part.length > 1
+                    ? <span key={i} className="chat-mention">{part}</span>
+                    : part
+            )}
+        </p>
+    );
+}
+
+export function ChatPanel({ onSend, onEdit, onDelete, onLoadOlder }) {
+    const { chatMessages, chatHasMore, connectionStatus } = useSelector((s) => s.collab);
+    const userId = useSelector((s) => s.auth.userId);
+
+    const [draft, setDraft] = useState("");
+    const [editingId, setEditingId] = useState(null);
+    const listRef = useRef(null);
+    const lastIdRef = useRef(null);
+
+    // Прокручуємо вниз лише коли з'являється нове повідомлення, а не при завантаженні старіших
+    useEffect(() => {
+        const last = chatMessages[chatMessages.length - 1]?.id ?? null;
+        if (last !== lastIdRef.current && listRef.current) {
+            listRef.current.scrollTop = listRef.current.scrollHeight;
+        }
+        lastIdRef.current = last;
+    }, [chatMessages]);
+
+    const handleSubmit = (e) => {
+        e.preventDefault();
+        const body = draft.trim();
+        if (!body) return;
+        if (editingId) onEdit(editingId, body);
+        else onSend(body);
+        setDraft("");
+        setEditingId(null);
+    };
+
+    const startEdit = (message) => {
+        setEditingId(message.id);
+        setDraft(message.body);
+    };
+
+    const cancelEdit = () => {
+        setEditingId(null);
+        setDraft("");
+    };
+
+    const connected = connectionStatus === "connected";
+
+    return (
+        <div className="chat-panel">
+            <div className="chat-list" ref={listRef}>
+                {chatHasMore && (
+                    <button className="chat-load-older" onClick={() => onLoadOlder(chatMessages[0]?.id)}>
+                        Попередні повідомлення
+                    </button>
+                )}
+                {chatMessages.length === 0 && <p className="collab-empty">Повідомлень ще немає</p>}
+                {chatMessages.map((m) => {
+                    const own = m.author_id === userId;
+                    const mentioned = !own && m.mentions?.includes(userId);
+                    return (
+                        <div key={m.id} className={`chat-message${mentioned ? " chat-message--mentioned" : ""}`}>
+                            <div className="chat-meta">
+                                <span className="chat-author">{m.author_username ?? "видалений користувач"}</span>
+                                <span className="chat-time">
+                                    {formatTime(m.created_at)}{m.edited_at ? " · змінено" : ""}
+                                </span>
+                                {own && (
+                                    <span className="chat-actions">
+                                        <button title="Редагувати" onClick={() => startEdit(m)}>
+                                            <span className="material-icons">edit</span>
+                                        </button>
+                                        <button title="Видалити" onClick={() => onDelete(m.id)}>
+                                            <span className="material-icons">delete</span>
+                                        </button>
+                                    </span>
+                                )}
+                            </div>
+                            <MessageBody body={m.body} />
+                        </div>
+                    );
+                })}
+            </div>
+            <form className="chat-form" onSubmit={handleSubmit}>
+                {editingId && (
+                    <div className="chat-editing">
+                        Редагування повідомлення
+                        <button type="button" onClick={cancelEdit}>скасувати</button>
+                    </div>
+                )}
+                <input
+                    className="collab-add-input"
+                    placeholder={connected ? "Повідомлення, @username для згадки" : "Немає з'єднання"}
+                    value={draft}
+                    disabled={!connected}
+                    maxLength={4000}
+                    onChange={(e) => setDraft(e.target.value)}
+                />
+            </form>
+        </div>
+    );
+}
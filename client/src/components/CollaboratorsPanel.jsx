@@ -1,6 +1,6 @@
-import React, { useState } from "react";
+import React, { useCallback, useEffect, useState } from "react";
 import { useSelector } from "react-redux";
-import { getMembersEndpoint, removeMemberEndpoint } from "../configs/paths.js";
+import { getInvitesEndpoint, getMembersEndpoint, getRunPolicyEndpoint, removeMemberEndpoint, revokeInviteEndpoint } from "../configs/paths.js";
 import { useToast } from "./Toast.jsx";
 
 function shortenPath(path, maxLen = 28) {
@@ -36,6 +36,18 @@ function RoleBadge({ role }) {
     );
 }
 
+function inviteUrl(token) {
+    return `${window.location.origin}/invite/${token}`;
+}
+
+function describeInvite(invite) {
+    const uses = invite.max_uses ? `${invite.uses}/${invite.max_uses}` : `${invite.uses}`;
+    const expires = invite.expires_at
+        ? `до ${new Date(invite.expires_at).toLocaleString([], { dateStyle: "short", timeStyle: "short" })}`
+        : "безстрокове";
+    return `${uses} викор. · ${expires}`;
+}
+
 function Avatar({ username, color }) {
     return (
         <div className="collab-avatar" style={{ background: `${color}22`, border: `2px solid ${color}55`, color }}>
@@ -44,8 +56,8 @@ function Avatar({ username, color }) {
     );
 }
 
-export function CollaboratorsPanel({ docId, onChangeRole }) {
-    const { collaborators, myRole, myConnId } = useSelector((s) => s.collab);
+export function CollaboratorsPanel({ docId, onChangeRole, onFollow, onUnfollow, onRequestAccess, onResolveAccessRequest }) {
+    const { collaborators, myRole, myConnId, followLeader, accessRequests, accessRequestPending, runsEditorsOnly } = useSelector((s) => s.collab);
     const token = useSelector((s) => s.auth.token);
     const showToast = useToast();
 
@@ -53,8 +65,89 @@ export function CollaboratorsPanel({ docId, onChangeRole }) {
     const [addLoading, setAddLoading] = useState(false);
     const [showAddForm, setShowAddForm] = useState(false);
 
+    const [accessMessage, setAccessMessage] = useState("");
+    const [showInviteForm, setShowInviteForm] = useState(false);
+    const [invites, setInvites] = useState([]);
+    const [inviteRole, setInviteRole] = useState("reader");
+    const [inviteMaxUses, setInviteMaxUses] = useState("");
+    const [inviteHours, setInviteHours] = useState("24");
+
     const isManager = myRole === "manager";
 
+    const loadInvites = useCallback(async () => {
+        try {
+            const res = await fetch(getInvitesEndpoint(docId), {
+                headers: { Authorization: `Bearer ${token}` },
+            });
+            if (res.ok) setInvites(await res.json());
+        } catch {
+            showToast("Помилка завантаження запрошень");
+        }
+    }, [docId, token, showToast]);
+
+    useEffect(() => {
+        if (isManager && showInviteForm) loadInvites();
+    }, [isManager, showInviteForm, loadInvites]);
+
+    const handleCreateInvite = async (e) => {
+        e.preventDefault();
+        try {
+            const res = await fetch(getInvitesEndpoint(docId), {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json",
+                    Authorization: `Bearer ${token}`,
+                },
+                body: JSON.stringify({
+                    role: inviteRole,
+                    max_uses: inviteMaxUses ? Number(inviteMaxUses) : null,
+                    expires_in_hours: inviteHours ? Number(inviteHours) : null,
+                }),
+            });
+            if (!res.ok) {
+                const msg = await res.text();
+                showToast(msg || "Помилка створення запрошення");
+                return;
+            }
+            const invite = await res.json();
+            await navigator.clipboard.writeText(inviteUrl(invite.token));
+            showToast("Посилання-запрошення скопійовано!");
+            loadInvites();
+        } catch {
+            showToast("Помилка підключення");
+        }
+    };
+
+    const handleRevokeInvite = async (inviteId) => {
+        try {
+            const res = await fetch(revokeInviteEndpoint(docId, inviteId), {
+                method: "DELETE",
+                headers: { Authorization: `Bearer ${token}` },
+            });
+            if (res.ok) setInvites((list) => list.filter((i) => i.id !== inviteId));
+            else showToast("Помилка відкликання запрошення");
+        } catch {
+            showToast("Помилка підключення");
+        }
+    };
+
+    // Нове правило приходить усім учасникам через WebSocket (run_policy)
+    const handleRunPolicy = async (editorsOnly) => {
+        try {
+            const res = await fetch(getRunPolicyEndpoint(docId), {
+                method: "PUT",
+                headers: {
+                    "Content-Type": "application/json",
+                    Authorization: `Bearer ${token}`,
+                },
+                body: JSON.stringify({ editors_only: editorsOnly }),
+            });
+            if (!res.ok) showToast("Помилка зміни правила запуску");
+        } catch {
+            showToast("Помилка підключення");
+        }
+    };
+
     const handleAddMember = async (e) => {
         e.preventDefault();
         if (!addUsername.trim()) return;
@@ -114,7 +207,12 @@ export function CollaboratorsPanel({ docId, onChangeRole }) {
                 ) : (
                     <ul className="collab-list">
                         {collaborators.map((c) => (
-                            <li key={c.connId} className={`collab-item ${c.isMe ? "collab-item--me" : ""}`}>
+                            <li
+                                key={c.connId}
+                                className={`collab-item ${c.isMe ? "collab-item--me" : ""}`}
+                                style={c.idle ? { opacity: 0.5 } : undefined}
+                                title={c.idle ? "Неактивний" : undefined}
+                            >
                                 <Avatar username={c.username} color={c.color} />
                                 <div className="collab-item-info">
                                     <span className="collab-name">
@@ -122,14 +220,29 @@ export function CollaboratorsPanel({ docId, onChangeRole }) {
                                         {c.isMe && <span className="collab-you-tag">ви</span>}
                                     </span>
                                     <RoleBadge role={c.role} />
-                                    {c.cursor?.path && (
-                                        <span className="collab-file-path" title={c.cursor.path}>
+                                    {c.following && c.following === myConnId && (
+                                        <span className="collab-follow-tag">стежить за вами</span>
+                                    )}
+                                    {(c.activeFile ?? c.cursor?.path) && (
+                                        <span className="collab-file-path" title={c.activeFile ?? c.cursor.path}>
                                             <span className="material-icons" style={{ fontSize: "10px", flexShrink: 0 }}>insert_drive_file</span>
-                                            {shortenPath(c.cursor.path)}
+                                            {shortenPath(c.activeFile ?? c.cursor.path)}
                                         </span>
                                     )}
                                 </div>
 
+                                {!c.isMe && (
+                                    <button
+                                        className={`collab-follow-btn ${followLeader === c.connId ? "active" : ""}`}
+                                        onClick={() => followLeader === c.connId ? onUnfollow?.() : onFollow?.(c.connId)}
+                                        title={followLeader === c.connId ? "Припинити стежити" : "Стежити за учасником"}
+                                    >
+                                        <span className="material-icons">
+                                            {followLeader === c.connId ? "visibility_off" : "visibility"}
+                                        </span>
+                                    </button>
+                                )}
+
                                 {/* Role change dropdown — only visible to Manager, not for themselves */}
                                 {isManager && !c.isMe && (
                                     <select
@@ -149,6 +262,62 @@ export function CollaboratorsPanel({ docId, onChangeRole }) {
                 )}
             </div>
 
+            {/* Edit access request — only for Reader */}
+            {myRole === "reader" && (
+                <div className="collab-list-section">
+                    <p className="collab-list-label">Право редагування</p>
+                    {accessRequestPending ? (
+                        <p className="collab-empty">Запит очікує на розгляд менеджера</p>
+                    ) : (
+                        <form
+                            className="collab-add-form"
+                            onSubmit={(e) => {
+                                e.preventDefault();
+                                onRequestAccess?.(accessMessage.trim());
+                                setAccessMessage("");
+                            }}
+                        >
+                            <div className="collab-add-input-row">
+                                <input
+                                    className="collab-add-input"
+                                    type="text"
+                                    placeholder="Коментар (необов'язково)"
+                                    maxLength={500}
+                                    value={accessMessage}
+                                    onChange={(e) => setAccessMessage(e.target.value)}
+                                />
+                                <button type="submit" className="btn primary collab-add-btn" title="Запросити право редагування">
+                                    <span className="material-icons">edit_note</span>
+                                </button>
+                            </div>
+                        </form>
+                    )}
+                </div>
+            )}
+
+            {/* Pending access requests — only for Manager */}
+            {isManager && accessRequests.length > 0 && (
+                <div className="collab-list-section">
+                    <p className="collab-list-label">Запити на редагування</p>
+                    <ul className="collab-invite-list">
+                        {accessRequests.map((request) => (
+                            <li key={request.id} className="collab-invite-item">
+                                <span className="collab-name">{request.username ?? "видалений користувач"}</span>
+                                <span className="collab-invite-meta" title={request.message ?? undefined}>
+                                    {request.message ?? ""}
+                                </span>
+                                <button title="Схвалити" onClick={() => onResolveAccessRequest?.(request.id, true)}>
+                                    <span className="material-icons">check</span>
+                                </button>
+                                <button title="Відхилити" onClick={() => onResolveAccessRequest?.(request.id, false)}>
+                                    <span className="material-icons">close</span>
+                                </button>
+                            </li>
+                        ))}
+                    </ul>
+                </div>
+            )}
+
             {/* Add member section — only for Manager */}
             {isManager && (
                 <div className="collab-list-section">
@@ -184,6 +353,98 @@ export function CollaboratorsPanel({ docId, onChangeRole }) {
                 </div>
             )}
 
+            {/* Invite links — only for Manager */}
+            {isManager && (
+                <div className="collab-list-section">
+                    <div className="collab-list-label-row">
+                        <p className="collab-list-label">Посилання-запрошення</p>
+                        <button
+                            className="collab-add-toggle"
+                            onClick={() => setShowInviteForm(v => !v)}
+                            title="Керувати запрошеннями"
+                        >
+                            <span className="material-icons">{showInviteForm ? "close" : "add_link"}</span>
+                        </button>
+                    </div>
+
+                    {showInviteForm && (
+                        <>
+                            <form className="collab-add-form collab-invite-form" onSubmit={handleCreateInvite}>
+                                <select
+                                    className="collab-role-select"
+                                    value={inviteRole}
+                                    onChange={(e) => setInviteRole(e.target.value)}
+                                    title="Роль"
+                                >
+                                    <option value="reader">Читач</option>
+                                    <option value="editor">Редактор</option>
+                                    <option value="manager">Керівник</option>
+                                </select>
+                                <input
+                                    className="collab-add-input"
+                                    type="number"
+                                    min="1"
+                                    placeholder="Використань: ∞"
+                                    value={inviteMaxUses}
+                                    onChange={(e) => setInviteMaxUses(e.target.value)}
+                                />
+                                <input
+                                    className="collab-add-input"
+                                    type="number"
+                                    min="1"
+                                    placeholder="Годин: ∞"
+                                    value={inviteHours}
+                                    onChange={(e) => setInviteHours(e.target.value)}
+                                />
+                                <button type="submit" className="btn primary collab-add-btn" title="Створити посилання">
+                                    <span className="material-icons">link</span>
+                                </button>
+                            </form>
+
+                            {invites.length === 0 ? (
+                                <p className="collab-empty">Активних запрошень немає</p>
+                            ) : (
+                                <ul className="collab-invite-list">
+                                    {invites.map((invite) => (
+                                        <li key={invite.id} className="collab-invite-item">
+                                            <RoleBadge role={invite.role} />
+                                            <span className="collab-invite-meta">{describeInvite(invite)}</span>
+                                            <button
+                                                title="Скопіювати посилання"
+                                                onClick={() => {
+                                                    navigator.clipboard.writeText(inviteUrl(invite.token));
+                                                    showToast("Посилання скопійовано!");
+                                                }}
+                                            >
+                                                <span className="material-icons">content_copy</span>
+                                            </button>
+                                            <button title="Відкликати" onClick={() => handleRevokeInvite(invite.id)}>
+                                                <span className="material-icons">link_off</span>
+                                            </button>
+                                        </li>
+                                    ))}
+                                </ul>
+                            )}
+                        </>
+                    )}
+                </div>
+            )}
+
+            {/* Run policy — only for Manager */}
+            {isManager && (
+                <div className="collab-list-section">
+                    <p className="collab-list-label">Запуск коду</p>
+                    <label className="collab-run-policy">
+                        <input
+                            type="checkbox"
+                            checked={runsEditorsOnly}
+                            onChange={(e) => handleRunPolicy(e.target.checked)}
+                        />
+                        <span>Лише редактори та керівники</span>
+                    </label>
+                </div>
+            )}
+
             {/* Copy invite link */}
             <button
                 id="copy-link-btn"
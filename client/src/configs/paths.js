@@ -2,8 +2,12 @@ function serverUrl() {
     return "localhost:8080/api";
 }
 
-export function webSocketUrl(id, token) {
-    return `ws://${serverUrl()}/ws/${id}?token=${encodeURIComponent(token)}`;
+export function webSocketUrl(id, ticket) {
+    return `ws://${serverUrl()}/ws/${id}?ticket=${encodeURIComponent(ticket)}`;
+}
+
+export function getWsTicketEndpoint(id) {
+    return `http://${serverUrl()}/documents/${id}/ws-ticket`;
 }
 
 export function getSnapshotEndpoint() {
@@ -30,6 +34,14 @@ export function getTestEndpoint(id) {
     return `http://${serverUrl()}/documents/${id}/test`;
 }
 
+export function getCancelRunEndpoint(id, runId) {
+    return `http://${serverUrl()}/documents/${id}/runs/${runId}/cancel`;
+}
+
+export function getRunPolicyEndpoint(id) {
+    return `http://${serverUrl()}/documents/${id}/run-policy`;
+}
+
 export function getFormatEndpoint(id) {
     return `http://${serverUrl()}/documents/${id}/format`;
 }
@@ -50,6 +62,18 @@ export function removeMemberEndpoint(id, userId) {
     return `http://${serverUrl()}/documents/${id}/members/${userId}`;
 }
 
+export function getInvitesEndpoint(id) {
+    return `http://${serverUrl()}/documents/${id}/invites`;
+}
+
+export function revokeInviteEndpoint(id, inviteId) {
+    return `http://${serverUrl()}/documents/${id}/invites/${inviteId}`;
+}
+
+export function acceptInviteEndpoint(token) {
+    return `http://${serverUrl()}/documents/invites/${encodeURIComponent(token)}/accept`;
+}
+
 export function getParticipantsEndpoint(id) {
     return `http://${serverUrl()}/documents/${id}/participants`;
 }
@@ -58,6 +82,10 @@ export function getExportEndpoint(id) {
     return `http://${serverUrl()}/documents/${id}/export`;
 }
 
+export function getGitBundleEndpoint(id) {
+    return `http://${serverUrl()}/documents/${id}/export/git`;
+}
+
 export function authRegisterEndpoint() {
     return `http://${serverUrl()}/auth/register`;
 }
@@ -65,3 +93,19 @@ export function authRegisterEndpoint() {
 export function authLoginEndpoint() {
     return `http://${serverUrl()}/auth/login`;
 }
+
+export function getTrashEndpoint(id) {
+    return `http://${serverUrl()}/documents/${id}/trash`;
+}
+
+export function getTrashEntryEndpoint(id, trashId) {
+    return `http://${serverUrl()}/documents/${id}/trash/${trashId}`;
+}
+
+export function getFileHistoryEndpoint(id, path) {
+    return `http://${serverUrl()}/documents/${id}/files/history?path=${encodeURIComponent(path)}`;
+}
+
+export function getFileRevisionEndpoint(id, revisionId) {
+    return `http://${serverUrl()}/documents/${id}/files/revisions/${revisionId}`;
+}
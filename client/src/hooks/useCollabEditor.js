@@ -10,7 +10,7 @@ import { basicSetup } from "codemirror";
 import { indentUnit } from "@codemirror/language";
 import { autocompletion, completionKeymap, acceptCompletion, completionStatus } from "@codemirror/autocomplete";
 import { createLspCompletionSource, renderMarkdown } from "../utils/lspCompletion.js";
-import { webSocketUrl, getDocumentTitleEndpoint, getExecuteEndpoint, getTestEndpoint, getFormatEndpoint, getHoverEndpoint } from "../configs/paths.js";
+import { webSocketUrl, getWsTicketEndpoint, getDocumentTitleEndpoint, getExecuteEndpoint, getTestEndpoint, getCancelRunEndpoint, getFormatEndpoint, getHoverEndpoint } from "../configs/paths.js";
 import { useToast } from "../components/Toast.jsx";
 import { stripAnsi } from "../utils/ansiToHtml.js";
 import {
@@ -22,6 +22,8 @@ import {
     appendConsoleOutput,
     setConsoleVisible,
     setIsRunning,
+    setRunStdinOpen,
+    setRunsEditorsOnly,
     setDiagnostics,
     updateActiveFileContent,
     upsertFile,
@@ -33,8 +35,22 @@ import {
     resetCollabState,
     setCollaborators,
     updateCollaboratorCursor,
+    setMyRole,
+    setMyConnId,
+    setChatHistory,
+    addChatMessage,
+    updateChatMessage,
+    removeChatMessage,
+    setFollowLeader,
+    setAccessRequests,
+    addAccessRequest,
+    removeAccessRequest,
+    setAccessRequestPending,
 } from "../store/slices/collabSlice.js";
 
+// Версія протоколу текстових WS-повідомлень, яку підтримує клієнт
+const PROTOCOL_VERSION = 1;
+
 // ── LocalStorage helpers ──────────────────────────────────────────────────────
 
 function lsKey(docId)  { return `co-write:files:${docId}`; }
@@ -116,13 +132,14 @@ export function useCollabEditor(documentId, isDark) {
 
     const token    = useSelector((s) => s.auth.token);
     const username = useSelector((s) => s.auth.username);
+    const userId   = useSelector((s) => s.auth.userId);
     const tokenRef = useRef(token);
     useEffect(() => { tokenRef.current = token; }, [token]);
 
     const {
         docTitle, connectionStatus, syncStatus, wordStats,
         consoleOutput, consoleVisible, isRunning,
-        files, activeFile, collaborators, myRole, myConnId,
+        files, activeFile, collaborators, myRole, myConnId, followLeader,
     } = useSelector((s) => s.collab);
 
     // ── Refs ──────────────────────────────────────────────────────────────────
@@ -138,6 +155,16 @@ export function useCollabEditor(documentId, isDark) {
     const isRemoteUpdateRef = useRef(false);
     const debounceRef       = useRef(null);
     const cursorThrottleRef = useRef(null);
+    const requestSeqRef     = useRef(0);
+    const chatOlderPendingRef = useRef(false);
+    const viewportThrottleRef = useRef(null);
+    const pendingViewportRef  = useRef(null);
+    // Поточний запуск через WS: request_id команди, id запуску та накопичений вивід для діагностик
+    const runRequestRef = useRef(null);
+    const runIdRef      = useRef(null);
+    const runOutputRef  = useRef("");
+    // Id запуску через REST: обирається клієнтом, щоб скасувати запуск до відповіді сервера
+    const restRunIdRef  = useRef(null);
 
     const runCodeRef    = useRef(null);
     const formatCodeRef = useRef(null);
@@ -149,12 +176,15 @@ export function useCollabEditor(documentId, isDark) {
     const docIdRef      = useRef(documentId);
     const collabsRef    = useRef(collaborators);
     const myConnIdRef   = useRef(myConnId);
+    const followLeaderRef = useRef(followLeader);
+    const hasFollowersRef = useRef(false);
 
     useEffect(() => { filesRef.current = files; },           [files]);
     useEffect(() => { activeFileRef.current = activeFile; }, [activeFile]);
     useEffect(() => { docIdRef.current = documentId; },      [documentId]);
     useEffect(() => { collabsRef.current = collaborators; }, [collaborators]);
     useEffect(() => { myConnIdRef.current = myConnId; },     [myConnId]);
+    useEffect(() => { followLeaderRef.current = followLeader; }, [followLeader]);
 
     // ── Automerge init ────────────────────────────────────────────────────────
     if (!docRef.current) {
@@ -187,28 +217,111 @@ export function useCollabEditor(documentId, isDark) {
         }
     }, []);
 
-    // ── FS event через WS ─────────────────────────────────────────────────────
-    const sendFsEvent = useCallback((event) => {
+    // ── Команди протоколу WS (з request_id для ack/error) ─────────────────────
+    const sendCommand = useCallback((command) => {
         const ws = socketRef.current;
-        if (!ws || ws.readyState !== WebSocket.OPEN) return;
-        try { ws.send(JSON.stringify({ event })); } catch (e) {
-            console.error("WS send fs event error:", e);
+        if (!ws || ws.readyState !== WebSocket.OPEN) return null;
+        requestSeqRef.current += 1;
+        const requestId = `r${requestSeqRef.current}`;
+        try {
+            ws.send(JSON.stringify({ ...command, request_id: requestId }));
+            return requestId;
+        } catch (e) {
+            console.error(`WS send ${command.type} error:`, e);
+            return null;
         }
     }, []);
 
+    // ── FS event через WS ─────────────────────────────────────────────────────
+    const sendFsEvent = useCallback((event) => {
+        sendCommand({ type: "fs_event", event });
+    }, [sendCommand]);
+
     // ── Role change ───────────────────────────────────────────────────────────
     const sendRoleChange = useCallback((targetConnId, newRole) => {
-        const ws = socketRef.current;
-        if (!ws || ws.readyState !== WebSocket.OPEN) return;
-        try {
-            ws.send(JSON.stringify({ type: "role_change", target_conn_id: targetConnId, new_role: newRole }));
-        } catch (e) {
-            console.error("WS send role change error:", e);
-        }
+        sendCommand({ type: "role_change", target_conn_id: targetConnId, new_role: newRole });
+    }, [sendCommand]);
+
+    // ── Режим follow ──────────────────────────────────────────────────────────
+    const sendFollow = useCallback((leaderConnId) => {
+        dispatch(setFollowLeader(leaderConnId));
+        sendCommand({ type: "follow", leader_conn_id: leaderConnId });
+    }, [dispatch, sendCommand]);
+
+    const sendUnfollow = useCallback(() => {
+        dispatch(setFollowLeader(null));
+        sendCommand({ type: "unfollow" });
+    }, [dispatch, sendCommand]);
+
+    /** Прокручує редактор до видимої області лідера, щойно відкрито потрібний файл. */
+    const applyPendingViewport = useCallback(() => {
+        const view = cmViewRef.current;
+        const viewport = pendingViewportRef.current;
+        if (!view || !viewport || viewport.path !== activeFileRef.current) return;
+        pendingViewportRef.current = null;
+        const line = Math.min(Math.max(viewport.from_line, 1), view.state.doc.lines);
+        view.dispatch({ effects: EditorView.scrollIntoView(view.state.doc.line(line).from, { y: "start" }) });
     }, []);
 
+    /** Повідомляє послідовникам, який файл і які рядки зараз видно (не частіше ніж раз на 100 мс). */
+    const sendViewport = useCallback(() => {
+        const view = cmViewRef.current;
+        if (!view || !hasFollowersRef.current || !activeFileRef.current) return;
+        if (viewportThrottleRef.current) return;
+        viewportThrottleRef.current = setTimeout(() => {
+            viewportThrottleRef.current = null;
+            const top    = view.scrollDOM.scrollTop;
+            const bottom = top + view.scrollDOM.clientHeight;
+            const doc    = view.state.doc;
+            sendCommand({
+                type:      "viewport",
+                path:      activeFileRef.current,
+                from_line: doc.lineAt(view.lineBlockAtHeight(top).from).number,
+                to_line:   doc.lineAt(view.lineBlockAtHeight(bottom).from).number,
+            });
+        }, 100);
+    }, [sendCommand]);
+
+    useEffect(() => {
+        hasFollowersRef.current = !!myConnId && collaborators.some(c => c.following === myConnId);
+        if (hasFollowersRef.current) sendViewport();
+    }, [collaborators, myConnId, sendViewport]);
+
+    // Лідер вийшов із сесії — вимикаємо follow
+    useEffect(() => {
+        if (followLeader && !collaborators.some(c => c.connId === followLeader)) sendUnfollow();
+    }, [collaborators, followLeader, sendUnfollow]);
+
+    // ── Чат ───────────────────────────────────────────────────────────────────
+    const sendChatMessage = useCallback((body) => {
+        sendCommand({ type: "chat_send", body });
+    }, [sendCommand]);
+
+    const editChatMessage = useCallback((messageId, body) => {
+        sendCommand({ type: "chat_edit", message_id: messageId, body });
+    }, [sendCommand]);
+
+    const deleteChatMessage = useCallback((messageId) => {
+        sendCommand({ type: "chat_delete", message_id: messageId });
+    }, [sendCommand]);
+
+    const loadOlderChat = useCallback((beforeId) => {
+        chatOlderPendingRef.current = true;
+        sendCommand({ type: "chat_history", before: beforeId });
+    }, [sendCommand]);
+
+    // ── Запити на право редагування ───────────────────────────────────────────
+    const requestEditAccess = useCallback((message) => {
+        dispatch(setAccessRequestPending(true));
+        sendCommand({ type: "request_access", message: message || null });
+    }, [dispatch, sendCommand]);
+
+    const resolveAccessRequest = useCallback((accessRequestId, approve) => {
+        sendCommand({ type: "resolve_access_request", access_request_id: accessRequestId, approve });
+    }, [sendCommand]);
+
     // ── Cursor position via WS ────────────────────────────────────────────────
-    const sendCursor = useCallback((position) => {
+    const sendCursor = useCallback((position, anchor = position) => {
         const ws = socketRef.current;
         if (!ws || ws.readyState !== WebSocket.OPEN) return;
         if (cursorThrottleRef.current) return;
@@ -217,41 +330,59 @@ export function useCollabEditor(documentId, isDark) {
         }, 50);
         try {
             ws.send(JSON.stringify({
-                type:     "cursor",
-                conn_id:  myConnIdRef.current,
-                path:     activeFileRef.current,
+                type:      "cursor",
+                path:      activeFileRef.current,
                 position,
+                selection: anchor !== position ? { anchor, head: position } : null,
             }));
         } catch (e) {
             console.error("WS send cursor error:", e);
         }
     }, []);
 
+    // ── Активний файл та неактивність (presence) ──────────────────────────────
+    const sendActivity = useCallback(() => {
+        sendCommand({
+            type:        "activity",
+            active_file: activeFileRef.current ?? null,
+            idle:        document.hidden,
+        });
+    }, [sendCommand]);
+
+    useEffect(() => { sendActivity(); }, [activeFile, sendActivity]);
+
+    useEffect(() => {
+        document.addEventListener("visibilitychange", sendActivity);
+        return () => document.removeEventListener("visibilitychange", sendActivity);
+    }, [sendActivity]);
+
     // ── Persist files ─────────────────────────────────────────────────────────
     const persistFiles = useCallback((updatedFiles) => {
         if (docIdRef.current) saveFilesToStorage(docIdRef.current, updatedFiles);
     }, []);
 
     // ── Локальні зміни тексту ─────────────────────────────────────────────────
+    const flushLocalChange = useCallback(() => {
+        clearTimeout(debounceRef.current);
+        debounceRef.current = null;
+        if (!cmViewRef.current) return;
+        const newText = cmViewRef.current.state.doc.toString();
+        const path    = activeFileRef.current;
+        if (!path) return;
+
+        dispatch(updateActiveFileContent(newText));
+        const nextFiles = { ...filesRef.current, [path]: newText };
+        persistFiles(nextFiles);
+        sendFsEvent({ action: "upsert", path, content: newText, is_dir: false });
+        dispatch(setSyncStatus("Синхронізовано"));
+    }, [sendFsEvent, persistFiles, dispatch]);
+
     const handleLocalChange = useCallback(() => {
         if (isRemoteUpdateRef.current || !cmViewRef.current) return;
         dispatch(setSyncStatus("Синхронізація..."));
         clearTimeout(debounceRef.current);
-
-        debounceRef.current = setTimeout(() => {
-            if (!cmViewRef.current) return;
-            const newText = cmViewRef.current.state.doc.toString();
-            const path    = activeFileRef.current;
-            if (!path) return;
-
-            dispatch(updateActiveFileContent(newText));
-            const nextFiles = { ...filesRef.current, [path]: newText };
-            persistFiles(nextFiles);
-            sendFsEvent({ action: "upsert", path, content: newText, is_dir: false });
-            dispatch(setSyncStatus("Синхронізовано"));
-            debounceRef.current = null;
-        }, 300);
-    }, [sendFsEvent, persistFiles, dispatch]);
+        debounceRef.current = setTimeout(flushLocalChange, 300);
+    }, [flushLocalChange, dispatch]);
 
     // ── Automerge binary frames ───────────────────────────────────────────────
     const handleBinary = useCallback((data) => {
@@ -284,27 +415,158 @@ export function useCollabEditor(documentId, isDark) {
         let msg;
         try { msg = JSON.parse(raw); } catch { return; }
 
+        if (msg?.type === "welcome") {
+            dispatch(setMyConnId(msg.conn_id));
+            dispatch(setMyRole(msg.role));
+            return;
+        }
+
+        if (msg?.type === "role_changed") {
+            dispatch(setMyRole(msg.role));
+            showToast(`Вашу роль змінено: ${msg.role}`);
+            return;
+        }
+
+        if (msg?.type === "ack") return;
+
+        if (msg?.type === "error") {
+            if (msg.request_id && msg.request_id === runRequestRef.current) {
+                runRequestRef.current = null;
+                dispatch(appendConsoleOutput(`Не вдалося запустити: ${msg.message}\n`));
+                dispatch(setIsRunning(false));
+                return;
+            }
+            showToast(msg.message || "Сервер відхилив дію");
+            return;
+        }
+
+        // ── Запуск коду: вивід запусків кімнати надходить по мірі виконання ──
+        if (msg?.type === "run_started") {
+            const mine = msg.conn_id === myConnIdRef.current;
+            if (mine) {
+                runRequestRef.current = null;
+            } else if (runRequestRef.current || runIdRef.current || restRunIdRef.current) {
+                // Консоль уже показує власний або інший запуск кімнати
+                return;
+            } else {
+                const execLabel = msg.kind === "test" ? "cargo test" : "cargo run";
+                dispatch(setConsoleVisible(true));
+                dispatch(setIsRunning(true));
+                dispatch(setConsoleOutput(`> ${msg.username}: ${execLabel}\n\n`));
+                dispatch(setDiagnostics([]));
+            }
+            runIdRef.current = msg.run_id;
+            runOutputRef.current = "";
+            // stdin приймається лише від учасника, що розпочав запуск
+            dispatch(setRunStdinOpen(mine && msg.interactive));
+            return;
+        }
+        if (msg?.type === "run_queued") {
+            if (msg.run_id !== runIdRef.current) return;
+            dispatch(appendConsoleOutput(`[У черзі: позиція ${msg.position}]\n`));
+            return;
+        }
+        if (msg?.type === "run_output") {
+            if (msg.run_id !== runIdRef.current) return;
+            runOutputRef.current += msg.data;
+            dispatch(appendConsoleOutput(msg.data));
+            document.querySelector(".console-body")?.scrollTo(0, 999999);
+            return;
+        }
+        if (msg?.type === "run_finished") {
+            if (msg.run_id !== runIdRef.current) return;
+            runIdRef.current = null;
+            const code = msg.exit_code ?? "—";
+            dispatch(appendConsoleOutput(msg.status === "cancelled"
+                ? "\n[Запуск скасовано]\n"
+                : `\n[Завершено: код ${code}, ${msg.duration_ms} мс]\n`));
+            dispatch(setDiagnostics(parseRustOutput(runOutputRef.current)));
+            dispatch(setIsRunning(false));
+            return;
+        }
+
+        if (msg?.type === "run_policy") {
+            dispatch(setRunsEditorsOnly(msg.editors_only));
+            return;
+        }
+
         if (msg?.type === "participants_update") {
-            dispatch(setCollaborators({ participants: msg.participants, myUsername: username }));
+            dispatch(setCollaborators({
+                participants: msg.participants,
+                myConnId:     myConnIdRef.current,
+                myUsername:   username,
+            }));
             return;
         }
 
-        if (msg?.type === "permission_denied") {
-            showToast(msg.reason || "Акція заборонена (недостатньо прав)");
+        // ── Чат кімнати ──────────────────────────────────────────────────────
+        if (msg?.type === "chat_history") {
+            dispatch(setChatHistory({
+                messages: msg.messages,
+                hasMore:  msg.has_more,
+                older:    chatOlderPendingRef.current,
+            }));
+            chatOlderPendingRef.current = false;
+            return;
+        }
+        if (msg?.type === "chat_message") {
+            dispatch(addChatMessage(msg.message));
+            const mentioned = userId && msg.message.mentions?.includes(userId);
+            if (mentioned && msg.message.author_id !== userId) {
+                showToast(`${msg.message.author_username ?? "Хтось"} згадав вас у чаті`);
+            }
+            return;
+        }
+        if (msg?.type === "chat_edited") {
+            dispatch(updateChatMessage(msg.message));
+            return;
+        }
+        if (msg?.type === "chat_deleted") {
+            dispatch(removeChatMessage(msg.message_id));
+            return;
+        }
+
+        // ── Запити на право редагування ──────────────────────────────────────
+        if (msg?.type === "access_requests") {
+            dispatch(setAccessRequests(msg.requests));
+            return;
+        }
+        if (msg?.type === "access_requested") {
+            dispatch(addAccessRequest(msg.request));
+            showToast(`${msg.request.username ?? "Читач"} просить право редагування`);
+            return;
+        }
+        if (msg?.type === "access_request_resolved") {
+            dispatch(removeAccessRequest(msg.access_request_id));
+            if (msg.user_id === userId) {
+                dispatch(setAccessRequestPending(false));
+                showToast(msg.approved ? "Запит схвалено — тепер ви можете редагувати" : "Запит на редагування відхилено");
+            }
+            return;
+        }
+
+        // Видима область учасника, за яким стежимо
+        if (msg?.type === "viewport") {
+            if (msg.conn_id !== followLeaderRef.current) return;
+            pendingViewportRef.current = msg;
+            if (msg.path !== activeFileRef.current) dispatch(openTab(msg.path));
+            else applyPendingViewport();
             return;
         }
 
         // Позиція курсора іншого учасника
         if (msg?.type === "cursor") {
             dispatch(updateCollaboratorCursor({
-                connId:   msg.conn_id,
-                path:     msg.path,
-                position: msg.position,
+                connId:    msg.conn_id,
+                path:      msg.path,
+                position:  msg.position,
+                selection: msg.selection ?? null,
             }));
             return;
         }
 
-        const event  = msg?.event;
+        if (msg?.type !== "fs_event") return;
+        const event  = msg.event;
         if (!event) return;
         const action = event.action;
 
@@ -340,7 +602,7 @@ export function useCollabEditor(documentId, isDark) {
             dispatch(setFiles(event.files));
             persistFiles(event.files);
         }
-    }, [dispatch, persistFiles, updateStats, showToast, username]);
+    }, [dispatch, persistFiles, updateStats, showToast, username, userId, applyPendingViewport]);
 
     // ── Cursor decorations у CM при зміні collaborators ───────────────────────
     useEffect(() => {
@@ -383,26 +645,79 @@ export function useCollabEditor(documentId, isDark) {
         let active = true;
         let reconnectTimeout;
 
-        const connect = () => {
+        const connect = async () => {
             if (!tokenRef.current) {
                 dispatch(setConnectionStatus("error"));
                 showToast("Необхідна автентифікація");
                 return;
             }
             dispatch(setConnectionStatus("connecting"));
-            const ws = new WebSocket(webSocketUrl(documentId, tokenRef.current));
+
+            // JWT не передаємо в URL — лише одноразовий квиток, що живе кілька секунд
+            let ticket;
+            try {
+                const res = await fetch(getWsTicketEndpoint(documentId), {
+                    method: "POST",
+                    headers: { Authorization: `Bearer ${tokenRef.current}` },
+                });
+                if (res.status === 401 || res.status === 403) {
+                    dispatch(setConnectionStatus("error"));
+                    showToast((await res.text()) || "Немає доступу до документа");
+                    return;
+                }
+                if (!res.ok) throw new Error(`HTTP ${res.status}`);
+                ({ ticket } = await res.json());
+            } catch {
+                if (!active) return;
+                dispatch(setConnectionStatus("disconnected"));
+                reconnectTimeout = setTimeout(connect, 5000);
+                return;
+            }
+            if (!active) return;
+
+            const ws = new WebSocket(webSocketUrl(documentId, ticket));
             ws.binaryType = "arraybuffer";
 
             ws.onopen = () => {
                 if (!active) { ws.close(); return; }
                 socketRef.current = ws;
                 dispatch(setConnectionStatus("connected"));
+                ws.send(JSON.stringify({
+                    type:             "hello",
+                    protocol_version: PROTOCOL_VERSION,
+                    capabilities:     ["cursors", "chat", "runs"],
+                }));
+                sendActivity();
                 sendSyncMessage();
             };
-            ws.onclose = () => {
+            ws.onclose = (evt) => {
                 if (!active) return;
                 socketRef.current = null;
                 dispatch(setConnectionStatus("disconnected"));
+                if (runRequestRef.current || runIdRef.current) {
+                    runRequestRef.current = null;
+                    runIdRef.current = null;
+                    dispatch(appendConsoleOutput("\nЗ'єднання втрачено — результат запуску невідомий\n"));
+                    dispatch(setIsRunning(false));
+                }
+                // 1008 (policy) — сервер закрив сесію навмисно (наприклад, доступ відкликано)
+                if (evt.code === 1008) {
+                    showToast(evt.reason || "Сервер закрив з'єднання");
+                    return;
+                }
+                // 1001 (going away) — сесію закрито через неактивність; відновлюємо, коли вкладка знову активна
+                if (evt.code === 1001) {
+                    showToast(evt.reason || "Сесію закрито через неактивність");
+                    const resume = () => {
+                        if (document.visibilityState !== "visible") return;
+                        document.removeEventListener("visibilitychange", resume);
+                        window.removeEventListener("focus", resume);
+                        if (active) connect();
+                    };
+                    document.addEventListener("visibilitychange", resume);
+                    window.addEventListener("focus", resume);
+                    return;
+                }
                 showToast("Втрачено з'єднання, перепідключення...");
                 reconnectTimeout = setTimeout(connect, 5000);
             };
@@ -423,6 +738,7 @@ export function useCollabEditor(documentId, isDark) {
             active = false;
             clearTimeout(reconnectTimeout);
             clearTimeout(cursorThrottleRef.current);
+            clearTimeout(viewportThrottleRef.current);
             socketRef.current?.close();
             dispatch(resetCollabState());
         };
@@ -477,8 +793,10 @@ export function useCollabEditor(documentId, isDark) {
 
         cmViewRef.current = view;
         updateStats(view.state.doc.toString());
+        view.scrollDOM.addEventListener("scroll", sendViewport, { passive: true });
 
         return () => {
+            view.scrollDOM.removeEventListener("scroll", sendViewport);
             view.destroy();
             cmViewRef.current = null;
         };
@@ -496,6 +814,8 @@ export function useCollabEditor(documentId, isDark) {
             updateStats(content);
             isRemoteUpdateRef.current = false;
         }
+        applyPendingViewport();
+        sendViewport();
     // eslint-disable-next-line react-hooks/exhaustive-deps
     }, [activeFile]);
 
@@ -519,90 +839,107 @@ export function useCollabEditor(documentId, isDark) {
     }, [myRole]);
 
     // ── Запуск коду ───────────────────────────────────────────────────────────
-    const runCode = useCallback(async () => {
-        if (!documentId || isRunning) return;
-
-        const snapshot = { ...filesRef.current };
-        if (activeFileRef.current && cmViewRef.current) {
-            snapshot[activeFileRef.current] = cmViewRef.current.state.doc.toString();
+    // Сервер запускає збережений стан проекту, тож спершу надсилаємо ще не відправлені правки:
+    // команди одного підключення обробляються по черзі, і run_start побачить їх у БД
+    const startRun = useCallback(async (kind) => {
+        if (debounceRef.current) flushLocalChange();
+
+        const requestId = sendCommand({ type: "run_start", kind, interactive: kind === "run" });
+        if (requestId) {
+            runRequestRef.current = requestId;
+            return;
         }
-        const projectFiles = Object.fromEntries(
-            Object.entries(snapshot).filter(([p]) => !p.endsWith(".gitkeep"))
-        );
-
-        dispatch(setConsoleVisible(true));
-        dispatch(setIsRunning(true));
-        dispatch(setConsoleOutput("> cargo run --release\nCompiling project...\nRunning executable...\n\n"));
-        dispatch(setDiagnostics([]));
 
+        // Без WS-з'єднання — через REST, без потокового виводу та stdin
+        const endpoint = kind === "test" ? getTestEndpoint(documentId) : getExecuteEndpoint(documentId);
+        const runId    = crypto.randomUUID();
+        restRunIdRef.current = runId;
         try {
-            const res  = await fetch(getExecuteEndpoint(documentId), {
+            const res  = await fetch(endpoint, {
                 method:  "POST",
                 headers: {
                     "Content-Type": "application/json",
                     ...(tokenRef.current ? { Authorization: `Bearer ${tokenRef.current}` } : {}),
                 },
-                body: JSON.stringify({ files: projectFiles }),
+                body: JSON.stringify({ run_id: runId }),
             });
+            if (!res.ok) {
+                // Черга заповнена, ліміт запусків або немає прав
+                dispatch(appendConsoleOutput(`Не вдалося запустити: ${await res.text()}\n`));
+                return;
+            }
             const data = await res.json();
+            if (data.status === "cancelled") {
+                dispatch(appendConsoleOutput("\n[Запуск скасовано]\n"));
+                return;
+            }
             const output = data.success
                 ? data.stdout + (data.stderr ? "\n" + data.stderr : "")
-                : (data.stderr || data.stdout || "Unknown compilation error");
+                : (data.stderr || data.stdout || data.message || (kind === "test" ? "Unknown testing error" : "Unknown compilation error"));
             dispatch(appendConsoleOutput(output));
             dispatch(setDiagnostics(parseRustOutput(output)));
         } catch (err) {
             dispatch(appendConsoleOutput("\nПомилка з'єднання з сервером: " + err.message));
         } finally {
+            restRunIdRef.current = null;
             dispatch(setIsRunning(false));
             setTimeout(() => {
                 document.querySelector(".console-body")?.scrollTo(0, 999999);
             }, 50);
         }
-    }, [documentId, isRunning, dispatch]);
+    }, [documentId, flushLocalChange, sendCommand, dispatch]);
+
+    const runCode = useCallback(async () => {
+        if (!documentId || isRunning) return;
+
+        dispatch(setConsoleVisible(true));
+        dispatch(setIsRunning(true));
+        dispatch(setConsoleOutput("> cargo run --release\n\n"));
+        dispatch(setDiagnostics([]));
+
+        await startRun("run");
+    }, [documentId, isRunning, startRun, dispatch]);
 
     // ── Запуск тестів ─────────────────────────────────────────────────────────
     const runTests = useCallback(async () => {
         if (!documentId || isRunning) return;
 
-        const snapshot = { ...filesRef.current };
-        if (activeFileRef.current && cmViewRef.current) {
-            snapshot[activeFileRef.current] = cmViewRef.current.state.doc.toString();
-        }
-        const projectFiles = Object.fromEntries(
-            Object.entries(snapshot).filter(([p]) => !p.endsWith(".gitkeep"))
-        );
-
         dispatch(setConsoleVisible(true));
         dispatch(setIsRunning(true));
-        const isCargo   = Object.keys(projectFiles).some(p => p === "Cargo.toml");
+        const isCargo   = Object.keys(filesRef.current).some(p => p === "Cargo.toml");
         const execLabel = isCargo ? "cargo test" : "rustc --test";
-        dispatch(setConsoleOutput(`> ${execLabel}\nCompiling tests...\nRunning tests...\n\n`));
+        dispatch(setConsoleOutput(`> ${execLabel}\n\n`));
         dispatch(setDiagnostics([]));
 
-        try {
-            const res  = await fetch(getTestEndpoint(documentId), {
-                method:  "POST",
-                headers: {
-                    "Content-Type": "application/json",
-                    ...(tokenRef.current ? { Authorization: `Bearer ${tokenRef.current}` } : {}),
-                },
-                body: JSON.stringify({ files: projectFiles }),
-            });
-            const data = await res.json();
-            const output = data.success
-                ? data.stdout + (data.stderr ? "\n" + data.stderr : "")
-                : (data.stderr || data.stdout || "Unknown testing error");
-            dispatch(appendConsoleOutput(output));
-            dispatch(setDiagnostics(parseRustOutput(output)));
-        } catch (err) {
-            dispatch(appendConsoleOutput("\nПомилка з'єднання з сервером: " + err.message));
-        } finally {
-            dispatch(setIsRunning(false));
-            setTimeout(() => {
-                document.querySelector(".console-body")?.scrollTo(0, 999999);
-            }, 50);
+        await startRun("test");
+    }, [documentId, isRunning, startRun, dispatch]);
+
+    // ── stdin інтерактивного запуску ──────────────────────────────────────────
+    const sendRunInput = useCallback((line) => {
+        if (!runIdRef.current) return;
+        dispatch(appendConsoleOutput(line + "\n"));
+        sendCommand({ type: "run_stdin", run_id: runIdRef.current, data: line + "\n" });
+    }, [sendCommand, dispatch]);
+
+    const closeRunInput = useCallback(() => {
+        if (!runIdRef.current) return;
+        sendCommand({ type: "run_stdin_close", run_id: runIdRef.current });
+        dispatch(setRunStdinOpen(false));
+    }, [sendCommand, dispatch]);
+
+    // Скасувати може автор запуску або Manager — інакше сервер відповість помилкою
+    const cancelRun = useCallback(() => {
+        if (runIdRef.current) {
+            sendCommand({ type: "run_cancel", run_id: runIdRef.current });
+            return;
         }
-    }, [documentId, isRunning, dispatch]);
+        if (!restRunIdRef.current) return;
+        // Результат скасування прийде відповіддю самого запуску зі статусом cancelled
+        fetch(getCancelRunEndpoint(documentId, restRunIdRef.current), {
+            method:  "POST",
+            headers: tokenRef.current ? { Authorization: `Bearer ${tokenRef.current}` } : {},
+        }).catch(() => {});
+    }, [documentId, sendCommand]);
 
     // ── Форматування ──────────────────────────────────────────────────────────
     const formatCode = useCallback(async () => {
@@ -668,10 +1005,21 @@ export function useCollabEditor(documentId, isDark) {
         isRunning,
         runCode,
         runTests,
+        sendRunInput,
+        closeRunInput,
+        cancelRun,
         formatCode,
         clearConsole,
         sendFsEvent,
         sendRoleChange,
+        sendChatMessage,
+        editChatMessage,
+        deleteChatMessage,
+        loadOlderChat,
+        sendFollow,
+        sendUnfollow,
+        requestEditAccess,
+        resolveAccessRequest,
     };
 }
 
@@ -779,7 +1127,8 @@ function buildExtensions(
                 updateStats(update.state.doc.toString());
             }
             if (update.selectionSet) {
-                onCursorMove?.(update.state.selection.main.head);
+                const { head, anchor } = update.state.selection.main;
+                onCursorMove?.(head, anchor);
             }
         }),
     ];
@@ -1,5 +1,5 @@
 import React, { useState, useEffect, useRef } from "react";
-import { useNavigate } from "react-router-dom";
+import { useNavigate, useLocation } from "react-router-dom";
 import { useDispatch } from "react-redux";
 import { setAuth } from "../store/slices/authSlice.js";
 import { authLoginEndpoint, authRegisterEndpoint } from "../configs/paths.js";
@@ -155,6 +155,7 @@ export function AuthPage() {
     const cardRef = useRef(null);
     const dispatch = useDispatch();
     const navigate = useNavigate();
+    const location = useLocation();
 
     // ── 3D tilt on mouse move ────────────────────────────────────────────────
     useEffect(() => {
@@ -223,7 +224,8 @@ export function AuthPage() {
             setSuccess(true);
             setTimeout(() => {
                 dispatch(setAuth(data));
-                navigate("/");
+                // Повертаємо на сторінку, з якої перенаправили на вхід (наприклад, посилання-запрошення)
+                navigate(location.state?.from ?? "/");
             }, 600);
         } catch (err) {
             setError("Помилка підключення до сервера");
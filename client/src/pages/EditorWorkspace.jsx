@@ -6,19 +6,21 @@ import { useToast } from "../components/Toast.jsx";
 import { FileExplorer } from "../components/FileExplorer.jsx";
 import { FileTabs } from "../components/FileTabs.jsx";
 import { CollaboratorsPanel } from "../components/CollaboratorsPanel.jsx";
+import { ChatPanel } from "../components/ChatPanel.jsx";
 import { Breadcrumb } from "../components/Breadcrumb.jsx";
 import { OutlinePanel } from "../components/OutlinePanel.jsx";
 import { ProblemsPanel } from "../components/ProblemsPanel.jsx";
 import { Minimap } from "../components/Minimap.jsx";
 import { closeTab, setConsoleVisible } from "../store/slices/collabSlice.js";
 import { ansiToHtml } from "../utils/ansiToHtml.js";
-import { getExportEndpoint } from "../configs/paths.js";
+import { getExportEndpoint, getGitBundleEndpoint } from "../configs/paths.js";
 
 const ACTIVITY_TABS = [
     { id: "explorer",      icon: "folder_open", label: "Провідник" },
     { id: "outline",       icon: "view_list",   label: "Структура" },
     { id: "control",       icon: "terminal",    label: "Команди" },
     { id: "collaborators", icon: "group",       label: "Колаборанти" },
+    { id: "chat",          icon: "forum",       label: "Чат" },
 ];
 
 const LS_SIDEBAR_W  = "co-write:sidebar-width";
@@ -35,11 +37,18 @@ export function EditorWorkspace({ isDark }) {
     const token      = useSelector((s) => s.auth.token);
     const activeFile = useSelector((s) => s.collab.activeFile);
     const diagnostics = useSelector((s) => s.collab.diagnostics);
+    const runStdinOpen = useSelector((s) => s.collab.runStdinOpen);
+    const myRole       = useSelector((s) => s.collab.myRole);
+    const runsEditorsOnly = useSelector((s) => s.collab.runsEditorsOnly);
+    // Менеджер може обмежити запуски коду редакторами та керівниками
+    const runLocked = runsEditorsOnly && myRole === "reader";
+    const runTitle  = runLocked ? "Запускати код можуть лише редактори та керівники" : undefined;
 
     // ── UI state ──────────────────────────────────────────────────────────────
     const [activeTab,         setActiveTab]         = useState("explorer");
     const [shortcutsOpen,     setShortcutsOpen]     = useState(false);
     const [consoleTab,        setConsoleTab]         = useState("output");  // "output" | "problems"
+    const [stdinLine,         setStdinLine]          = useState("");
     const [minimapVisible,    setMinimapVisible]     = useState(
         () => localStorage.getItem(LS_MINIMAP) !== "false"
     );
@@ -76,10 +85,21 @@ export function EditorWorkspace({ isDark }) {
         isRunning,
         runCode,
         runTests,
+        sendRunInput,
+        closeRunInput,
+        cancelRun,
         formatCode,
         clearConsole,
         sendFsEvent,
         sendRoleChange,
+        sendChatMessage,
+        editChatMessage,
+        deleteChatMessage,
+        loadOlderChat,
+        sendFollow,
+        sendUnfollow,
+        requestEditAccess,
+        resolveAccessRequest,
     } = useCollabEditor(documentId, isDark);
 
     // ── Авто-перемикання на Problems після збірки з помилками ─────────────────
@@ -179,6 +199,27 @@ export function EditorWorkspace({ isDark }) {
         }
     };
 
+    const handleExportGit = async () => {
+        if (!token) return;
+        try {
+            const res = await fetch(getGitBundleEndpoint(documentId), {
+                method:  "POST",
+                headers: { Authorization: `Bearer ${token}` },
+            });
+            if (!res.ok) { showToast("Помилка експорту git bundle"); return; }
+            const blob = await res.blob();
+            const url  = URL.createObjectURL(blob);
+            const a    = document.createElement("a");
+            a.href     = url;
+            a.download = `${docTitle || "project"}.bundle`;
+            a.click();
+            URL.revokeObjectURL(url);
+            showToast("Git bundle завантажено!");
+        } catch {
+            showToast("Помилка підключення при експорті");
+        }
+    };
+
     const handleCopyLink = () => {
         navigator.clipboard
             .writeText(window.location.href)
@@ -260,11 +301,11 @@ export function EditorWorkspace({ isDark }) {
                             <p className="ed-section-comment">{"// виконання"}</p>
                             <div className="action-buttons">
                                 <div className="run-buttons-row">
-                                    <button id="runCode" className="btn success" onClick={runCode} disabled={isRunning}>
+                                    <button id="runCode" className="btn success" onClick={runCode} disabled={isRunning || runLocked} title={runTitle}>
                                         <span className="ed-dollar">{isRunning ? "›" : "$"}</span>
                                         <span>{isRunning ? "running…" : "cargo run"}</span>
                                     </button>
-                                    <button id="runTests" className="btn info" onClick={runTests} disabled={isRunning}>
+                                    <button id="runTests" className="btn info" onClick={runTests} disabled={isRunning || runLocked} title={runTitle}>
                                         <span className="ed-dollar">$</span>
                                         <span>cargo test</span>
                                     </button>
@@ -278,6 +319,10 @@ export function EditorWorkspace({ isDark }) {
                                     <span className="ed-dollar">↓</span>
                                     <span>export .tar.xz</span>
                                 </button>
+                                <button id="exportGitBundle" className="btn" onClick={handleExportGit}>
+                                    <span className="ed-dollar">↓</span>
+                                    <span>export .bundle</span>
+                                </button>
                                 <button id="copyLink" className="btn" onClick={handleCopyLink}>
                                     <span className="ed-dollar">@</span>
                                     <span>share link</span>
@@ -295,6 +340,21 @@ export function EditorWorkspace({ isDark }) {
                             <CollaboratorsPanel
                                 docId={documentId}
                                 onChangeRole={(connId, newRole) => sendRoleChange(connId, newRole)}
+                                onFollow={sendFollow}
+                                onUnfollow={sendUnfollow}
+                                onRequestAccess={requestEditAccess}
+                                onResolveAccessRequest={resolveAccessRequest}
+                            />
+                        </div>
+                    )}
+
+                    {activeTab === "chat" && (
+                        <div className="ed-panel-anim" style={{ display: "flex", flexDirection: "column", flex: 1, minHeight: 0 }}>
+                            <ChatPanel
+                                onSend={sendChatMessage}
+                                onEdit={editChatMessage}
+                                onDelete={deleteChatMessage}
+                                onLoadOlder={loadOlderChat}
                             />
                         </div>
                     )}
@@ -352,6 +412,11 @@ export function EditorWorkspace({ isDark }) {
                                 </button>
                             </div>
                             <div className="console-actions">
+                                {isRunning && (
+                                    <button className="console-btn" title="Зупинити запуск" onClick={cancelRun}>
+                                        <span className="material-icons">stop</span>
+                                    </button>
+                                )}
                                 <button className="console-btn" title="Очистити" onClick={clearConsole}>
                                     <span className="material-icons">delete_sweep</span>
                                 </button>
@@ -367,11 +432,44 @@ export function EditorWorkspace({ isDark }) {
 
                         <div className="console-body">
                             {consoleTab === "output" ? (
-                                <pre
-                                    id="consoleOutput"
-                                    className="console-output"
-                                    dangerouslySetInnerHTML={{ __html: consoleHtml }}
-                                />
+                                <>
+                                    <pre
+                                        id="consoleOutput"
+                                        className="console-output"
+                                        dangerouslySetInnerHTML={{ __html: consoleHtml }}
+                                    />
+                                    {runStdinOpen && (
+                                        <form
+                                            className="console-stdin"
+                                            onSubmit={(e) => {
+                                                e.preventDefault();
+                                                sendRunInput(stdinLine);
+                                                setStdinLine("");
+                                            }}
+                                        >
+                                            <span className="console-stdin-prompt">stdin&gt;</span>
+                                            <input
+                                                className="console-stdin-input"
+                                                value={stdinLine}
+                                                autoFocus
+                                                spellCheck={false}
+                                                placeholder="Введіть рядок і натисніть Enter (Ctrl+D — кінець вводу)"
+                                                onChange={(e) => setStdinLine(e.target.value)}
+                                                onKeyDown={(e) => {
+                                                    if (e.ctrlKey && e.key === "d") {
+                                                        e.preventDefault();
+                                                        if (stdinLine) sendRunInput(stdinLine);
+                                                        setStdinLine("");
+                                                        closeRunInput();
+                                                    }
+                                                }}
+                                            />
+                                            <button type="button" className="console-btn" title="Кінець вводу (EOF)" onClick={closeRunInput}>
+                                                <span className="material-icons">keyboard_return</span>
+                                            </button>
+                                        </form>
+                                    )}
+                                </>
                             ) : (
                                 <ProblemsPanel cmViewRef={cmViewRef} />
                             )}
@@ -0,0 +1,53 @@
+import React, { useEffect, useRef, useState } from "react";
+import { useNavigate, useParams } from "react-router-dom";
+import { useSelector } from "react-redux";
+import { acceptInviteEndpoint } from "../configs/paths.js";
+import { useToast } from "../components/Toast.jsx";
+
+/** Приймає посилання-запрошення та відкриває проєкт. */
+export function InviteAccept() {
+    const { token: inviteToken } = useParams();
+    const token = useSelector((s) => s.auth.token);
+    const navigate = useNavigate();
+    const showToast = useToast();
+    const [error, setError] = useState("");
+    // StrictMode викликає ефект двічі — не витрачаємо зайве використання запрошення
+    const requestedRef = useRef(false);
+
+    useEffect(() => {
+        if (requestedRef.current) return;
+        requestedRef.current = true;
+
+        (async () => {
+            try {
+                const res = await fetch(acceptInviteEndpoint(inviteToken), {
+                    method: "POST",
+                    headers: { Authorization: `Bearer ${token}` },
+                });
+                if (!res.ok) {
+                    const msg = await res.text();
+                    setError(msg || "Запрошення недійсне");
+                    return;
+                }
+                const { document_id } = await res.json();
+                showToast("Ви приєдналися до проєкту");
+                navigate(`/editor?id=${document_id}`, { replace: true });
+            } catch {
+                setError("Помилка підключення до сервера");
+            }
+        })();
+    }, [inviteToken, token, navigate, showToast]);
+
+    return (
+        <div style={{ display: "flex", flexDirection: "column", alignItems: "center", justifyContent: "center", minHeight: "100vh", gap: 16 }}>
+            {error ? (
+                <>
+                    <p>{error}</p>
+                    <button className="btn primary" onClick={() => navigate("/")}>До списку проєктів</button>
+                </>
+            ) : (
+                <p>Приєднання до проєкту…</p>
+            )}
+        </div>
+    );
+}
@@ -12,6 +12,10 @@ const initialState = {
     consoleOutput: "",
     consoleVisible: false,
     isRunning: false,
+    // Чи приймає поточний запуск stdin з консолі
+    runStdinOpen: false,
+    // Чи можуть запускати код лише Editor та Manager
+    runsEditorsOnly: false,
     // Список відкритих вкладок (незалежний від файлової системи)
     openTabs: ["src/main.rs"],
     // Розібрані помилки/попередження після збірки
@@ -20,11 +24,19 @@ const initialState = {
     collaborators: [], // [{ connId, userId, username, role, color, cursor, isMe }]
     myRole: null,
     myConnId: null,
+    // conn_id учасника, за яким стежимо в режимі follow
+    followLeader: null,
     // Файловий проект
     files: {},
     activeFile: "src/main.rs",
     // Git статуси файлів (потребує backend endpoint)
     gitStatus: {}, // { "src/main.rs": "M" }
+    // Чат кімнати (від старіших до новіших)
+    chatMessages: [], // [{ id, author_id, author_username, body, mentions, created_at, edited_at }]
+    chatHasMore: false,
+    // Запити на право редагування: нерозглянуті (для Manager) та власний запит (для Reader)
+    accessRequests: [], // [{ id, user_id, username, message, status, created_at }]
+    accessRequestPending: false,
 };
 
 const collabSlice = createSlice({
@@ -54,6 +66,13 @@ const collabSlice = createSlice({
         },
         setIsRunning: (state, action) => {
             state.isRunning = action.payload;
+            if (!action.payload) state.runStdinOpen = false;
+        },
+        setRunStdinOpen: (state, action) => {
+            state.runStdinOpen = action.payload;
+        },
+        setRunsEditorsOnly: (state, action) => {
+            state.runsEditorsOnly = action.payload;
         },
         // ── Діагностика ──────────────────────────────────────────────────────────
         setDiagnostics: (state, action) => {
@@ -150,12 +169,19 @@ const collabSlice = createSlice({
             state.consoleOutput = "";
             state.consoleVisible = false;
             state.isRunning = false;
+            state.runStdinOpen = false;
+            state.runsEditorsOnly = false;
             state.openTabs = ["src/main.rs"];
             state.diagnostics = [];
             state.collaborators = [];
             state.myRole = null;
             state.myConnId = null;
+            state.followLeader = null;
             state.gitStatus = {};
+            state.chatMessages = [];
+            state.chatHasMore = false;
+            state.accessRequests = [];
+            state.accessRequestPending = false;
         },
         // ── Колаборанти ──────────────────────────────────────────────────────────
         setCollaborators: (state, action) => {
@@ -175,7 +201,10 @@ const collabSlice = createSlice({
                 role: p.role,
                 color: COLORS[i % COLORS.length],
                 isMe: p.conn_id === myConnId,
-                cursor: existingCursors[p.conn_id] ?? null,
+                cursor: p.cursor ?? existingCursors[p.conn_id] ?? null,
+                activeFile: p.active_file ?? null,
+                idle: !!p.idle,
+                following: p.following ?? null,
             }));
             if (myConnId) {
                 state.myConnId = myConnId;
@@ -191,10 +220,12 @@ const collabSlice = createSlice({
         },
         /** Оновлює позицію курсора конкретного колаборанта. */
         updateCollaboratorCursor: (state, action) => {
-            const { connId, path, position } = action.payload;
+            const { connId, path, position, selection = null } = action.payload;
             const collab = state.collaborators.find(c => c.connId === connId);
             if (collab) {
-                collab.cursor = { path, position };
+                collab.cursor = { path, position, selection };
+                if (path) collab.activeFile = path;
+                collab.idle = false;
             }
         },
         setMyRole: (state, action) => {
@@ -203,9 +234,51 @@ const collabSlice = createSlice({
         setMyConnId: (state, action) => {
             state.myConnId = action.payload;
         },
+        setFollowLeader: (state, action) => {
+            state.followLeader = action.payload;
+        },
         setGitStatus: (state, action) => {
             state.gitStatus = action.payload;
         },
+        // ── Чат ──────────────────────────────────────────────────────────────────
+        /** Сторінка історії: без `older` замінює список (новий join), інакше додається на початок. */
+        setChatHistory: (state, action) => {
+            const { messages, hasMore, older } = action.payload;
+            if (older) {
+                const known = new Set(state.chatMessages.map(m => m.id));
+                state.chatMessages = [...messages.filter(m => !known.has(m.id)), ...state.chatMessages];
+            } else {
+                state.chatMessages = messages;
+            }
+            state.chatHasMore = hasMore;
+        },
+        addChatMessage: (state, action) => {
+            if (!state.chatMessages.some(m => m.id === action.payload.id)) {
+                state.chatMessages.push(action.payload);
+            }
+        },
+        updateChatMessage: (state, action) => {
+            const idx = state.chatMessages.findIndex(m => m.id === action.payload.id);
+            if (idx !== -1) state.chatMessages[idx] = action.payload;
+        },
+        removeChatMessage: (state, action) => {
+            state.chatMessages = state.chatMessages.filter(m => m.id !== action.payload);
+        },
+        // ── Запити на право редагування ──────────────────────────────────────────
+        setAccessRequests: (state, action) => {
+            state.accessRequests = action.payload;
+        },
+        addAccessRequest: (state, action) => {
+            if (!state.accessRequests.some(r => r.id === action.payload.id)) {
+                state.accessRequests.push(action.payload);
+            }
+        },
+        removeAccessRequest: (state, action) => {
+            state.accessRequests = state.accessRequests.filter(r => r.id !== action.payload);
+        },
+        setAccessRequestPending: (state, action) => {
+            state.accessRequestPending = action.payload;
+        },
     }
 });
 
@@ -218,6 +291,8 @@ export const {
     appendConsoleOutput,
     setConsoleVisible,
     setIsRunning,
+    setRunStdinOpen,
+    setRunsEditorsOnly,
     setDiagnostics,
     openTab,
     closeTab,
@@ -232,7 +307,16 @@ export const {
     updateCollaboratorCursor,
     setMyRole,
     setMyConnId,
+    setFollowLeader,
     setGitStatus,
+    setChatHistory,
+    addChatMessage,
+    updateChatMessage,
+    removeChatMessage,
+    setAccessRequests,
+    addAccessRequest,
+    removeAccessRequest,
+    setAccessRequestPending,
 } = collabSlice.actions;
 
 export default collabSlice.reducer;
@@ -0,0 +1,8 @@
+DELETE FROM project_files WHERE deleted_at IS NOT NULL;
+DROP INDEX IF EXISTS project_files_trash_idx;
+DROP INDEX IF EXISTS project_files_live_path_idx;
+ALTER TABLE project_files ADD CONSTRAINT project_files_document_id_path_key UNIQUE (document_id, path);
+ALTER TABLE project_files
+    DROP COLUMN IF EXISTS trash_id,
+    DROP COLUMN IF EXISTS deleted_by,
+    DROP COLUMN IF EXISTS deleted_at;
@@ -0,0 +1,12 @@
+ALTER TABLE project_files
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN trash_id   UUID;
+
+-- Шлях унікальний лише серед "живих" файлів: у кошику може лежати кілька версій одного шляху
+ALTER TABLE project_files DROP CONSTRAINT project_files_document_id_path_key;
+CREATE UNIQUE INDEX project_files_live_path_idx
+    ON project_files (document_id, path) WHERE deleted_at IS NULL;
+
+CREATE INDEX project_files_trash_idx
+    ON project_files (document_id, trash_id) WHERE deleted_at IS NOT NULL;
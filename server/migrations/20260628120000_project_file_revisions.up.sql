@@ -0,0 +1,23 @@
+CREATE TABLE project_file_revisions (
+    id          UUID        PRIMARY KEY DEFAULT uuidv7(),
+    document_id UUID        NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    -- Ідентичність файлу зберігається при перейменуваннях, тому історія йде за file_id, а не за шляхом
+    file_id     UUID        NOT NULL REFERENCES project_files(id) ON DELETE CASCADE,
+    path        TEXT        NOT NULL,
+    old_path    TEXT,
+    content     TEXT        NOT NULL,
+    kind        TEXT        NOT NULL CHECK (kind IN ('edit', 'rename', 'delete', 'restore')),
+    author_id   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX project_file_revisions_file_idx
+    ON project_file_revisions (file_id, created_at);
+CREATE INDEX project_file_revisions_path_idx
+    ON project_file_revisions (document_id, path);
+
+-- Поточний стан існуючих файлів стає першою ревізією їхньої історії
+INSERT INTO project_file_revisions (document_id, file_id, path, content, kind, created_at)
+SELECT document_id, id, path, content, 'edit', updated_at
+FROM project_files
+WHERE NOT is_dir AND deleted_at IS NULL;
@@ -0,0 +1,14 @@
+CREATE TABLE chat_messages (
+    id          UUID        PRIMARY KEY DEFAULT uuidv7(),
+    document_id UUID        NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    author_id   UUID        REFERENCES users(id) ON DELETE SET NULL,
+    body        TEXT        NOT NULL,
+    -- Учасники документа, згадані в повідомленні через @username
+    mentions    UUID[]      NOT NULL DEFAULT '{}',
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    edited_at   TIMESTAMPTZ
+);
+
+-- uuidv7 впорядковані за часом, тому сторінки історії беруться за id
+CREATE INDEX chat_messages_document_idx
+    ON chat_messages (document_id, id DESC);
@@ -0,0 +1,16 @@
+CREATE TABLE document_invites (
+    id          UUID        PRIMARY KEY DEFAULT uuidv7(),
+    document_id UUID        NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    token       TEXT        NOT NULL UNIQUE,
+    role        TEXT        NOT NULL CHECK (role IN ('reader', 'editor', 'manager')),
+    -- NULL — без обмеження кількості використань
+    max_uses    INTEGER     CHECK (max_uses > 0),
+    uses        INTEGER     NOT NULL DEFAULT 0,
+    expires_at  TIMESTAMPTZ,
+    revoked_at  TIMESTAMPTZ,
+    created_by  UUID        REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX document_invites_document_idx
+    ON document_invites (document_id, created_at);
@@ -0,0 +1,15 @@
+CREATE TABLE access_requests (
+    id          UUID        PRIMARY KEY DEFAULT uuidv7(),
+    document_id UUID        NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    user_id     UUID        NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    message     TEXT,
+    status      TEXT        NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'denied')),
+    resolved_by UUID        REFERENCES users(id) ON DELETE SET NULL,
+    resolved_at TIMESTAMPTZ,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Один користувач може мати лише один нерозглянутий запит на документ
+CREATE UNIQUE INDEX access_requests_pending_idx
+    ON access_requests (document_id, user_id)
+    WHERE status = 'pending';
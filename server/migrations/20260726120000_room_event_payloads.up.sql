@@ -0,0 +1,10 @@
+-- Події кімнат, що не вміщуються в NOTIFY (до 8000 байт): сповіщення містить лише id запису.
+-- Записи потрібні лише кілька хвилин, тому таблиця не журналюється.
+CREATE UNLOGGED TABLE room_event_payloads (
+    id          UUID        PRIMARY KEY DEFAULT uuidv7(),
+    document_id UUID        NOT NULL,
+    payload     TEXT        NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX room_event_payloads_created_at_idx ON room_event_payloads (created_at);
@@ -0,0 +1,2 @@
+-- Хто може запускати код у документі: false — усі учасники, true — лише Editor та Manager
+ALTER TABLE documents ADD COLUMN runs_editors_only BOOLEAN NOT NULL DEFAULT false;
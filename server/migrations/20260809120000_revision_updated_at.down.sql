@@ -0,0 +1 @@
+ALTER TABLE project_file_revisions DROP COLUMN IF EXISTS updated_at;
@@ -0,0 +1,4 @@
+-- Час останнього злиття правок у ревізію; created_at лишається часом її створення,
+-- тож вікно злиття відраховується від початку ревізії, а не зсувається з кожною правкою
+ALTER TABLE project_file_revisions ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+UPDATE project_file_revisions SET updated_at = created_at;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS ws_tickets;
+DROP TABLE IF EXISTS room_presence;
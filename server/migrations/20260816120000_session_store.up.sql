@@ -0,0 +1,21 @@
+-- Присутність учасників кімнат і квитки WebSocket для розгортань без Redis.
+-- Стан короткоживучий і відновлюється heartbeat-ом, тому таблиці не журналюються.
+CREATE UNLOGGED TABLE room_presence (
+    document_id UUID        NOT NULL,
+    conn_id     UUID        NOT NULL,
+    info        TEXT        NOT NULL,
+    expires_at  TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (document_id, conn_id)
+);
+
+CREATE INDEX room_presence_expires_at_idx ON room_presence (expires_at);
+
+CREATE UNLOGGED TABLE ws_tickets (
+    ticket      TEXT        PRIMARY KEY,
+    document_id UUID        NOT NULL,
+    user_id     UUID        NOT NULL,
+    username    TEXT        NOT NULL,
+    expires_at  TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX ws_tickets_expires_at_idx ON ws_tickets (expires_at);
@@ -0,0 +1,6 @@
+DELETE FROM project_file_revisions r
+WHERE NOT EXISTS (SELECT 1 FROM project_files f WHERE f.id = r.file_id);
+
+ALTER TABLE project_file_revisions
+    ADD CONSTRAINT project_file_revisions_file_id_fkey
+    FOREIGN KEY (file_id) REFERENCES project_files(id) ON DELETE CASCADE;
@@ -0,0 +1,4 @@
+-- Остаточне видалення файлу з кошика не повинне стирати його ревізії: з них будується
+-- історія файлу та git-історія проекту, і вже експортовані коміти мають лишатися незмінними.
+-- file_id лишається ідентичністю файлу в історії, навіть коли рядка в project_files уже немає.
+ALTER TABLE project_file_revisions DROP CONSTRAINT project_file_revisions_file_id_fkey;
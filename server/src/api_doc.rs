@@ -2,6 +2,7 @@ use utoipa::OpenApi;
 
 use crate::app::domains::document;
 use crate::app::domains::execution;
+use crate::app::domains::git;
 use crate::app::domains::lsp;
 
 
@@ -19,7 +20,12 @@ use crate::app::domains::lsp;
         document::controller::get_document_title,
         execution::controller::execute_code,
         execution::controller::execute_tests,
+        execution::controller::cancel_run,
+        execution::controller::get_run_queue_position,
         execution::controller::format_code,
+        git::controller::export_git_bundle,
+        git::controller::info_refs,
+        git::controller::upload_pack,
         lsp::controller::complete,
         lsp::controller::hover,
 
@@ -28,7 +34,8 @@ use crate::app::domains::lsp;
     ),
     components(
         schemas(
-            execution::models::ExecutionResponse
+            execution::models::ExecutionResponse,
+            execution::models::RunQueueResponse
         )
     )
 )]
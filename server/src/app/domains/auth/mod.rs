@@ -4,6 +4,7 @@ pub mod repository;
 pub mod service;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
 pub use controller::{login, register};
@@ -0,0 +1,108 @@
+use dashmap::DashSet;
+use futures_util::future::{BoxFuture, FutureExt as _};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use super::{Broker, BrokerMetrics};
+use crate::app::domains::document::fanout;
+use crate::app::{RequestResult, ServiceContext};
+use crate::constants::document::MEMORY_BROKER_CAPACITY;
+use crate::core::app_data::AppData;
+
+/// Спільна шина подій для реплік, що працюють в одному процесі.
+///
+/// Одновузловому розгортанню достатньо однієї шини; тести можуть передати ту саму шину
+/// кільком екземплярам `AppData`, щоб перевірити розсилку між «репліками» без Redis.
+#[derive(Clone)]
+pub struct MemoryHub {
+    sender: broadcast::Sender<(Uuid, Arc<[u8]>)>,
+}
+
+impl Default for MemoryHub {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(MEMORY_BROKER_CAPACITY);
+        Self { sender }
+    }
+}
+
+/// Брокер подій кімнат у пам'яті процесу.
+pub struct MemoryBroker {
+    hub: MemoryHub,
+    rooms: Arc<DashSet<Uuid>>,
+    running: Arc<AtomicBool>,
+}
+
+impl MemoryBroker {
+    /// Створює брокер, підключений до шини `hub`.
+    pub fn new(hub: &MemoryHub) -> Self {
+        Self {
+            hub: hub.clone(),
+            rooms: Arc::default(),
+            running: Arc::default(),
+        }
+    }
+}
+
+impl Broker for MemoryBroker {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn publish(&self, doc_id: Uuid, payload: Vec<u8>) -> BoxFuture<'_, RequestResult<()>> {
+        // Помилка означає лише, що жодна репліка зараз не отримує події
+        let _ = self.hub.sender.send((doc_id, payload.into()));
+        futures_util::future::ready(Ok(())).boxed()
+    }
+
+    fn subscribe(&self, doc_id: Uuid) {
+        self.rooms.insert(doc_id);
+    }
+
+    fn run(&self, app_data: &AppData) {
+        let cancel_token = app_data.token().child_token();
+        let app_data = app_data.clone();
+        let rooms = self.rooms.clone();
+        let running = self.running.clone();
+        let mut receiver = self.hub.sender.subscribe();
+
+        actix_rt::spawn(async move {
+            let ctx = ServiceContext::from(&app_data);
+            running.store(true, Ordering::Relaxed);
+
+            loop {
+                let received = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    received = receiver.recv() => received,
+                };
+                match received {
+                    Ok((doc_id, payload)) => {
+                        if !rooms.contains(&doc_id) {
+                            continue;
+                        }
+                        if app_data.rooms.is_empty(&doc_id) {
+                            rooms.remove(&doc_id);
+                            continue;
+                        }
+                        fanout::deliver(&ctx, doc_id, &payload).await;
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Отримувач подій кімнат відстав і пропустив {skipped} подій");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+
+            running.store(false, Ordering::Relaxed);
+        });
+    }
+
+    fn metrics(&self) -> BrokerMetrics {
+        BrokerMetrics {
+            subscriptions: self.rooms.len(),
+            connected: self.running.load(Ordering::Relaxed),
+            reconnects: 0,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+pub mod memory;
+pub mod postgres;
+pub mod redis_streams;
+
+pub use memory::{MemoryBroker, MemoryHub};
+pub use postgres::PostgresBroker;
+pub use redis_streams::RedisStreamsBroker;
+
+use futures_util::future::BoxFuture;
+use uuid::Uuid;
+
+use crate::app::RequestResult;
+use crate::core::app_data::AppData;
+
+/// Транспорт подій кімнат між репліками сервера.
+///
+/// Брокер лише передає серіалізовані `RoomEvent`: локальну доставку, пропуск власних подій
+/// та виключення ініціатора виконує `fanout`, тож реалізації не залежать від протоколу кімнат.
+pub trait Broker: Send + Sync {
+    /// Назва реалізації для логів та метрик.
+    fn name(&self) -> &'static str;
+
+    /// Передає подію кімнати іншим реплікам.
+    fn publish(&self, doc_id: Uuid, payload: Vec<u8>) -> BoxFuture<'_, RequestResult<()>>;
+
+    /// Починає отримувати події кімнати, до якої підключився перший локальний клієнт.
+    fn subscribe(&self, doc_id: Uuid);
+
+    /// Запускає фонове отримання подій; кожна передається в `fanout::deliver`.
+    fn run(&self, app_data: &AppData);
+
+    /// Стан отримувача подій.
+    fn metrics(&self) -> BrokerMetrics;
+}
+
+/// Лічильники отримувача подій брокера.
+#[derive(Default, Debug)]
+pub struct BrokerMetrics {
+    /// Кількість кімнат, події яких отримує репліка.
+    pub subscriptions: usize,
+    /// Чи є зараз з'єднання з транспортом.
+    pub connected: bool,
+    /// Скільки разів отримувач перепідключався після розриву.
+    pub reconnects: u64,
+}
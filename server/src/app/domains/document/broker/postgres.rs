@@ -0,0 +1,271 @@
+use dashmap::DashSet;
+use futures_util::future::{BoxFuture, FutureExt as _};
+use sqlx::PgPool;
+use sqlx::postgres::{PgListener, PgNotification};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::time;
+use uuid::Uuid;
+
+use super::{Broker, BrokerMetrics};
+use crate::app::domains::document::fanout::{self, next_backoff};
+use crate::app::domains::document::repository;
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::constants::document::{
+    ROOM_EVENT_PAYLOAD_PURGE_SECONDS, ROOM_EVENT_PAYLOAD_TTL_SECONDS, ROOM_NOTIFY_CHANNEL, ROOM_NOTIFY_MAX_BYTES,
+};
+use crate::core::app_data::AppData;
+
+/// Вміст сповіщення в каналі подій кімнат: `<doc_id> <json>` або `<doc_id> @<id>`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Notification {
+    /// Подія повністю міститься в сповіщенні.
+    Inline { doc_id: Uuid, payload: String },
+    /// Подія завелика для `NOTIFY` і збережена в `room_event_payloads`.
+    Stored { doc_id: Uuid, id: Uuid },
+}
+
+impl Notification {
+    /// Чи вміститься подія в сповіщення разом з id документа.
+    pub(crate) fn fits_inline(payload: &str) -> bool {
+        Uuid::nil().to_string().len() + 1 + payload.len() < ROOM_NOTIFY_MAX_BYTES
+    }
+
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let (doc_id, rest) = text.split_once(' ')?;
+        let doc_id = doc_id.parse().ok()?;
+        match rest.strip_prefix('@') {
+            Some(id) => Some(Self::Stored { doc_id, id: id.parse().ok()? }),
+            None => Some(Self::Inline { doc_id, payload: rest.to_string() }),
+        }
+    }
+
+    fn doc_id(&self) -> Uuid {
+        match self {
+            Self::Inline { doc_id, .. } | Self::Stored { doc_id, .. } => *doc_id,
+        }
+    }
+}
+
+impl Display for Notification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Inline { doc_id, payload } => write!(f, "{doc_id} {payload}"),
+            Self::Stored { doc_id, id } => write!(f, "{doc_id} @{id}"),
+        }
+    }
+}
+
+/// Брокер на Postgres `LISTEN/NOTIFY` для розгортань без Redis.
+///
+/// Сповіщення не зберігаються, тож події, надіслані під час розриву з'єднання отримувача,
+/// втрачаються — на відміну від Redis Streams, повторного читання тут немає.
+pub struct PostgresBroker {
+    state: Arc<ListenerState>,
+}
+
+impl PostgresBroker {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            state: Arc::new(ListenerState {
+                pool,
+                rooms: DashSet::new(),
+                connected: AtomicBool::new(false),
+                reconnects: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl Broker for PostgresBroker {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    /// Надсилає подію в канал `ROOM_NOTIFY_CHANNEL`; завелику подію спершу зберігає в таблиці.
+    fn publish(&self, doc_id: Uuid, payload: Vec<u8>) -> BoxFuture<'_, RequestResult<()>> {
+        async move {
+            let pool = &self.state.pool;
+            let payload = String::from_utf8(payload)
+                .map_err(|e| RequestError::internal_server_error(format!("Подія кімнати не є UTF-8: {e}")))?;
+
+            let notification = if Notification::fits_inline(&payload) {
+                Notification::Inline { doc_id, payload }
+            } else {
+                let id = repository::insert_room_event_payload(doc_id, &payload, pool).await?;
+                Notification::Stored { doc_id, id }
+            };
+            repository::notify_room_event(ROOM_NOTIFY_CHANNEL, &notification.to_string(), pool).await
+        }
+        .boxed()
+    }
+
+    fn subscribe(&self, doc_id: Uuid) {
+        self.state.rooms.insert(doc_id);
+    }
+
+    /// Запускає отримувача сповіщень та періодичне прибирання збережених подій.
+    ///
+    /// Після розриву з'єднання отримувач підключається знову з наростаючою паузою.
+    fn run(&self, app_data: &AppData) {
+        let cancel_token = app_data.token().child_token();
+        let app_data = app_data.clone();
+        let state = self.state.clone();
+
+        run_payload_purge(&app_data, state.pool.clone());
+
+        actix_rt::spawn(async move {
+            let ctx = ServiceContext::from(&app_data);
+            let mut backoff = Duration::ZERO;
+            let mut connected_before = false;
+
+            'reconnect: while !cancel_token.is_cancelled() {
+                if !backoff.is_zero() {
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = time::sleep(backoff) => {}
+                    }
+                }
+
+                let mut listener = match state.listen().await {
+                    Ok(listener) => listener,
+                    Err(err) => {
+                        backoff = next_backoff(backoff);
+                        tracing::warn!("Не вдалося підписатися на канал подій кімнат: {err}; повтор через {backoff:?}");
+                        continue;
+                    }
+                };
+                state.connected.store(true, Ordering::Relaxed);
+                if connected_before {
+                    state.reconnects.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!("Отримувач подій кімнат знову підписаний на {ROOM_NOTIFY_CHANNEL}");
+                }
+                connected_before = true;
+                backoff = Duration::ZERO;
+
+                loop {
+                    let received = tokio::select! {
+                        _ = cancel_token.cancelled() => break 'reconnect,
+                        received = listener.try_recv() => received,
+                    };
+                    match received {
+                        Ok(Some(notification)) => state.deliver(&ctx, notification.payload()).await,
+                        Ok(None) => {
+                            // PgListener сам перепідключається та відновлює підписку
+                            state.reconnects.fetch_add(1, Ordering::Relaxed);
+                            tracing::warn!("Втрачено з'єднання з каналом подій кімнат; події під час розриву втрачено");
+                        }
+                        Err(err) => {
+                            state.connected.store(false, Ordering::Relaxed);
+                            backoff = next_backoff(backoff);
+                            tracing::warn!("Помилка отримання подій кімнат: {err}; повтор через {backoff:?}");
+                            continue 'reconnect;
+                        }
+                    }
+                }
+            }
+
+            state.connected.store(false, Ordering::Relaxed);
+            tracing::info!("Отримувача подій кімнат зупинено");
+        });
+    }
+
+    fn metrics(&self) -> BrokerMetrics {
+        BrokerMetrics {
+            subscriptions: self.state.rooms.len(),
+            connected: self.state.connected.load(Ordering::Relaxed),
+            reconnects: self.state.reconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Стан брокера, спільний з фоновим отримувачем.
+struct ListenerState {
+    pool: PgPool,
+    /// Кімнати, в яких на цій репліці є підключення.
+    rooms: DashSet<Uuid>,
+    connected: AtomicBool,
+    reconnects: AtomicU64,
+}
+
+impl ListenerState {
+    /// Окреме з'єднання з пулу, підписане на канал подій кімнат.
+    async fn listen(&self) -> Result<ListenerGuard, sqlx::Error> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(ROOM_NOTIFY_CHANNEL).await?;
+        Ok(ListenerGuard(Some(listener)))
+    }
+
+    /// Доставляє подію локальним клієнтам, якщо в кімнаті ще є підключення.
+    async fn deliver(&self, ctx: &ServiceContext<'_>, text: &str) {
+        let Some(notification) = Notification::parse(text) else {
+            tracing::error!("Некоректне сповіщення в каналі {ROOM_NOTIFY_CHANNEL}");
+            return;
+        };
+        let doc_id = notification.doc_id();
+        if !self.rooms.contains(&doc_id) {
+            return;
+        }
+        if ctx.rooms.is_empty(&doc_id) {
+            self.rooms.remove(&doc_id);
+            return;
+        }
+
+        match notification {
+            Notification::Inline { payload, .. } => fanout::deliver(ctx, doc_id, payload.as_bytes()).await,
+            Notification::Stored { id, .. } => match repository::get_room_event_payload(id, &self.pool).await {
+                Ok(Some(payload)) => fanout::deliver(ctx, doc_id, payload.as_bytes()).await,
+                Ok(None) => tracing::warn!("Подію кімнати {doc_id} ({id}) вже прибрано"),
+                Err(err) => tracing::error!("Не вдалося прочитати подію кімнати {doc_id} ({id}): {err}"),
+            },
+        }
+    }
+}
+
+/// `PgListener`, що не панікує, коли його завдання знищується разом з рантаймом.
+///
+/// Під час зупинки сервера `PgListener` не може повернути з'єднання в пул без рантайму Tokio
+/// (sqlx#1389), тож у такому разі його просто забуваємо — процес однаково завершується.
+struct ListenerGuard(Option<PgListener>);
+
+impl ListenerGuard {
+    async fn try_recv(&mut self) -> Result<Option<PgNotification>, sqlx::Error> {
+        match self.0.as_mut() {
+            Some(listener) => listener.try_recv().await,
+            None => Err(sqlx::Error::PoolClosed),
+        }
+    }
+}
+
+impl Drop for ListenerGuard {
+    fn drop(&mut self) {
+        if let Some(listener) = self.0.take() {
+            if tokio::runtime::Handle::try_current().is_err() {
+                std::mem::forget(listener);
+            }
+        }
+    }
+}
+
+/// Періодично видаляє збережені великі події, які вже отримали всі репліки.
+fn run_payload_purge(app_data: &AppData, pool: PgPool) {
+    let cancel_token = app_data.token().child_token();
+    let interval = Duration::from_secs(ROOM_EVENT_PAYLOAD_PURGE_SECONDS);
+
+    actix_rt::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = time::sleep(interval) => {}
+            }
+
+            match repository::purge_room_event_payloads(ROOM_EVENT_PAYLOAD_TTL_SECONDS as f64, &pool).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::debug!("Прибрано {purged} збережених подій кімнат"),
+                Err(err) => tracing::warn!("Не вдалося прибрати збережені події кімнат: {err}"),
+            }
+        }
+    });
+}
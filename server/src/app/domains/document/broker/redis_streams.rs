@@ -0,0 +1,305 @@
+use dashmap::DashMap;
+use futures_util::future::{BoxFuture, FutureExt as _};
+use redis::AsyncCommands as _;
+use redis::aio::MultiplexedConnection;
+use redis::streams::{StreamReadOptions, StreamReadReply};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::time;
+use uuid::Uuid;
+
+use super::{Broker, BrokerMetrics};
+use crate::app::domains::document::fanout::{self, next_backoff};
+use crate::app::domains::document::models::Rooms;
+use crate::app::redis::client::RedisClient;
+use crate::app::redis::keys::RedisKey;
+use crate::app::{RequestResult, ServiceContext};
+use crate::constants::document::{
+    ROOM_STREAM_BATCH_SIZE, ROOM_STREAM_BLOCK_MS, ROOM_STREAM_MAX_LEN, ROOM_STREAM_TTL_SECONDS,
+};
+use crate::core::app_data::AppData;
+
+/// Поле запису потоку з JSON-серіалізованим `RoomEvent`.
+const PAYLOAD_FIELD: &str = "payload";
+
+/// Id запису потоку, з якого починати читання: усе, що додано починаючи з моменту `unix_ms`.
+pub(crate) fn offset_at(unix_ms: i64) -> String {
+    format!("{unix_ms}-0")
+}
+
+/// Початковий id потоку для підписки, що чекала на читача `waited`, за відповіддю Redis `TIME`.
+///
+/// Id записів `XADD *` задає годинник Redis, тож і межу беремо з нього, а не з годинника репліки:
+/// інакше за розбіжності годинників нова підписка пропускала б події або повторювала старі.
+pub(crate) fn start_offset(redis_secs: i64, redis_micros: i64, waited: Duration) -> String {
+    let now_ms = redis_secs * 1000 + redis_micros / 1000;
+    offset_at(now_ms - waited.as_millis() as i64)
+}
+
+/// Позиція читання потоку кімнати.
+#[derive(Clone, Debug)]
+enum StreamOffset {
+    /// Підписка з моменту `Instant`, яку читач ще не прив'язав до годинника Redis.
+    Pending(Instant),
+    /// Id останнього доставленого запису (або початковий id).
+    At(String),
+}
+
+/// Брокер на Redis Streams: кожна кімната — потік `document:room:<doc_id>`.
+///
+/// Усі потоки читаються одним фоновим завданням через одне з'єднання Redis,
+/// тож кількість з'єднань не залежить від кількості відкритих документів.
+pub struct RedisStreamsBroker {
+    state: Arc<StreamState>,
+}
+
+impl RedisStreamsBroker {
+    /// Створює брокер; `redis_url` потрібен для окремого з'єднання блокуючого читання.
+    pub fn new(redis: RedisClient, redis_url: String) -> Self {
+        Self {
+            state: Arc::new(StreamState {
+                redis,
+                redis_url,
+                offsets: DashMap::new(),
+                wake: Notify::new(),
+                connected: AtomicBool::new(false),
+                reconnects: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl Broker for RedisStreamsBroker {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    /// Додає запис до потоку кімнати.
+    ///
+    /// Потік обрізається приблизно до `ROOM_STREAM_MAX_LEN` записів і зникає, якщо в кімнаті
+    /// довго немає подій, тож репліка, що тимчасово втратила з'єднання, дочитає пропущене.
+    fn publish(&self, doc_id: Uuid, payload: Vec<u8>) -> BoxFuture<'_, RequestResult<()>> {
+        async move {
+            let key = RedisKey::DocumentRoom(doc_id).to_string();
+
+            let redis = &self.state.redis;
+            let mut pipe = redis.get_pipe();
+            pipe.cmd("XADD")
+                .arg(&key)
+                .arg("MAXLEN")
+                .arg("~")
+                .arg(ROOM_STREAM_MAX_LEN)
+                .arg("*")
+                .arg(PAYLOAD_FIELD)
+                .arg(payload)
+                .ignore()
+                .expire(&key, ROOM_STREAM_TTL_SECONDS)
+                .ignore();
+            redis.exec_pipe::<()>(&pipe).await
+        }
+        .boxed()
+    }
+
+    /// Починає читати потік кімнати з поточного моменту. Повторна підписка зберігає наявний id.
+    ///
+    /// Початковий id визначає читач за годинником Redis, див. `start_offset`.
+    fn subscribe(&self, doc_id: Uuid) {
+        let mut inserted = false;
+        self.state.offsets.entry(doc_id).or_insert_with(|| {
+            inserted = true;
+            StreamOffset::Pending(Instant::now())
+        });
+        if inserted {
+            // Перериваємо поточне очікування читача, щоб він одразу додав новий потік
+            self.state.wake.notify_one();
+        }
+    }
+
+    /// Запускає єдиного на репліку читача потоків усіх кімнат з підключеннями.
+    ///
+    /// Репліка сама зберігає id останнього доставленого запису кожного потоку. Після розриву з'єднання
+    /// з Redis читач перепідключається з наростаючою паузою та продовжує з тих самих id, тож події,
+    /// опубліковані під час розриву, доставляються клієнтам без втрат.
+    fn run(&self, app_data: &AppData) {
+        let cancel_token = app_data.token().child_token();
+        let app_data = app_data.clone();
+        let reader = self.state.clone();
+
+        actix_rt::spawn(async move {
+            let ctx = ServiceContext::from(&app_data);
+            let options = StreamReadOptions::default()
+                .block(ROOM_STREAM_BLOCK_MS)
+                .count(ROOM_STREAM_BATCH_SIZE);
+            let mut backoff = Duration::ZERO;
+            let mut connected_before = false;
+
+            'reconnect: while !cancel_token.is_cancelled() {
+                if !backoff.is_zero() {
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = time::sleep(backoff) => {}
+                    }
+                }
+
+                let (mut conn, client_id) = match connect(&reader.redis_url).await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        backoff = next_backoff(backoff);
+                        tracing::warn!("Не вдалося підключитися до потоків кімнат: {err}; повтор через {backoff:?}");
+                        continue;
+                    }
+                };
+                reader.connected.store(true, Ordering::Relaxed);
+                if connected_before {
+                    reader.reconnects.fetch_add(1, Ordering::Relaxed);
+                    tracing::info!("Читач потоків кімнат перепідключився, дочитуємо пропущені події");
+                }
+                connected_before = true;
+                backoff = Duration::ZERO;
+
+                loop {
+                    reader.prune(&app_data.rooms);
+                    if let Err(err) = reader.resolve_pending(&mut conn).await {
+                        reader.connected.store(false, Ordering::Relaxed);
+                        backoff = next_backoff(backoff);
+                        tracing::warn!("Не вдалося отримати час Redis для нових підписок: {err}; повтор через {backoff:?}");
+                        continue 'reconnect;
+                    }
+                    let (doc_ids, keys, offsets) = reader.snapshot();
+                    if keys.is_empty() {
+                        tokio::select! {
+                            _ = cancel_token.cancelled() => break 'reconnect,
+                            _ = reader.wake.notified() => continue,
+                        }
+                    }
+
+                    let reply: redis::RedisResult<Option<StreamReadReply>> = {
+                        let read = conn.xread_options(&keys, &offsets, &options);
+                        tokio::pin!(read);
+                        loop {
+                            tokio::select! {
+                                biased;
+                                _ = cancel_token.cancelled() => break 'reconnect,
+                                reply = &mut read => break reply,
+                                _ = reader.wake.notified() => reader.unblock(client_id).await,
+                            }
+                        }
+                    };
+
+                    let reply = match reply {
+                        Ok(Some(reply)) => reply,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            reader.connected.store(false, Ordering::Relaxed);
+                            backoff = next_backoff(backoff);
+                            tracing::warn!("Втрачено з'єднання з потоками кімнат: {err}; повтор через {backoff:?}");
+                            continue 'reconnect;
+                        }
+                    };
+
+                    for stream in reply.keys {
+                        let Some(doc_id) = keys.iter().position(|k| *k == stream.key).map(|i| doc_ids[i]) else {
+                            continue;
+                        };
+                        for entry in stream.ids {
+                            reader.advance(doc_id, &entry.id);
+                            match entry.get::<Vec<u8>>(PAYLOAD_FIELD) {
+                                Some(payload) => fanout::deliver(&ctx, doc_id, &payload).await,
+                                None => {
+                                    tracing::error!("Запис {} потоку кімнати {doc_id} без поля {PAYLOAD_FIELD}", entry.id);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            reader.connected.store(false, Ordering::Relaxed);
+            tracing::info!("Читача потоків кімнат зупинено");
+        });
+    }
+
+    fn metrics(&self) -> BrokerMetrics {
+        BrokerMetrics {
+            subscriptions: self.state.offsets.len(),
+            connected: self.state.connected.load(Ordering::Relaxed),
+            reconnects: self.state.reconnects.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Стан брокера, спільний з фоновим читачем.
+struct StreamState {
+    redis: RedisClient,
+    redis_url: String,
+    /// Позиція читання кожного потоку.
+    offsets: DashMap<Uuid, StreamOffset>,
+    wake: Notify,
+    connected: AtomicBool,
+    reconnects: AtomicU64,
+}
+
+impl StreamState {
+    /// Прибирає потоки кімнат, у яких на цій репліці більше немає підключень.
+    fn prune(&self, rooms: &Rooms) {
+        self.offsets.retain(|doc_id, _| !rooms.is_empty(doc_id));
+    }
+
+    /// Прив'язує нові підписки до годинника Redis.
+    async fn resolve_pending(&self, conn: &mut MultiplexedConnection) -> redis::RedisResult<()> {
+        if !self.offsets.iter().any(|entry| matches!(entry.value(), StreamOffset::Pending(_))) {
+            return Ok(());
+        }
+
+        let (secs, micros): (i64, i64) = redis::cmd("TIME").query_async(conn).await?;
+        for mut entry in self.offsets.iter_mut() {
+            if let StreamOffset::Pending(since) = *entry.value() {
+                *entry.value_mut() = StreamOffset::At(start_offset(secs, micros, since.elapsed()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Поточні ключі потоків та id, з яких їх читати.
+    ///
+    /// Підписки, що з'явилися після `resolve_pending`, пропускаються до наступного проходу читача.
+    fn snapshot(&self) -> (Vec<Uuid>, Vec<String>, Vec<String>) {
+        let mut doc_ids = Vec::with_capacity(self.offsets.len());
+        let mut keys = Vec::with_capacity(self.offsets.len());
+        let mut offsets = Vec::with_capacity(self.offsets.len());
+        for entry in self.offsets.iter() {
+            let StreamOffset::At(offset) = entry.value() else {
+                continue;
+            };
+            doc_ids.push(*entry.key());
+            keys.push(RedisKey::DocumentRoom(*entry.key()).to_string());
+            offsets.push(offset.clone());
+        }
+        (doc_ids, keys, offsets)
+    }
+
+    /// Запам'ятовує id доставленого запису, якщо кімната ще читається.
+    fn advance(&self, doc_id: Uuid, id: &str) {
+        if let Some(mut offset) = self.offsets.get_mut(&doc_id) {
+            *offset = StreamOffset::At(id.to_string());
+        }
+    }
+
+    /// Перериває блокуючий XREAD читача, щоб він перечитав список потоків.
+    async fn unblock(&self, client_id: i64) {
+        let mut pipe = self.redis.get_pipe();
+        pipe.cmd("CLIENT").arg("UNBLOCK").arg(client_id).ignore();
+        if let Err(err) = self.redis.exec_pipe::<()>(&pipe).await {
+            tracing::warn!("Не вдалося перервати очікування читача потоків: {err}");
+        }
+    }
+}
+
+/// Окреме з'єднання для блокуючого XREAD, щоб не займати підключення з пулу, та його `CLIENT ID`.
+async fn connect(redis_url: &str) -> redis::RedisResult<(MultiplexedConnection, i64)> {
+    let mut conn = redis::Client::open(redis_url)?.get_multiplexed_async_connection().await?;
+    let client_id: i64 = redis::cmd("CLIENT").arg("ID").query_async(&mut conn).await?;
+    Ok((conn, client_id))
+}
@@ -1,19 +1,20 @@
 use actix_web::{
     HttpRequest, HttpResponse, Responder,
-    web::{Data, Json, Path},
+    web::{Data, Json, Path, Query},
 };
 use serde::Deserialize;
 use uuid::Uuid;
 
-use super::service;
+use super::models::{CreateInviteRequest, RestoreTrashRequest, RunPolicy};
+use super::{presence, service};
 use crate::core::app_data::AppData;
-use crate::app::{RequestResult, ServiceContext};
+use crate::app::{RequestError, RequestResult, ServiceContext};
 use crate::app::domains::auth::{validate_token, Claims};
 
 // ─────────────────────────── Helpers ─────────────────────────────────────────
 
 /// Витягує та валідує JWT з заголовка Authorization запиту.
-fn extract_claims(req: &HttpRequest, jwt_secret: &str) -> RequestResult<Claims> {
+pub(crate) fn extract_claims(req: &HttpRequest, jwt_secret: &str) -> RequestResult<Claims> {
     let header = req
         .headers()
         .get("Authorization")
@@ -134,19 +135,221 @@ pub async fn remove_member(
     Ok(HttpResponse::Ok().body("Учасника видалено"))
 }
 
+// ─────────────────────────── Metrics ─────────────────────────────────────────
+
+/// Перевіряє токен метрик у заголовку `Authorization: Bearer`.
+///
+/// Якщо `METRICS_TOKEN` не задано, метрики вимкнено й ендпоінт відповідає 404.
+pub(crate) fn check_metrics_token(req: &HttpRequest, expected: Option<&str>) -> RequestResult<()> {
+    let Some(expected) = expected else {
+        return Err(RequestError::not_found("Метрики вимкнено"));
+    };
+    let provided = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or_default();
+
+    // Порівняння без раннього виходу, щоб час відповіді не підказував токен
+    let matches = provided.len() == expected.len()
+        && provided.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0;
+    if matches {
+        Ok(())
+    } else {
+        Err(RequestError::unauthorized("Недійсний токен метрик"))
+    }
+}
+
+/// Повертає стан розсилки подій кімнат цієї репліки: брокер, кількість підписок, з'єднання та перепідключення.
+///
+/// Доступно лише з токеном `METRICS_TOKEN`.
+#[tracing::instrument(name = "fanout_metrics", skip(req, app_data))]
+pub async fn fanout_metrics(req: HttpRequest, app_data: Data<AppData>) -> RequestResult<impl Responder> {
+    check_metrics_token(&req, app_data.metrics_token.as_deref())?;
+    Ok(HttpResponse::Ok().json(app_data.fanout.metrics()))
+}
+
+// ─────────────────────────── Invites ─────────────────────────────────────────
+
+/// Видає одноразовий квиток для підключення до WebSocket документа.
+#[tracing::instrument(name = "issue_ws_ticket", skip(req, app_data), fields(doc_id = %doc_id))]
+pub async fn issue_ws_ticket(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let ticket = service::issue_ws_ticket(doc_id.into_inner(), claims.sub, &claims.username, &ctx).await?;
+    Ok(HttpResponse::Ok().json(ticket))
+}
+
+/// Створює посилання-запрошення з роллю, обмеженням використань та терміном дії (тільки Manager).
+///
+/// Запрошення з роллю Manager створює лише власник документа; термін дії — до року.
+#[tracing::instrument(name = "create_invite", skip(req, body, app_data), fields(doc_id = %doc_id))]
+pub async fn create_invite(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    body: Json<CreateInviteRequest>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let invite = service::create_invite(doc_id.into_inner(), body.into_inner(), claims.sub, &ctx).await?;
+    Ok(HttpResponse::Created().json(invite))
+}
+
+/// Повертає чинні запрошення документа (тільки Manager).
+#[tracing::instrument(name = "list_invites", skip(req, app_data), fields(doc_id = %doc_id))]
+pub async fn list_invites(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let invites = service::list_invites(doc_id.into_inner(), claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+/// Відкликає запрошення (тільки Manager).
+#[tracing::instrument(name = "revoke_invite", skip(req, app_data))]
+pub async fn revoke_invite(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, invite_id) = path.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    service::revoke_invite(doc_id, invite_id, claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().body("Запрошення відкликано"))
+}
+
+/// Приймає запрошення: поточний користувач стає учасником документа.
+#[tracing::instrument(name = "accept_invite", skip_all)]
+pub async fn accept_invite(
+    req: HttpRequest,
+    token: Path<String>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let accepted = service::accept_invite(&token, claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().json(accepted))
+}
+
+// ─────────────────────────── Runs ────────────────────────────────────────────
+
+/// Змінює правило запуску коду: лише Editor та Manager або всі учасники (тільки Manager).
+#[tracing::instrument(name = "set_run_policy", skip(req, body, app_data), fields(doc_id = %doc_id))]
+pub async fn set_run_policy(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    body: Json<RunPolicy>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let policy = service::set_run_policy(doc_id.into_inner(), body.into_inner(), claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().json(policy))
+}
+
 // ─────────────────────────── Participants (Session) ──────────────────────────
 
-/// Повертає список учасників активної сесії документа.
+/// Повертає список учасників активної сесії документа з усіх реплік.
 pub async fn get_participants(
     req: HttpRequest,
     doc_id: Path<Uuid>,
     app_data: Data<AppData>,
 ) -> RequestResult<impl Responder> {
     extract_claims(&req, &app_data.jwt_secret)?;
-    let participants = app_data.rooms.get_participants(&doc_id.into_inner());
+    let (participants, _) = presence::list(doc_id.into_inner(), app_data.sessions.as_ref()).await?;
     Ok(HttpResponse::Ok().json(participants))
 }
 
+// ─────────────────────────── Trash ───────────────────────────────────────────
+
+/// Повертає вміст кошика проекту (видалені файли та папки).
+#[tracing::instrument(name = "list_trash", skip(req, app_data), fields(doc_id = %doc_id))]
+pub async fn list_trash(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let entries = service::list_trash(doc_id.into_inner(), &ctx).await?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+/// Відновлює запис кошика (Editor або Manager).
+#[tracing::instrument(name = "restore_trash", skip(req, body, app_data))]
+pub async fn restore_trash(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    body: Option<Json<RestoreTrashRequest>>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, trash_id) = path.into_inner();
+    let body = body.map(Json::into_inner).unwrap_or_default();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let events = service::restore_from_trash(doc_id, trash_id, body, claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().json(events))
+}
+
+/// Остаточно видаляє запис кошика (тільки Manager).
+#[tracing::instrument(name = "purge_trash", skip(req, app_data))]
+pub async fn purge_trash(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, trash_id) = path.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    service::purge_trash_entry(doc_id, trash_id, claims.sub, &ctx).await?;
+    Ok(HttpResponse::Ok().body("Запис кошика видалено"))
+}
+
+// ─────────────────────────── File History ────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct FileHistoryQuery {
+    pub path: String,
+}
+
+/// Повертає історію ревізій файлу з авторами, часом та diff до попередньої ревізії.
+#[tracing::instrument(name = "file_history", skip(req, app_data), fields(doc_id = %doc_id))]
+pub async fn file_history(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    query: Query<FileHistoryQuery>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    extract_claims(&req, &app_data.jwt_secret)?;
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let timeline = service::file_history(doc_id.into_inner(), &query.path, &ctx).await?;
+    Ok(HttpResponse::Ok().json(timeline))
+}
+
+/// Повертає вміст файлу на момент вказаної ревізії.
+#[tracing::instrument(name = "get_file_revision", skip(req, app_data))]
+pub async fn get_file_revision(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, revision_id) = path.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let revision = service::get_file_revision(doc_id, revision_id, &ctx).await?;
+    Ok(HttpResponse::Ok().json(revision))
+}
+
 // ─────────────────────────── Export ──────────────────────────────────────────
 
 /// Експортує файли проекту як tar.xz архів.
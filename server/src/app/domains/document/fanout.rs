@@ -0,0 +1,135 @@
+use serde::Serialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::broker::Broker;
+use super::models::{PubSubMessage, RoomEvent};
+use super::ws_handler::dispatch_room_message;
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::constants::document::ROOM_STREAM_RECONNECT_MAX_SECONDS;
+use crate::core::app_data::AppData;
+
+// ─────────────────────────── Fanout ──────────────────────────────────────────
+
+/// Розсилка подій кімнат між репліками через обраний брокер.
+#[derive(Clone)]
+pub struct Fanout {
+    /// Ідентифікатор цієї репліки в подіях кімнат, новий при кожному запуску.
+    replica_id: Uuid,
+    broker: Arc<dyn Broker>,
+    delivered: Arc<AtomicU64>,
+}
+
+impl Fanout {
+    pub fn new(broker: Arc<dyn Broker>) -> Self {
+        Self {
+            replica_id: Uuid::now_v7(),
+            broker,
+            delivered: Arc::default(),
+        }
+    }
+
+    /// Ідентифікатор цієї репліки.
+    pub fn replica_id(&self) -> Uuid {
+        self.replica_id
+    }
+
+    /// Чи опублікувала подію ця репліка — тоді її клієнти вже отримали подію під час публікації.
+    pub(crate) fn is_own(&self, event: &RoomEvent) -> bool {
+        event.replica_id == self.replica_id
+    }
+
+    /// Починає отримувати події кімнати від інших реплік. Повторна підписка нічого не змінює.
+    pub fn subscribe(&self, doc_id: Uuid) {
+        self.broker.subscribe(doc_id);
+    }
+
+    /// Знімок лічильників розсилки.
+    pub fn metrics(&self) -> FanoutMetricsSnapshot {
+        let broker = self.broker.metrics();
+        FanoutMetricsSnapshot {
+            replica_id: self.replica_id,
+            broker: self.broker.name(),
+            subscriptions: broker.subscriptions,
+            connected: broker.connected,
+            reconnects: broker.reconnects,
+            delivered: self.delivered.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Стан розсилки подій кімнат для моніторингу.
+#[derive(Serialize, Debug)]
+pub struct FanoutMetricsSnapshot {
+    pub replica_id: Uuid,
+    /// Реалізація брокера: `redis`, `postgres` або `memory`.
+    pub broker: &'static str,
+    /// Кількість кімнат, події яких отримує репліка.
+    pub subscriptions: usize,
+    /// Чи є зараз з'єднання з брокером.
+    pub connected: bool,
+    /// Скільки разів отримувач перепідключався після розриву.
+    pub reconnects: u64,
+    /// Скільки подій інших реплік доставлено локальним кімнатам.
+    pub delivered: u64,
+}
+
+// ─────────────────────────── Publish ─────────────────────────────────────────
+
+/// Розсилає подію кімнати клієнтам усіх реплік, окрім підключення-ініціатора `origin`.
+///
+/// Клієнтам цієї репліки подія доставляється одразу, а іншим — через брокер,
+/// тож його недоступність зачіпає лише інші репліки.
+pub async fn publish(
+    ctx: &ServiceContext<'_>,
+    doc_id: Uuid,
+    origin: Option<Uuid>,
+    message: PubSubMessage,
+) -> RequestResult<()> {
+    let event = RoomEvent {
+        replica_id: ctx.fanout.replica_id(),
+        origin_conn_id: origin,
+        message,
+    };
+    let payload = serde_json::to_vec(&event)
+        .map_err(|e| RequestError::internal_server_error(format!("Помилка серіалізації події кімнати: {e}")))?;
+
+    // Локальна доставка може знову публікувати (зміна доступу → список учасників), тому future в купі
+    Box::pin(dispatch_room_message(ctx, doc_id, origin, event.message)).await;
+    ctx.fanout.broker.publish(doc_id, payload).await
+}
+
+// ─────────────────────────── Receive ─────────────────────────────────────────
+
+/// Доставляє локальним клієнтам подію, отриману брокером від інших реплік.
+///
+/// Власні події репліки пропускаються: під час публікації вони вже доставлені її клієнтам.
+pub async fn deliver(ctx: &ServiceContext<'_>, doc_id: Uuid, payload: &[u8]) {
+    let event = match serde_json::from_slice::<RoomEvent>(payload) {
+        Ok(event) => event,
+        Err(err) => {
+            tracing::error!("Помилка десеріалізації події кімнати {doc_id}: {err}");
+            return;
+        }
+    };
+    if ctx.fanout.is_own(&event) {
+        return;
+    }
+
+    ctx.fanout.delivered.fetch_add(1, Ordering::Relaxed);
+    dispatch_room_message(ctx, doc_id, event.origin_conn_id, event.message).await;
+}
+
+/// Запускає отримання подій кімнат від інших реплік.
+pub fn run(app_data: &AppData) {
+    let fanout = &app_data.fanout;
+    tracing::info!("Запущено розсилку подій кімнат (брокер {}, репліка {})", fanout.broker.name(), fanout.replica_id);
+    fanout.broker.run(app_data);
+}
+
+/// Пауза перед черговою спробою перепідключення: подвоюється до `ROOM_STREAM_RECONNECT_MAX_SECONDS`.
+pub(crate) fn next_backoff(current: Duration) -> Duration {
+    (current * 2).clamp(Duration::from_secs(1), Duration::from_secs(ROOM_STREAM_RECONNECT_MAX_SECONDS))
+}
@@ -1,11 +1,22 @@
+pub mod broker;
 pub mod controller;
+pub mod fanout;
 pub mod models;
+pub mod presence;
 pub mod repository;
 pub mod service;
+pub mod sessions;
 pub mod ws_handler;
 
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
 pub use controller::{
     create_document, get_document, get_document_title,
     list_documents, add_member, remove_member, get_participants, export_project,
+    list_trash, restore_trash, purge_trash, file_history, get_file_revision,
+    issue_ws_ticket, fanout_metrics, create_invite, list_invites, revoke_invite, accept_invite,
+    set_run_policy,
 };
 pub use ws_handler::ws_handler;
@@ -1,12 +1,15 @@
+pub mod outbound;
 pub mod request;
 pub mod response;
 pub mod rows;
 pub mod ws;
 
-pub use request::CreateDocumentRequest;
-pub use response::DocumentResponse;
-pub use rows::{DocumentRow, ChangeRow, ProjectFileRow, DocumentSummary};
+pub use outbound::{Outbound, OutboundFrame, PushOutcome};
+pub use request::{CreateDocumentRequest, CreateInviteRequest, RestoreConflict, RestoreTrashRequest, RunPolicy};
+pub use response::{AcceptInviteResponse, DocumentResponse, FileHistoryEntry, WsTicketResponse};
+pub use rows::{DocumentRow, ChangeRow, ProjectFileRow, DocumentSummary, TrashEntry, FileRevisionRow, RevisionKind, ChatMessageRow, InviteRow, AccessRequestRow};
 pub use ws::{
-    Rooms, Connection, PubSubMessage, FileSystemEvent, FileSystemMessage,
-    SessionRole, ParticipantInfo, ServerMessage,
+    Rooms, Connection, PubSubMessage, FileSystemEvent,
+    SessionRole, ParticipantInfo, CursorState, Selection, ClientEnvelope, ClientMessage, ServerMessage, ErrorCode, ProtocolError,
+    ChatEvent, Viewport, AccessRequestEvent, WsTicket, RoomEvent,
 };
@@ -0,0 +1,155 @@
+use actix_web::web::Bytes;
+use actix_ws::{CloseCode, CloseReason, Session};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+use crate::constants::ws::{OUTBOUND_CLOSE_TIMEOUT_SECONDS, OUTBOUND_QUEUE_CAPACITY};
+
+// ─────────────────────────── Frames ──────────────────────────────────────────
+
+/// Кадр, що чекає відправки клієнту.
+#[derive(Debug)]
+pub enum OutboundFrame {
+    /// Automerge-зміни та відповіді на них.
+    Binary(Bytes),
+    /// Команди протоколу, які клієнт не може пропустити (fs_event, ack, error, role_changed).
+    Text(String),
+    /// Курсори та список учасників: при заповненій черзі відкидаються першими,
+    /// бо наступне оновлення все одно замінить попереднє.
+    Presence(String),
+    Ping(Bytes),
+    Pong(Bytes),
+    /// Останній кадр черги: після нього writer закриває сесію.
+    Close(Option<CloseReason>),
+}
+
+/// Результат постановки кадру в чергу.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// Кадр присутності відкинуто, бо клієнт відстає.
+    Dropped,
+    /// Черга переповнена — з'єднання буде закрито.
+    Overflow,
+    /// Writer уже завершився.
+    Closed,
+}
+
+// ─────────────────────────── Outbound queue ──────────────────────────────────
+
+/// Обмежена впорядкована черга вихідних кадрів підключення.
+///
+/// Усі кадри клієнту проходять через одну чергу й відправляються одним writer-завданням,
+/// тому порядок зберігається, а повільний клієнт не накопичує завдань на сервері.
+/// Коли вільного місця лишається менше чверті, кадри присутності відкидаються;
+/// якщо черга заповнена повністю, підключення закривається з кодом `1013`.
+#[derive(Clone)]
+pub struct Outbound {
+    tx: mpsc::Sender<OutboundFrame>,
+    presence_headroom: usize,
+    overflow: CancellationToken,
+}
+
+impl Outbound {
+    /// Створює чергу для сесії та запускає writer-завдання.
+    pub fn spawn(session: Session) -> Self {
+        let (outbound, rx) = Self::channel(OUTBOUND_QUEUE_CAPACITY);
+        actix_rt::spawn(run_writer(session, rx, outbound.overflow.clone()));
+        outbound
+    }
+
+    /// Створює чергу без writer-завдання; кадри читаються з повернутого приймача.
+    pub(crate) fn channel(capacity: usize) -> (Self, mpsc::Receiver<OutboundFrame>) {
+        let (tx, rx) = mpsc::channel(capacity);
+        let outbound = Self {
+            tx,
+            presence_headroom: capacity / 4,
+            overflow: CancellationToken::new(),
+        };
+        (outbound, rx)
+    }
+
+    /// Ставить кадр у чергу, не очікуючи клієнта.
+    pub fn push(&self, frame: OutboundFrame) -> PushOutcome {
+        if self.overflow.is_cancelled() {
+            return PushOutcome::Overflow;
+        }
+        if matches!(frame, OutboundFrame::Presence(_)) && self.tx.capacity() <= self.presence_headroom {
+            return PushOutcome::Dropped;
+        }
+
+        match self.tx.try_send(frame) {
+            Ok(()) => PushOutcome::Queued,
+            Err(TrySendError::Full(_)) => {
+                tracing::warn!("Черга вихідних повідомлень переповнена, клієнта буде відключено");
+                self.overflow.cancel();
+                PushOutcome::Overflow
+            }
+            Err(TrySendError::Closed(_)) => PushOutcome::Closed,
+        }
+    }
+
+    /// Закриває сесію після відправки всіх уже поставлених кадрів.
+    pub fn close(&self, reason: Option<CloseReason>) {
+        if self.push(OutboundFrame::Close(reason)) == PushOutcome::Overflow {
+            self.overflow.cancel();
+        }
+    }
+
+    /// Завершується, коли черга переповнилась і writer закрив сесію.
+    pub async fn overflowed(&self) {
+        self.overflow.cancelled().await
+    }
+}
+
+// ─────────────────────────── Writer ──────────────────────────────────────────
+
+/// Єдине завдання, що відправляє кадри черги в сесію по порядку.
+async fn run_writer(mut session: Session, mut rx: mpsc::Receiver<OutboundFrame>, overflow: CancellationToken) {
+    loop {
+        let frame = tokio::select! {
+            biased;
+            _ = overflow.cancelled() => break,
+            frame = rx.recv() => match frame {
+                Some(frame) => frame,
+                None => return,
+            },
+        };
+
+        let sent = match frame {
+            OutboundFrame::Close(reason) => {
+                let _ = session.close(reason).await;
+                return;
+            }
+            frame => tokio::select! {
+                biased;
+                _ = overflow.cancelled() => break,
+                sent = deliver(&mut session, frame) => sent,
+            },
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+
+    let reason = CloseReason {
+        code: CloseCode::Again,
+        description: Some("Клієнт не встигає отримувати повідомлення".to_string()),
+    };
+    let timeout = Duration::from_secs(OUTBOUND_CLOSE_TIMEOUT_SECONDS);
+    if time::timeout(timeout, session.close(Some(reason))).await.is_err() {
+        tracing::warn!("Не вдалося вчасно закрити сесію відсталого клієнта");
+    }
+}
+
+async fn deliver(session: &mut Session, frame: OutboundFrame) -> Result<(), actix_ws::Closed> {
+    match frame {
+        OutboundFrame::Binary(bytes) => session.binary(bytes).await,
+        OutboundFrame::Text(text) | OutboundFrame::Presence(text) => session.text(text).await,
+        OutboundFrame::Ping(bytes) => session.ping(&bytes).await,
+        OutboundFrame::Pong(bytes) => session.pong(&bytes).await,
+        OutboundFrame::Close(_) => Ok(()),
+    }
+}
@@ -1,7 +1,51 @@
 use serde::{Deserialize, Serialize};
 
+use super::ws::SessionRole;
+
 /// Модель запиту на створення нового документа.
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 pub struct CreateDocumentRequest {
     pub title: String,
 }
+
+/// Поведінка відновлення з кошика, якщо шлях уже зайнятий іншим файлом.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreConflict {
+    /// Повернути 409 Conflict.
+    #[default]
+    Fail,
+    /// Відновити під вільним іменем на кшталт `parser (restored).rs`.
+    Rename,
+    /// Перемістити поточний файл до кошика та відновити на його місце.
+    Overwrite,
+}
+
+/// Модель запиту на відновлення запису з кошика.
+#[derive(Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct RestoreTrashRequest {
+    /// Новий шлях для відновлення (за замовчуванням — початковий шлях).
+    pub new_path: Option<String>,
+    #[serde(default)]
+    pub on_conflict: RestoreConflict,
+}
+
+/// Модель запиту на створення запрошення до документа.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct CreateInviteRequest {
+    /// Роль, яку отримає користувач, що прийме запрошення.
+    #[schema(value_type = String, example = "editor")]
+    pub role: SessionRole,
+    /// Скільки разів запрошення можна використати (за замовчуванням — без обмеження).
+    pub max_uses: Option<i32>,
+    /// Через скільки годин запрошення стане недійсним: від 1 до `INVITE_MAX_EXPIRES_IN_HOURS`
+    /// (за замовчуванням — безстрокове).
+    pub expires_in_hours: Option<i64>,
+}
+
+/// Правило запуску коду в документі; приймається від менеджера й повертається у відповідь.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, utoipa::ToSchema)]
+pub struct RunPolicy {
+    /// `true` — запускати код можуть лише Editor та Manager, `false` — усі учасники.
+    pub editors_only: bool,
+}
@@ -1,5 +1,6 @@
 use uuid::Uuid;
 use super::rows::DocumentRow;
+use super::ws::SessionRole;
 
 /// Модель відповіді з даними документа.
 #[derive(serde::Serialize, utoipa::ToSchema)]
@@ -19,3 +20,34 @@ impl From<DocumentRow> for DocumentResponse {
         }
     }
 }
+
+/// Запис історії файлу: ревізія разом із diff відносно попередньої ревізії.
+#[derive(serde::Serialize)]
+pub struct FileHistoryEntry {
+    pub id: Uuid,
+    pub path: String,
+    pub old_path: Option<String>,
+    pub kind: String,
+    pub author_id: Option<Uuid>,
+    pub author_username: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Час останньої правки, злитої в ревізію.
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Unified diff відносно попередньої ревізії (для першої — відносно порожнього файлу).
+    pub diff: String,
+}
+
+/// Результат прийняття запрошення: документ і роль користувача в ньому.
+#[derive(serde::Serialize)]
+pub struct AcceptInviteResponse {
+    pub document_id: Uuid,
+    pub role: SessionRole,
+}
+
+/// Одноразовий квиток для підключення до WebSocket документа.
+#[derive(serde::Serialize)]
+pub struct WsTicketResponse {
+    pub ticket: String,
+    /// Через скільки секунд квиток стане недійсним.
+    pub expires_in: u64,
+}
@@ -43,3 +43,101 @@ pub struct DocumentSummary {
     pub owner_username: String,
     pub is_owner: bool,
 }
+
+/// Запис кошика проекту: файл або папка (з вмістом), видалені однією операцією.
+#[derive(sqlx::FromRow, serde::Serialize)]
+pub struct TrashEntry {
+    pub trash_id: Uuid,
+    /// Шлях видаленого файлу або кореневої папки.
+    pub path: String,
+    pub is_dir: bool,
+    /// Кількість рядків (файлів і папок), видалених разом.
+    pub files_count: i64,
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
+    pub deleted_by: Option<Uuid>,
+    pub deleted_by_username: Option<String>,
+}
+
+/// Ревізія файлу проекту з таблиці project_file_revisions.
+#[derive(sqlx::FromRow, serde::Serialize)]
+pub struct FileRevisionRow {
+    pub id: Uuid,
+    /// Стабільний ідентифікатор файлу, що не змінюється при перейменуваннях.
+    pub file_id: Uuid,
+    pub path: String,
+    /// Попередній шлях (для перейменувань та відновлень під іншим ім'ям).
+    pub old_path: Option<String>,
+    pub content: String,
+    /// Тип ревізії: `edit`, `rename`, `delete` або `restore`.
+    pub kind: String,
+    pub author_id: Option<Uuid>,
+    pub author_username: Option<String>,
+    /// Час створення ревізії: від нього відраховується вікно злиття правок.
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Час останньої правки, злитої в ревізію.
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Тип ревізії файлу, що зберігається в колонці `project_file_revisions.kind`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RevisionKind {
+    Edit,
+    Rename,
+    Delete,
+    Restore,
+}
+
+impl RevisionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionKind::Edit    => "edit",
+            RevisionKind::Rename  => "rename",
+            RevisionKind::Delete  => "delete",
+            RevisionKind::Restore => "restore",
+        }
+    }
+}
+
+/// Повідомлення чату кімнати з таблиці chat_messages.
+#[derive(sqlx::FromRow, serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ChatMessageRow {
+    pub id: Uuid,
+    pub author_id: Option<Uuid>,
+    pub author_username: Option<String>,
+    pub body: String,
+    /// Ідентифікатори згаданих учасників документа.
+    pub mentions: Vec<Uuid>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub edited_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Запрошення до документа з таблиці document_invites.
+#[derive(sqlx::FromRow, serde::Serialize, Clone, Debug)]
+pub struct InviteRow {
+    pub id: Uuid,
+    pub document_id: Uuid,
+    /// Секрет посилання-запрошення.
+    pub token: String,
+    /// Роль, яку отримає користувач: `reader`, `editor` або `manager`.
+    pub role: String,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_by_username: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Запит читача на право редагування з таблиці access_requests.
+#[derive(sqlx::FromRow, serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct AccessRequestRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    /// Необов'язковий коментар читача для менеджерів.
+    pub message: Option<String>,
+    /// `pending`, `approved` або `denied`.
+    pub status: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -1,10 +1,14 @@
 use actix_web::web::Bytes;
-use actix_ws::Session;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+use super::outbound::{Outbound, OutboundFrame};
+use super::rows::{AccessRequestRow, ChatMessageRow};
+use crate::app::domains::execution::models::{OutputChunk, RunEvent, RunKind, RunStatus};
+
 // ─────────────────────────── Roles ───────────────────────────────────────────
 
 /// Роль учасника сесії.
@@ -27,17 +31,92 @@ impl SessionRole {
     pub fn can_manage(&self) -> bool {
         matches!(self, SessionRole::Manager)
     }
+
+    /// Роль з колонки `document_members.role`; невідомі значення вважаються `Reader`.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "editor"  => SessionRole::Editor,
+            "manager" => SessionRole::Manager,
+            _         => SessionRole::Reader,
+        }
+    }
+
+    /// Назва ролі у форматі колонки `document_members.role`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionRole::Reader  => "reader",
+            SessionRole::Editor  => "editor",
+            SessionRole::Manager => "manager",
+        }
+    }
 }
 
 // ─────────────────────────── ParticipantInfo ─────────────────────────────────
 
 /// Інформація про учасника сесії (для відправки клієнтам).
-#[derive(Clone, Serialize, Deserialize)]
+///
+/// Ця ж структура зберігається у сховищі сесій як запис присутності, тому її бачать клієнти всіх реплік.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ParticipantInfo {
     pub conn_id: Uuid,
     pub user_id: Uuid,
     pub username: String,
     pub role: SessionRole,
+    /// Файл, відкритий в редакторі учасника.
+    #[serde(default)]
+    pub active_file: Option<String>,
+    /// Остання відома позиція курсора та виділення.
+    #[serde(default)]
+    pub cursor: Option<CursorState>,
+    /// Учасник неактивний (вкладка прихована або давно не було вводу).
+    #[serde(default)]
+    pub idle: bool,
+    /// Підключення, за яким стежить учасник (режим follow).
+    #[serde(default)]
+    pub following: Option<Uuid>,
+    /// Остання видима область редактора учасника; її одразу отримує новий послідовник.
+    #[serde(default)]
+    pub viewport: Option<Viewport>,
+}
+
+impl From<&Connection> for ParticipantInfo {
+    fn from(c: &Connection) -> Self {
+        Self {
+            conn_id: c.id,
+            user_id: c.user_id,
+            username: c.username.clone(),
+            role: c.role.clone(),
+            active_file: None,
+            cursor: None,
+            idle: false,
+            following: c.following,
+            viewport: None,
+        }
+    }
+}
+
+/// Позиція курсора учасника у файлі.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CursorState {
+    pub path: Option<String>,
+    pub position: Option<usize>,
+    #[serde(default)]
+    pub selection: Option<Selection>,
+}
+
+/// Видима область редактора: файл та діапазон рядків (з 1, включно).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Viewport {
+    pub path: String,
+    pub from_line: u32,
+    pub to_line: u32,
+}
+
+/// Виділений фрагмент тексту (зміщення у символах).
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Selection {
+    pub anchor: usize,
+    pub head: usize,
 }
 
 // ─────────────────────────── Rooms ───────────────────────────────────────────
@@ -67,101 +146,152 @@ impl Rooms {
         }
     }
 
-    /// Повертає список учасників кімнати.
+    /// Повертає список учасників кімнати на цій репліці.
     pub fn get_participants(&self, room_id: &Uuid) -> Vec<ParticipantInfo> {
         self.value
             .get(room_id)
             .map(|r| {
-                r.iter()
-                    .map(|c| ParticipantInfo {
-                        conn_id: c.id,
-                        user_id: c.user_id,
-                        username: c.username.clone(),
-                        role: c.role.clone(),
-                    })
-                    .collect()
+                r.iter().map(ParticipantInfo::from).collect()
             })
             .unwrap_or_default()
     }
 
-    /// Змінює роль учасника. Тільки Manager може це робити.
-    /// Повертає true якщо зміна виконана.
-    pub fn set_role(
-        &self,
-        room_id: &Uuid,
-        target_conn_id: Uuid,
-        new_role: SessionRole,
-        requester_conn_id: Uuid,
-    ) -> bool {
-        if let Some(mut room) = self.value.get_mut(room_id) {
-            // Перевіряємо що requester є Manager
-            let is_manager = room
-                .iter()
-                .any(|c| c.id == requester_conn_id && c.role.can_manage());
-            if !is_manager {
-                return false;
+    /// Встановлює нову роль усім підключенням користувача в кімнаті.
+    /// Повертає ідентифікатори змінених підключень.
+    pub fn set_user_role(&self, room_id: &Uuid, user_id: Uuid, role: &SessionRole) -> Vec<Uuid> {
+        let Some(mut room) = self.value.get_mut(room_id) else {
+            return Vec::new();
+        };
+        room.iter_mut()
+            .filter(|c| c.user_id == user_id)
+            .map(|c| {
+                c.role = role.clone();
+                c.id
+            })
+            .collect()
+    }
+
+    /// Вилучає з кімнати всі підключення користувача та повертає їх.
+    pub fn take_user_connections(&self, room_id: &Uuid, user_id: Uuid) -> Vec<Connection> {
+        let Some(mut room) = self.value.get_mut(room_id) else {
+            return Vec::new();
+        };
+        let (taken, kept): (Vec<_>, Vec<_>) = room.drain(..).partition(|c| c.user_id == user_id);
+        *room = kept;
+        if room.is_empty() {
+            drop(room);
+            self.value.remove(room_id);
+        }
+        taken
+    }
+
+    /// Ставить бінарне оновлення (Automerge) в черги всіх учасників окрім відправника.
+    pub fn send_change(&self, room_id: &Uuid, connection_id: Uuid, change: Bytes) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter().filter(|c| c.id != connection_id) {
+                conn.outbound.push(OutboundFrame::Binary(change.clone()));
+            }
+        }
+    }
+
+    /// Ставить текстове JSON-повідомлення в черги всіх учасників окрім відправника.
+    pub fn send_text(&self, room_id: &Uuid, connection_id: Uuid, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter().filter(|c| c.id != connection_id) {
+                conn.outbound.push(OutboundFrame::Text(text.clone()));
             }
-            // Менеджер не може понизити сам себе
-            if target_conn_id == requester_conn_id {
-                return false;
+        }
+    }
+
+    /// Ставить оновлення присутності в черги ВСІХ учасників у кімнаті (включаючи відправника).
+    pub fn broadcast_presence(&self, room_id: &Uuid, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter() {
+                conn.outbound.push(OutboundFrame::Presence(text.clone()));
             }
-            if let Some(target) = room.iter_mut().find(|c| c.id == target_conn_id) {
-                target.role = new_role;
-                return true;
+        }
+    }
+
+    /// Повертає поточну роль підключення в кімнаті.
+    pub fn get_role(&self, room_id: &Uuid, conn_id: Uuid) -> Option<SessionRole> {
+        self.value
+            .get(room_id)
+            .and_then(|r| r.iter().find(|c| c.id == conn_id).map(|c| c.role.clone()))
+    }
+
+    /// Зберігає узгоджені під час рукостискання можливості протоколу підключення.
+    pub fn set_capabilities(&self, room_id: &Uuid, conn_id: Uuid, capabilities: Vec<String>) {
+        if let Some(mut room) = self.value.get_mut(room_id) {
+            if let Some(conn) = room.iter_mut().find(|c| c.id == conn_id) {
+                conn.capabilities = capabilities;
             }
         }
-        false
     }
 
-    /// Надсилає бінарне оновлення (Automerge) всім учасникам окрім відправника.
-    pub async fn send_change(&self, room_id: &Uuid, connection_id: Uuid, change: Bytes) {
-        if let Some(room) = self.value.get_mut(room_id) {
-            let clients = room.clone();
-            for mut conn in clients.into_iter() {
-                if conn.id == connection_id {
+    /// Ставить текстове повідомлення в черги учасників (окрім відправника), які увімкнули можливість `capability`.
+    pub fn send_text_to_capable(&self, room_id: &Uuid, connection_id: Uuid, capability: &str, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter() {
+                if conn.id == connection_id || !conn.capabilities.iter().any(|c| c == capability) {
                     continue;
                 }
-                let change = change.clone();
-                actix_rt::spawn(async move {
-                    if let Err(err) = conn.session.binary(change).await {
-                        tracing::warn!("Не вдалося надіслати зміни клієнту {connection_id}: {err}");
-                    }
-                });
+                conn.outbound.push(OutboundFrame::Text(text.clone()));
             }
         }
     }
 
-    /// Надсилає текстове JSON-повідомлення всім учасникам окрім відправника.
-    pub async fn send_text(&self, room_id: &Uuid, connection_id: Uuid, text: String) {
-        if let Some(room) = self.value.get_mut(room_id) {
-            let clients = room.clone();
-            for mut conn in clients.into_iter() {
-                if conn.id == connection_id {
+    /// Чи увімкнуло підключення можливість протоколу `capability`.
+    pub fn has_capability(&self, room_id: &Uuid, conn_id: Uuid, capability: &str) -> bool {
+        self.value
+            .get(room_id)
+            .and_then(|r| r.iter().find(|c| c.id == conn_id).map(|c| c.capabilities.iter().any(|cap| cap == capability)))
+            .unwrap_or(false)
+    }
+
+    /// Ставить оновлення присутності в черги учасників (окрім відправника), які увімкнули можливість `capability`.
+    pub fn send_presence_to_capable(&self, room_id: &Uuid, connection_id: Uuid, capability: &str, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter() {
+                if conn.id == connection_id || !conn.capabilities.iter().any(|c| c == capability) {
                     continue;
                 }
-                let text = text.clone();
-                actix_rt::spawn(async move {
-                    if let Err(err) = conn.session.text(text).await {
-                        tracing::warn!(
-                            "Не вдалося надіслати текстове повідомлення клієнту {connection_id}: {err}"
-                        );
-                    }
-                });
+                conn.outbound.push(OutboundFrame::Presence(text.clone()));
+            }
+        }
+    }
+
+    /// Запам'ятовує, за ким стежить підключення (`None` — режим follow вимкнено).
+    pub fn set_following(&self, room_id: &Uuid, conn_id: Uuid, leader: Option<Uuid>) {
+        if let Some(mut room) = self.value.get_mut(room_id) {
+            if let Some(conn) = room.iter_mut().find(|c| c.id == conn_id) {
+                conn.following = leader;
             }
         }
     }
 
-    /// Надсилає текстове повідомлення ВСІМ учасникам у кімнаті (включаючи відправника).
-    pub async fn broadcast_text(&self, room_id: &Uuid, text: String) {
-        if let Some(room) = self.value.get_mut(room_id) {
-            let clients = room.clone();
-            for mut conn in clients.into_iter() {
-                let text = text.clone();
-                actix_rt::spawn(async move {
-                    if let Err(err) = conn.session.text(text).await {
-                        tracing::warn!("Не вдалося надіслати broadcast повідомлення: {err}");
-                    }
-                });
+    /// Ставить оновлення присутності в черги підключень цієї репліки, що стежать за `leader`.
+    pub fn send_presence_to_followers(&self, room_id: &Uuid, leader: Uuid, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter().filter(|c| c.following == Some(leader)) {
+                conn.outbound.push(OutboundFrame::Presence(text.clone()));
+            }
+        }
+    }
+
+    /// Ставить текстове повідомлення в черги підключень з роллю Manager.
+    pub fn send_text_to_managers(&self, room_id: &Uuid, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter().filter(|c| c.role.can_manage()) {
+                conn.outbound.push(OutboundFrame::Text(text.clone()));
+            }
+        }
+    }
+
+    /// Ставить текстове повідомлення в черги всіх підключень користувача.
+    pub fn send_text_to_user(&self, room_id: &Uuid, user_id: Uuid, text: String) {
+        if let Some(room) = self.value.get(room_id) {
+            for conn in room.iter().filter(|c| c.user_id == user_id) {
+                conn.outbound.push(OutboundFrame::Text(text.clone()));
             }
         }
     }
@@ -192,23 +322,169 @@ pub struct Connection {
     pub user_id: Uuid,
     pub username: String,
     pub role: SessionRole,
-    pub session: Session,
+    /// Черга вихідних кадрів; усі повідомлення клієнту проходять через неї.
+    pub outbound: Outbound,
+    /// Можливості протоколу, узгоджені в `hello`/`welcome` (порожньо до рукостискання).
+    pub capabilities: Vec<String>,
+    /// Підключення, за яким стежить цей клієнт у режимі follow.
+    pub following: Option<Uuid>,
+    /// Скасовується, коли сервер завершує сесію (наприклад, відкликано доступ):
+    /// цикл обробки підключення виходить, не чекаючи, доки клієнт закриє сокет.
+    pub closed: CancellationToken,
 }
 
 // ─────────────────────────── WS Client Messages ──────────────────────────────
 
-/// Повідомлення від клієнта через WebSocket (текстовий фрейм).
+/// Команда клієнта через WebSocket (текстовий фрейм).
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
+    /// Початок рукостискання: версія протоколу та бажані можливості клієнта.
+    Hello {
+        protocol_version: u32,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
+    /// Подія файлової системи.
+    FsEvent { event: FileSystemEvent },
     /// Запит на зміну ролі (тільки Manager).
     RoleChange {
         target_conn_id: Uuid,
         new_role: SessionRole,
     },
-    /// Подія файлової системи.
-    #[serde(untagged)]
-    FileSystem(FileSystemMessage),
+    /// Позиція курсора та виділення в активному файлі.
+    Cursor {
+        path: Option<String>,
+        position: Option<usize>,
+        #[serde(default)]
+        selection: Option<Selection>,
+    },
+    /// Зміна активного файлу або стану неактивності учасника.
+    Activity {
+        active_file: Option<String>,
+        #[serde(default)]
+        idle: bool,
+    },
+    /// Нове повідомлення чату кімнати.
+    ChatSend { body: String },
+    /// Редагування власного повідомлення чату.
+    ChatEdit { message_id: Uuid, body: String },
+    /// Видалення власного повідомлення чату.
+    ChatDelete { message_id: Uuid },
+    /// Почати стежити за видимою областю іншого учасника.
+    Follow { leader_conn_id: Uuid },
+    /// Вимкнути режим follow.
+    Unfollow,
+    /// Зміна видимої області редактора (надсилається, коли за клієнтом хтось стежить).
+    Viewport {
+        #[serde(flatten)]
+        viewport: Viewport,
+    },
+    /// Запит сторінки історії чату, старшої за `before` (або найновішої).
+    ChatHistory {
+        #[serde(default)]
+        before: Option<Uuid>,
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    /// Запит читача на право редагування.
+    RequestAccess {
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// Рішення менеджера щодо запиту на право редагування.
+    ResolveAccessRequest { access_request_id: Uuid, approve: bool },
+    /// Запуск програми або тестів зі збереженим станом проекту; вивід надходить кадрами `run_*`.
+    RunStart {
+        kind: RunKind,
+        /// Id ревізії з історії файлів: запуск проекту в стані одразу після неї (за замовчуванням — поточний стан).
+        #[serde(default)]
+        version: Option<Uuid>,
+        /// Вміст stdin; в інтерактивному режимі — перші дані, після яких stdin лишається відкритим.
+        #[serde(default)]
+        stdin: Option<String>,
+        /// Приймати stdin командами `run_stdin` під час виконання.
+        #[serde(default)]
+        interactive: bool,
+    },
+    /// Дані для stdin інтерактивного запуску (рядки передаються як є, разом з `\n`).
+    RunStdin { run_id: Uuid, data: String },
+    /// Закрити stdin інтерактивного запуску (EOF).
+    RunStdinClose { run_id: Uuid },
+    /// Скасувати запуск у черзі або під час виконання (автор запуску або Manager).
+    RunCancel { run_id: Uuid },
+}
+
+impl ClientMessage {
+    /// Значення поля `type`, які розуміє сервер.
+    pub const TYPES: &'static [&'static str] = &[
+        "hello", "fs_event", "role_change", "cursor", "activity",
+        "chat_send", "chat_edit", "chat_delete", "chat_history",
+        "follow", "unfollow", "viewport", "request_access", "resolve_access_request",
+        "run_start", "run_stdin", "run_stdin_close", "run_cancel",
+    ];
+}
+
+/// Конверт команди клієнта: необов'язковий `request_id` та сама команда.
+///
+/// Якщо `request_id` вказано, сервер відповідає на команду `ack` або `error` з тим самим id.
+#[derive(Deserialize, Debug)]
+pub struct ClientEnvelope {
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+/// Мінімальний розбір конверта, щоб повідомити клієнту причину відхилення команди.
+#[derive(Deserialize)]
+struct RawEnvelope {
+    #[serde(default)]
+    request_id: Option<String>,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+}
+
+impl ClientEnvelope {
+    /// Розбирає текстовий фрейм клієнта. Помилка містить готовий до відправки код та опис.
+    pub fn parse(text: &str) -> Result<Self, ProtocolError> {
+        let err = match serde_json::from_str::<Self>(text) {
+            Ok(envelope) => return Ok(envelope),
+            Err(err) => err,
+        };
+
+        let Ok(raw) = serde_json::from_str::<RawEnvelope>(text) else {
+            return Err(ProtocolError::new(None, ErrorCode::MalformedMessage, "Повідомлення не є JSON-об'єктом"));
+        };
+
+        match raw.kind {
+            Some(kind) if ClientMessage::TYPES.contains(&kind.as_str()) => Err(ProtocolError::new(
+                raw.request_id,
+                ErrorCode::InvalidRequest,
+                format!("Некоректне повідомлення '{}': {}", kind, err),
+            )),
+            Some(kind) => Err(ProtocolError::new(
+                raw.request_id,
+                ErrorCode::UnsupportedMessage,
+                format!("Непідтримуваний тип повідомлення: {}", kind),
+            )),
+            None => Err(ProtocolError::new(
+                raw.request_id,
+                ErrorCode::MalformedMessage,
+                "Відсутнє поле 'type'",
+            )),
+        }
+    }
+}
+
+// ─────────────────────────── Tickets ─────────────────────────────────────────
+
+/// Дані одноразового квитка підключення, що зберігаються у сховищі сесій до першого використання.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WsTicket {
+    pub doc_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
 }
 
 // ─────────────────────────── WS Server Messages ──────────────────────────────
@@ -217,29 +493,245 @@ pub enum ClientMessage {
 #[derive(Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
+    /// Відповідь на `hello`: узгоджена версія протоколу та можливості.
+    Welcome {
+        protocol_version: u32,
+        conn_id: Uuid,
+        role: SessionRole,
+        capabilities: Vec<String>,
+    },
+    /// Команду з `request_id` успішно виконано.
+    Ack { request_id: String },
+    /// Помилка обробки команди або некоректне повідомлення.
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+        code: ErrorCode,
+        message: String,
+    },
     /// Оновлений список учасників сесії.
     ParticipantsUpdate { participants: Vec<ParticipantInfo> },
-    /// Відмова у доступі.
-    PermissionDenied { reason: String },
+    /// Подія файлової системи від іншого учасника або сервера.
+    FsEvent { event: FileSystemEvent },
+    /// Позиція курсора іншого учасника.
+    Cursor {
+        conn_id: Uuid,
+        #[serde(flatten)]
+        cursor: CursorState,
+    },
+    /// Роль цього підключення змінено.
+    RoleChanged { role: SessionRole },
+    /// Видима область учасника, за яким стежить клієнт.
+    Viewport {
+        conn_id: Uuid,
+        #[serde(flatten)]
+        viewport: Viewport,
+    },
+    /// Сторінка історії чату (від старіших до новіших).
+    ChatHistory {
+        messages: Vec<ChatMessageRow>,
+        /// Чи є ще старіші повідомлення.
+        has_more: bool,
+    },
+    /// Нове повідомлення чату.
+    ChatMessage { message: ChatMessageRow },
+    /// Повідомлення чату відредаговано.
+    ChatEdited { message: ChatMessageRow },
+    /// Повідомлення чату видалено.
+    ChatDeleted { message_id: Uuid },
+    /// Нерозглянуті запити на право редагування (менеджеру після рукостискання).
+    AccessRequests { requests: Vec<AccessRequestRow> },
+    /// Новий запит на право редагування (менеджерам).
+    AccessRequested { request: AccessRequestRow },
+    /// Запит розглянуто (менеджерам та автору запиту).
+    AccessRequestResolved {
+        access_request_id: Uuid,
+        user_id: Uuid,
+        approved: bool,
+    },
+    /// Учасник кімнати розпочав запуск.
+    RunStarted {
+        run_id: Uuid,
+        kind: RunKind,
+        interactive: bool,
+        conn_id: Uuid,
+        user_id: Uuid,
+        username: String,
+    },
+    /// Запуск чекає у черзі на вільний обробник.
+    RunQueued { run_id: Uuid, position: usize },
+    /// Фрагмент виводу збирання або виконання.
+    RunOutput {
+        run_id: Uuid,
+        #[serde(flatten)]
+        chunk: OutputChunk,
+    },
+    /// Запуск завершено; останній кадр запуску.
+    RunFinished {
+        run_id: Uuid,
+        status: RunStatus,
+        exit_code: Option<i32>,
+        duration_ms: u64,
+    },
+    /// Хто може запускати код: `editors_only` — лише Editor та Manager, інакше всі учасники.
+    RunPolicy { editors_only: bool },
+}
+
+/// Машинозчитуваний код помилки у фреймі `error`.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Фрейм не є JSON-об'єктом або не містить `type`.
+    MalformedMessage,
+    /// Невідомий тип повідомлення.
+    UnsupportedMessage,
+    /// Версія протоколу клієнта не підтримується.
+    UnsupportedVersion,
+    /// Команду надіслано до `hello`.
+    HandshakeRequired,
+    /// Повідомлення відомого типу з некоректними полями або аргументами.
+    InvalidRequest,
+    /// Недостатньо прав.
+    Forbidden,
+    /// Внутрішня помилка сервера.
+    Internal,
+}
+
+/// Помилка протоколу, що надсилається клієнту у фреймі `error`.
+#[derive(Debug)]
+pub struct ProtocolError {
+    pub request_id: Option<String>,
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(request_id: Option<String>, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { request_id, code, message: message.into() }
+    }
+}
+
+impl From<ProtocolError> for ServerMessage {
+    fn from(err: ProtocolError) -> Self {
+        ServerMessage::Error {
+            request_id: err.request_id,
+            code: err.code,
+            message: err.message,
+        }
+    }
 }
 
 // ─────────────────────────── Pub/Sub ─────────────────────────────────────────
 
-/// Тип повідомлення, що передається через Redis Pub/Sub між репліками сервера.
+/// Подія кімнати, що передається між репліками сервера.
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum PubSubMessage {
     /// Бінарне Automerge sync-повідомлення (base64 encoded для JSON).
     SyncChange {
-        sender_conn_id: Uuid,
         #[serde(with = "base64_bytes")]
         change: Vec<u8>,
     },
     /// Подія файлової системи — синхронізація дерева файлів між клієнтами.
-    FileSystemEvent {
-        sender_conn_id: Uuid,
-        event: FileSystemEvent,
+    FileSystemEvent { event: FileSystemEvent },
+    /// Переміщення курсора учасника-ініціатора.
+    Cursor { cursor: CursorState },
+    /// Актуальний список учасників кімнати з усіх реплік.
+    ParticipantsUpdate { participants: Vec<ParticipantInfo> },
+    /// Змінено доступ користувача до документа: нова роль або `None`, якщо його видалено.
+    AccessChanged {
+        user_id: Uuid,
+        role: Option<SessionRole>,
     },
+    /// Подія чату кімнати.
+    Chat { event: ChatEvent },
+    /// Зміна видимої області учасника-ініціатора для його послідовників.
+    Viewport { viewport: Viewport },
+    /// Створено або розглянуто запит на право редагування.
+    AccessRequest { event: AccessRequestEvent },
+    /// Подія запуску коду учасником кімнати.
+    Run { event: RunEvent },
+    /// Змінено правило, хто може запускати код у документі.
+    RunPolicy { editors_only: bool },
+    /// Запит на скасування запуску, що може виконуватися на іншій репліці.
+    RunCancel {
+        run_id: Uuid,
+        user_id: Uuid,
+        is_manager: bool,
+    },
+}
+
+/// Запис потоку кімнати: подія разом з її джерелом.
+#[derive(Serialize, Deserialize)]
+pub struct RoomEvent {
+    /// Репліка, що опублікувала подію; своїм клієнтам вона доставила її одразу, тому з потоку пропускає.
+    pub replica_id: Uuid,
+    /// Підключення-ініціатор: йому подія не надсилається (`None` — подію створив сервер).
+    pub origin_conn_id: Option<Uuid>,
+    pub message: PubSubMessage,
+}
+
+// ─────────────────────────── Access Request Events ───────────────────────────
+
+/// Зміна запиту на право редагування, що розсилається менеджерам усіх реплік.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AccessRequestEvent {
+    Created { request: AccessRequestRow },
+    Resolved {
+        access_request_id: Uuid,
+        user_id: Uuid,
+        approved: bool,
+    },
+}
+
+impl From<AccessRequestEvent> for ServerMessage {
+    fn from(event: AccessRequestEvent) -> Self {
+        match event {
+            AccessRequestEvent::Created { request } => ServerMessage::AccessRequested { request },
+            AccessRequestEvent::Resolved { access_request_id, user_id, approved } => {
+                ServerMessage::AccessRequestResolved { access_request_id, user_id, approved }
+            }
+        }
+    }
+}
+
+// ─────────────────────────── Chat Events ─────────────────────────────────────
+
+/// Зміна в чаті кімнати, що розсилається учасникам усіх реплік.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChatEvent {
+    Posted { message: ChatMessageRow },
+    Edited { message: ChatMessageRow },
+    Deleted { message_id: Uuid },
+}
+
+impl From<ChatEvent> for ServerMessage {
+    fn from(event: ChatEvent) -> Self {
+        match event {
+            ChatEvent::Posted { message } => ServerMessage::ChatMessage { message },
+            ChatEvent::Edited { message } => ServerMessage::ChatEdited { message },
+            ChatEvent::Deleted { message_id } => ServerMessage::ChatDeleted { message_id },
+        }
+    }
+}
+
+// ─────────────────────────── Run Events ──────────────────────────────────────
+
+impl From<RunEvent> for ServerMessage {
+    fn from(event: RunEvent) -> Self {
+        match event {
+            RunEvent::Started { run_id, kind, interactive, conn_id, user_id, username } => {
+                ServerMessage::RunStarted { run_id, kind, interactive, conn_id, user_id, username }
+            }
+            RunEvent::Queued { run_id, position } => ServerMessage::RunQueued { run_id, position },
+            RunEvent::Output { run_id, chunk } => ServerMessage::RunOutput { run_id, chunk },
+            RunEvent::Finished { run_id, status, exit_code, duration_ms } => {
+                ServerMessage::RunFinished { run_id, status, exit_code, duration_ms }
+            }
+        }
+    }
 }
 
 // ─────────────────────────── FileSystem Events ───────────────────────────────
@@ -258,12 +750,6 @@ pub enum FileSystemEvent {
     Snapshot { files: HashMap<String, String> },
 }
 
-/// Клієнтська обгортка для файлової події, що надходить через WebSocket.
-#[derive(Serialize, Deserialize, Debug)]
-pub struct FileSystemMessage {
-    pub event: FileSystemEvent,
-}
-
 // ─────────────────────────── base64 serde helper ─────────────────────────────
 
 /// Серіалізація Vec<u8> як base64-рядка для передачі через JSON (Redis Pub/Sub).
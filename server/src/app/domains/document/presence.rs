@@ -0,0 +1,134 @@
+use std::time::Duration;
+use tokio::time;
+use uuid::Uuid;
+
+use super::fanout;
+use super::models::{ParticipantInfo, PubSubMessage, Rooms, ServerMessage};
+use super::sessions::SessionStore;
+use crate::app::{RequestResult, ServiceContext};
+use crate::constants::document::PRESENCE_HEARTBEAT_SECONDS;
+use crate::core::app_data::AppData;
+
+// ─────────────────────────── Presence store ──────────────────────────────────
+
+/// Записує учасника до присутності кімнати.
+///
+/// Якщо репліка зупиниться, її записи зникнуть після `PRESENCE_TTL_SECONDS`.
+pub async fn join(doc_id: Uuid, info: &ParticipantInfo, store: &dyn SessionStore) -> RequestResult<()> {
+    store.save_presence(doc_id, info).await
+}
+
+/// Видаляє підключення з присутності кімнати.
+pub async fn leave(doc_id: Uuid, conn_id: Uuid, store: &dyn SessionStore) -> RequestResult<()> {
+    store.remove_presence(doc_id, conn_id).await
+}
+
+/// Повертає запис присутності підключення з будь-якої репліки.
+pub async fn get(doc_id: Uuid, conn_id: Uuid, store: &dyn SessionStore) -> RequestResult<Option<ParticipantInfo>> {
+    store.get_presence(doc_id, conn_id).await
+}
+
+/// Змінює запис присутності підключення. Повертає `None`, якщо запис уже зник.
+pub async fn update<F>(
+    doc_id: Uuid,
+    conn_id: Uuid,
+    store: &dyn SessionStore,
+    apply: F,
+) -> RequestResult<Option<ParticipantInfo>>
+where
+    F: FnOnce(&mut ParticipantInfo),
+{
+    let Some(mut info) = store.get_presence(doc_id, conn_id).await? else {
+        return Ok(None);
+    };
+
+    apply(&mut info);
+    store.save_presence(doc_id, &info).await?;
+    Ok(Some(info))
+}
+
+/// Повертає учасників кімнати з усіх реплік, упорядкованих за часом підключення.
+///
+/// Другий елемент результату — кількість прибраних прострочених підключень.
+pub async fn list(doc_id: Uuid, store: &dyn SessionStore) -> RequestResult<(Vec<ParticipantInfo>, usize)> {
+    store.list_presence(doc_id).await
+}
+
+/// Подовжує TTL записів присутності підключень цієї репліки; зниклі записи створюються заново.
+pub async fn refresh(doc_id: Uuid, local: &[ParticipantInfo], store: &dyn SessionStore) -> RequestResult<()> {
+    store.refresh_presence(doc_id, local).await
+}
+
+// ─────────────────────────── Broadcast ───────────────────────────────────────
+
+/// Розсилає актуальний список учасників кімнати клієнтам усіх реплік.
+///
+/// Якщо сховище сесій недоступне, список з локальних підключень надсилається лише клієнтам цієї репліки.
+pub async fn broadcast_participants(ctx: &ServiceContext<'_>, doc_id: Uuid) {
+    let participants = match list(doc_id, ctx.sessions).await {
+        Ok((participants, _)) => participants,
+        Err(err) => {
+            tracing::warn!("Не вдалося прочитати присутність кімнати {doc_id} : {err}");
+            return broadcast_locally(ctx.rooms, doc_id, ctx.rooms.get_participants(&doc_id));
+        }
+    };
+
+    let message = PubSubMessage::ParticipantsUpdate { participants };
+    if let Err(err) = fanout::publish(ctx, doc_id, None, message).await {
+        tracing::warn!("Не вдалося опублікувати список учасників кімнати {doc_id}: {err}");
+    }
+}
+
+/// Надсилає список учасників усім клієнтам кімнати на цій репліці.
+pub fn broadcast_locally(rooms: &Rooms, doc_id: Uuid, participants: Vec<ParticipantInfo>) {
+    let message = ServerMessage::ParticipantsUpdate { participants };
+    if let Ok(text) = serde_json::to_string(&message) {
+        rooms.broadcast_presence(&doc_id, text);
+    }
+}
+
+// ─────────────────────────── Heartbeat ───────────────────────────────────────
+
+/// Запуск фонового процесу, що подовжує присутність локальних підключень та
+/// повідомляє клієнтів, коли зникають учасники зупинених реплік.
+pub fn run_presence_heartbeat(app_data: &AppData) {
+    let interval = Duration::from_secs(PRESENCE_HEARTBEAT_SECONDS);
+    let cancel_token = app_data.token().child_token();
+    let app_data = app_data.clone();
+
+    actix_rt::spawn(async move {
+        tracing::info!("Запущено heartbeat присутності учасників");
+
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => {
+                    tracing::info!("Зупинка heartbeat присутності учасників");
+                    break;
+                }
+                _ = time::sleep(interval) => {}
+            }
+
+            let rooms: Vec<(Uuid, Vec<ParticipantInfo>)> = app_data
+                .rooms
+                .value
+                .iter()
+                .map(|room| (*room.key(), room.value().iter().map(ParticipantInfo::from).collect()))
+                .collect();
+
+            for (doc_id, local) in rooms {
+                if let Err(err) = refresh(doc_id, &local, app_data.sessions.as_ref()).await {
+                    tracing::warn!("Не вдалося оновити присутність кімнати {doc_id}: {err}");
+                    continue;
+                }
+                match list(doc_id, app_data.sessions.as_ref()).await {
+                    Ok((_, 0)) => {}
+                    Ok((_, pruned)) => {
+                        tracing::info!("З кімнати {doc_id} прибрано {pruned} прострочених учасників");
+                        broadcast_participants(&ServiceContext::from(&app_data), doc_id).await;
+                    }
+                    Err(err) => tracing::warn!("Не вдалося перевірити присутність кімнати {doc_id}: {err}"),
+                }
+            }
+        }
+    });
+}
@@ -2,7 +2,10 @@ use actix_web::web::Bytes;
 use sqlx::PgExecutor;
 use uuid::Uuid;
 
-use super::models::{DocumentRow, ChangeRow, ProjectFileRow, DocumentSummary, SessionRole};
+use super::models::{
+    DocumentRow, ChangeRow, ProjectFileRow, DocumentSummary, SessionRole, TrashEntry, FileRevisionRow,
+    ChatMessageRow, InviteRow, AccessRequestRow,
+};
 use crate::app::RequestResult;
 
 // ─────────────────────────── Documents ───────────────────────────────────────
@@ -78,6 +81,35 @@ where
     Ok(title)
 }
 
+/// Чи можуть запускати код у документі лише Editor та Manager.
+pub async fn get_runs_editors_only<'c, E>(id: Uuid, executor: E) -> RequestResult<bool>
+where
+    E: PgExecutor<'c>,
+{
+    let editors_only = sqlx::query_scalar::<_, bool>(
+        "SELECT runs_editors_only FROM documents WHERE id = $1",
+    )
+    .bind(id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(editors_only)
+}
+
+/// Змінює правило запуску коду в документі.
+pub async fn set_runs_editors_only<'c, E>(id: Uuid, editors_only: bool, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("UPDATE documents SET runs_editors_only = $2 WHERE id = $1")
+        .bind(id)
+        .bind(editors_only)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
 /// Оновлює вміст (snapshot) документа та час останнього оновлення.
 pub async fn update<'c, I, E>(id: Uuid, content: I, executor: E) -> RequestResult<()>
 where
@@ -170,7 +202,9 @@ where
     E: PgExecutor<'c>,
 {
     let rows = sqlx::query_as::<_, ProjectFileRow>(
-        "SELECT id, path, content, is_dir FROM project_files WHERE document_id = $1 ORDER BY path"
+        "SELECT id, path, content, is_dir FROM project_files
+         WHERE document_id = $1 AND deleted_at IS NULL
+         ORDER BY path"
     )
     .bind(doc_id)
     .fetch_all(executor)
@@ -179,143 +213,1057 @@ where
     Ok(rows)
 }
 
-/// Вставляє або оновлює файл проекту (upsert).
+/// Вставляє або оновлює файл проекту (upsert). Повертає ідентифікатор файлу.
 pub async fn upsert_file<'c, E>(
     doc_id: Uuid,
     path: &str,
     content: &str,
     is_dir: bool,
     executor: E,
-) -> RequestResult<()>
+) -> RequestResult<Uuid>
 where
     E: PgExecutor<'c>,
 {
-    sqlx::query(
+    let file_id = sqlx::query_scalar::<_, Uuid>(
         "INSERT INTO project_files (document_id, path, content, is_dir, updated_at)
          VALUES ($1, $2, $3, $4, NOW())
-         ON CONFLICT (document_id, path) DO UPDATE
-         SET content = EXCLUDED.content, is_dir = EXCLUDED.is_dir, updated_at = NOW()"
+         ON CONFLICT (document_id, path) WHERE deleted_at IS NULL DO UPDATE
+         SET content = EXCLUDED.content, is_dir = EXCLUDED.is_dir, updated_at = NOW()
+         RETURNING id"
     )
     .bind(doc_id)
     .bind(path)
     .bind(content)
     .bind(is_dir)
-    .execute(executor)
+    .fetch_one(executor)
     .await?;
 
-    Ok(())
+    Ok(file_id)
 }
 
-/// Видаляє файл проекту за шляхом.
-pub async fn delete_file<'c, E>(doc_id: Uuid, path: &str, executor: E) -> RequestResult<()>
+/// Переміщує файл проекту (разом із вкладеними шляхами) до кошика.
+///
+/// Усі рядки, видалені однією операцією, отримують спільний `trash_id`. Повертає видалені рядки.
+pub async fn delete_file<'c, E>(
+    doc_id: Uuid,
+    path: &str,
+    deleted_by: Option<Uuid>,
+    executor: E,
+) -> RequestResult<Vec<ProjectFileRow>>
 where
     E: PgExecutor<'c>,
 {
-    sqlx::query(
-        "DELETE FROM project_files WHERE document_id = $1 AND (path = $2 OR path LIKE $3)"
+    let rows = sqlx::query_as::<_, ProjectFileRow>(
+        "UPDATE project_files
+         SET deleted_at = NOW(), deleted_by = $4, trash_id = $5
+         WHERE document_id = $1 AND deleted_at IS NULL AND (path = $2 OR path LIKE $3)
+         RETURNING id, path, content, is_dir"
     )
     .bind(doc_id)
     .bind(path)
     .bind(format!("{}/", path) + "%")
-    .execute(executor)
+    .bind(deleted_by)
+    .bind(Uuid::now_v7())
+    .fetch_all(executor)
     .await?;
 
-    Ok(())
+    Ok(rows)
 }
 
-/// Перейменовує файл або директорію проекту.
+/// Перейменовує файл або директорію проекту. Повертає рядки з уже оновленими шляхами.
 pub async fn rename_file<'c, E>(
     doc_id: Uuid,
     old_path: &str,
     new_path: &str,
     executor: E,
-) -> RequestResult<()>
+) -> RequestResult<Vec<ProjectFileRow>>
 where
     E: PgExecutor<'c>,
 {
     // Перейменовуємо сам файл/директорію та всі вкладені шляхи
-    sqlx::query(
+    let rows = sqlx::query_as::<_, ProjectFileRow>(
         "UPDATE project_files
          SET path = $3 || SUBSTRING(path FROM LENGTH($2) + 1), updated_at = NOW()
-         WHERE document_id = $1 AND (path = $2 OR path LIKE $4)"
+         WHERE document_id = $1 AND deleted_at IS NULL AND (path = $2 OR path LIKE $4)
+         RETURNING id, path, content, is_dir"
     )
     .bind(doc_id)
     .bind(old_path)
     .bind(new_path)
     .bind(format!("{}/", old_path) + "%")
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+// ─────────────────────────── Trash ───────────────────────────────────────────
+
+/// Повертає вміст кошика проекту: по одному запису на кожну операцію видалення.
+pub async fn list_trash<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<Vec<TrashEntry>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, TrashEntry>(
+        "SELECT pf.trash_id,
+                MIN(pf.path) AS path,
+                (ARRAY_AGG(pf.is_dir ORDER BY pf.path))[1] AS is_dir,
+                COUNT(*) AS files_count,
+                MAX(pf.deleted_at) AS deleted_at,
+                pf.deleted_by,
+                u.username AS deleted_by_username
+         FROM project_files pf
+         LEFT JOIN users u ON u.id = pf.deleted_by
+         WHERE pf.document_id = $1 AND pf.deleted_at IS NOT NULL
+         GROUP BY pf.trash_id, pf.deleted_by, u.username
+         ORDER BY MAX(pf.deleted_at) DESC"
+    )
+    .bind(doc_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Повертає всі рядки, видалені однією операцією (запис кошика).
+pub async fn get_trash_files<'c, E>(doc_id: Uuid, trash_id: Uuid, executor: E) -> RequestResult<Vec<ProjectFileRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, ProjectFileRow>(
+        "SELECT id, path, content, is_dir FROM project_files
+         WHERE document_id = $1 AND trash_id = $2 AND deleted_at IS NOT NULL
+         ORDER BY path"
+    )
+    .bind(doc_id)
+    .bind(trash_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Повертає шляхи серед переданих, які вже зайняті "живими" файлами проекту.
+pub async fn find_live_paths<'c, E>(doc_id: Uuid, paths: &[String], executor: E) -> RequestResult<Vec<String>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_scalar::<_, String>(
+        "SELECT path FROM project_files
+         WHERE document_id = $1 AND deleted_at IS NULL AND path = ANY($2)"
+    )
+    .bind(doc_id)
+    .bind(paths)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Відновлює рядок з кошика під вказаним шляхом.
+pub async fn restore_file<'c, E>(file_id: Uuid, path: &str, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query(
+        "UPDATE project_files
+         SET path = $2, deleted_at = NULL, deleted_by = NULL, trash_id = NULL, updated_at = NOW()
+         WHERE id = $1"
+    )
+    .bind(file_id)
+    .bind(path)
     .execute(executor)
     .await?;
 
     Ok(())
 }
 
-// ─────────────────────────── Members ─────────────────────────────────────────
+/// Остаточно видаляє запис кошика.
+///
+/// Ревізії файлів лишаються: остання з них — `delete`, тож історія та git-коміти не змінюються.
+pub async fn purge_trash_entry<'c, E>(doc_id: Uuid, trash_id: Uuid, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query(
+        "DELETE FROM project_files
+         WHERE document_id = $1 AND trash_id = $2 AND deleted_at IS NOT NULL"
+    )
+    .bind(doc_id)
+    .bind(trash_id)
+    .execute(executor)
+    .await?;
 
-/// Додає учасника до проекту за user_id.
-pub async fn add_member<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<()>
+    Ok(result.rows_affected())
+}
+
+/// Остаточно видаляє з кошиків усіх проектів файли, що лежать там довше за `retention_secs`.
+///
+/// Як і в `purge_trash_entry`, ревізії файлів не видаляються.
+pub async fn purge_expired_trash<'c, E>(retention_secs: f64, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query(
+        "DELETE FROM project_files
+         WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - make_interval(secs => $1)"
+    )
+    .bind(retention_secs)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// ─────────────────────────── File History ────────────────────────────────────
+
+const REVISION_COLUMNS: &str =
+    "r.id, r.file_id, r.path, r.old_path, r.content, r.kind, r.author_id,
+     u.username AS author_username, r.created_at, r.updated_at";
+
+/// Додає нову ревізію файлу.
+#[allow(clippy::too_many_arguments)]
+pub async fn insert_revision<'c, E>(
+    doc_id: Uuid,
+    file_id: Uuid,
+    path: &str,
+    old_path: Option<&str>,
+    content: &str,
+    kind: &str,
+    author_id: Option<Uuid>,
+    executor: E,
+) -> RequestResult<()>
 where
     E: PgExecutor<'c>,
 {
     sqlx::query(
-        "INSERT INTO document_members (document_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+        "INSERT INTO project_file_revisions (document_id, file_id, path, old_path, content, kind, author_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)"
     )
     .bind(doc_id)
-    .bind(user_id)
+    .bind(file_id)
+    .bind(path)
+    .bind(old_path)
+    .bind(content)
+    .bind(kind)
+    .bind(author_id)
     .execute(executor)
     .await?;
 
     Ok(())
 }
 
-/// Видаляє учасника з проекту.
-pub async fn remove_member<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<()>
+/// Оновлює вміст існуючої ревізії (злиття послідовних правок одного автора).
+///
+/// `created_at` не змінюється, щоб вікно злиття не зсувалося з кожною правкою.
+pub async fn amend_revision<'c, E>(revision_id: Uuid, content: &str, executor: E) -> RequestResult<()>
 where
     E: PgExecutor<'c>,
 {
-    sqlx::query("DELETE FROM document_members WHERE document_id = $1 AND user_id = $2")
-        .bind(doc_id)
-        .bind(user_id)
-        .execute(executor)
-        .await?;
+    sqlx::query(
+        "UPDATE project_file_revisions SET content = $2, updated_at = NOW() WHERE id = $1"
+    )
+    .bind(revision_id)
+    .bind(content)
+    .execute(executor)
+    .await?;
 
     Ok(())
 }
 
-/// Повертає збережену роль учасника документа. None — якщо не є членом.
-pub async fn get_member_role<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<Option<SessionRole>>
+/// Повертає останню ревізію файлу.
+pub async fn last_revision<'c, E>(file_id: Uuid, executor: E) -> RequestResult<Option<FileRevisionRow>>
 where
     E: PgExecutor<'c>,
 {
-    let role_str = sqlx::query_scalar::<_, String>(
-        "SELECT role FROM document_members WHERE document_id = $1 AND user_id = $2"
+    let row = sqlx::query_as::<_, FileRevisionRow>(&format!(
+        "SELECT {REVISION_COLUMNS}
+         FROM project_file_revisions r
+         LEFT JOIN users u ON u.id = r.author_id
+         WHERE r.file_id = $1
+         ORDER BY r.created_at DESC, r.id DESC
+         LIMIT 1"
+    ))
+    .bind(file_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Знаходить файл, який зараз (або востаннє) мав шлях `path`.
+///
+/// Перевага надається живому файлу; якщо його немає — файлу з найсвіжішою ревізією за цим шляхом.
+pub async fn find_file_id_by_path<'c, E>(doc_id: Uuid, path: &str, executor: E) -> RequestResult<Option<Uuid>>
+where
+    E: PgExecutor<'c>,
+{
+    let file_id = sqlx::query_scalar::<_, Uuid>(
+        "SELECT file_id FROM (
+             SELECT id AS file_id, 0 AS priority, updated_at AS at
+             FROM project_files
+             WHERE document_id = $1 AND path = $2 AND deleted_at IS NULL AND NOT is_dir
+             UNION ALL
+             SELECT file_id, 1 AS priority, created_at AS at
+             FROM project_file_revisions
+             WHERE document_id = $1 AND path = $2
+         ) candidates
+         ORDER BY priority, at DESC
+         LIMIT 1"
     )
     .bind(doc_id)
-    .bind(user_id)
+    .bind(path)
     .fetch_optional(executor)
     .await?;
 
-    let role = role_str.map(|r| match r.as_str() {
-        "editor"  => SessionRole::Editor,
-        "manager" => SessionRole::Manager,
-        _         => SessionRole::Reader,
-    });
+    Ok(file_id)
+}
+
+/// Повертає всі ревізії файлу у хронологічному порядку.
+pub async fn get_file_revisions<'c, E>(doc_id: Uuid, file_id: Uuid, executor: E) -> RequestResult<Vec<FileRevisionRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, FileRevisionRow>(&format!(
+        "SELECT {REVISION_COLUMNS}
+         FROM project_file_revisions r
+         LEFT JOIN users u ON u.id = r.author_id
+         WHERE r.document_id = $1 AND r.file_id = $2
+         ORDER BY r.created_at, r.id"
+    ))
+    .bind(doc_id)
+    .bind(file_id)
+    .fetch_all(executor)
+    .await?;
 
-    Ok(role)
+    Ok(rows)
 }
 
-/// Оновлює роль учасника документа в БД.
-pub async fn set_member_role<'c, E>(doc_id: Uuid, user_id: Uuid, role: &str, executor: E) -> RequestResult<()>
+/// Повертає всі ревізії файлів документа у хронологічному порядку.
+pub async fn get_document_revisions<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<Vec<FileRevisionRow>>
 where
     E: PgExecutor<'c>,
 {
-    sqlx::query(
-        "UPDATE document_members SET role = $3 WHERE document_id = $1 AND user_id = $2"
+    let rows = sqlx::query_as::<_, FileRevisionRow>(&format!(
+        "SELECT {REVISION_COLUMNS}
+         FROM project_file_revisions r
+         LEFT JOIN users u ON u.id = r.author_id
+         WHERE r.document_id = $1
+         ORDER BY r.created_at, r.id"
+    ))
+    .bind(doc_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Відновлює файли проекту (шлях, вміст) у стані одразу після ревізії `revision_id`.
+///
+/// Для кожного файлу береться його остання ревізія, не новіша за `revision_id`;
+/// файли, видалені на той момент, пропускаються.
+pub async fn get_files_at_revision<'c, E>(doc_id: Uuid, revision_id: Uuid, executor: E) -> RequestResult<Vec<(String, String)>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "WITH version AS (
+             SELECT created_at, id FROM project_file_revisions
+             WHERE document_id = $1 AND id = $2
+         ),
+         latest AS (
+             SELECT DISTINCT ON (r.file_id) r.path, r.content, r.kind
+             FROM project_file_revisions r, version v
+             WHERE r.document_id = $1 AND (r.created_at, r.id) <= (v.created_at, v.id)
+             ORDER BY r.file_id, r.created_at DESC, r.id DESC
+         )
+         SELECT path, content FROM latest WHERE kind <> 'delete'"
     )
     .bind(doc_id)
-    .bind(user_id)
-    .bind(role)
-    .execute(executor)
+    .bind(revision_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Повертає ревізію файлу разом із вмістом.
+pub async fn get_revision<'c, E>(doc_id: Uuid, revision_id: Uuid, executor: E) -> RequestResult<Option<FileRevisionRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, FileRevisionRow>(&format!(
+        "SELECT {REVISION_COLUMNS}
+         FROM project_file_revisions r
+         LEFT JOIN users u ON u.id = r.author_id
+         WHERE r.document_id = $1 AND r.id = $2"
+    ))
+    .bind(doc_id)
+    .bind(revision_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+// ─────────────────────────── Chat ────────────────────────────────────────────
+
+const CHAT_COLUMNS: &str =
+    "m.id, m.author_id, u.username AS author_username, m.body, m.mentions, m.created_at, m.edited_at";
+
+/// Зберігає нове повідомлення чату та повертає його.
+pub async fn insert_chat_message<'c, E>(
+    doc_id: Uuid,
+    author_id: Uuid,
+    body: &str,
+    mentions: &[Uuid],
+    executor: E,
+) -> RequestResult<ChatMessageRow>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, ChatMessageRow>(&format!(
+        "WITH m AS (
+             INSERT INTO chat_messages (document_id, author_id, body, mentions)
+             VALUES ($1, $2, $3, $4)
+             RETURNING *
+         )
+         SELECT {CHAT_COLUMNS} FROM m LEFT JOIN users u ON u.id = m.author_id"
+    ))
+    .bind(doc_id)
+    .bind(author_id)
+    .bind(body)
+    .bind(mentions)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Повертає повідомлення чату документа.
+pub async fn get_chat_message<'c, E>(doc_id: Uuid, message_id: Uuid, executor: E) -> RequestResult<Option<ChatMessageRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, ChatMessageRow>(&format!(
+        "SELECT {CHAT_COLUMNS}
+         FROM chat_messages m
+         LEFT JOIN users u ON u.id = m.author_id
+         WHERE m.document_id = $1 AND m.id = $2"
+    ))
+    .bind(doc_id)
+    .bind(message_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Змінює текст повідомлення та позначає його як відредаговане.
+pub async fn update_chat_message<'c, E>(
+    doc_id: Uuid,
+    message_id: Uuid,
+    body: &str,
+    mentions: &[Uuid],
+    executor: E,
+) -> RequestResult<ChatMessageRow>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, ChatMessageRow>(&format!(
+        "WITH m AS (
+             UPDATE chat_messages SET body = $3, mentions = $4, edited_at = NOW()
+             WHERE document_id = $1 AND id = $2
+             RETURNING *
+         )
+         SELECT {CHAT_COLUMNS} FROM m LEFT JOIN users u ON u.id = m.author_id"
+    ))
+    .bind(doc_id)
+    .bind(message_id)
+    .bind(body)
+    .bind(mentions)
+    .fetch_one(executor)
     .await?;
 
+    Ok(row)
+}
+
+/// Видаляє повідомлення чату.
+pub async fn delete_chat_message<'c, E>(doc_id: Uuid, message_id: Uuid, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("DELETE FROM chat_messages WHERE document_id = $1 AND id = $2")
+        .bind(doc_id)
+        .bind(message_id)
+        .execute(executor)
+        .await?;
+
     Ok(())
 }
+
+/// Повертає до `limit` повідомлень, старших за `before` (або найновіших), від новіших до старіших.
+pub async fn get_chat_page<'c, E>(
+    doc_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+    executor: E,
+) -> RequestResult<Vec<ChatMessageRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, ChatMessageRow>(&format!(
+        "SELECT {CHAT_COLUMNS}
+         FROM chat_messages m
+         LEFT JOIN users u ON u.id = m.author_id
+         WHERE m.document_id = $1 AND ($2::uuid IS NULL OR m.id < $2)
+         ORDER BY m.id DESC
+         LIMIT $3"
+    ))
+    .bind(doc_id)
+    .bind(before)
+    .bind(limit)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Знаходить серед власника та учасників документа користувачів із вказаними іменами.
+pub async fn find_member_ids_by_usernames<'c, E>(doc_id: Uuid, usernames: &[String], executor: E) -> RequestResult<Vec<Uuid>>
+where
+    E: PgExecutor<'c>,
+{
+    let ids = sqlx::query_scalar::<_, Uuid>(
+        "SELECT u.id
+         FROM users u
+         WHERE u.username = ANY($2)
+           AND (
+               u.id = (SELECT owner_id FROM documents WHERE id = $1)
+               OR EXISTS (SELECT 1 FROM document_members dm WHERE dm.document_id = $1 AND dm.user_id = u.id)
+           )
+         ORDER BY u.id"
+    )
+    .bind(doc_id)
+    .bind(usernames)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(ids)
+}
+
+// ─────────────────────────── Members ─────────────────────────────────────────
+
+/// Додає учасника до проекту за user_id.
+pub async fn add_member<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query(
+        "INSERT INTO document_members (document_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+    )
+    .bind(doc_id)
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Додає учасника з вказаною роллю. Повертає `false`, якщо користувач уже є учасником.
+pub async fn add_member_with_role<'c, E>(doc_id: Uuid, user_id: Uuid, role: &str, executor: E) -> RequestResult<bool>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query(
+        "INSERT INTO document_members (document_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"
+    )
+    .bind(doc_id)
+    .bind(user_id)
+    .bind(role)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Видаляє учасника з проекту.
+pub async fn remove_member<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("DELETE FROM document_members WHERE document_id = $1 AND user_id = $2")
+        .bind(doc_id)
+        .bind(user_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Повертає збережену роль учасника документа. None — якщо не є членом.
+pub async fn get_member_role<'c, E>(doc_id: Uuid, user_id: Uuid, executor: E) -> RequestResult<Option<SessionRole>>
+where
+    E: PgExecutor<'c>,
+{
+    let role_str = sqlx::query_scalar::<_, String>(
+        "SELECT role FROM document_members WHERE document_id = $1 AND user_id = $2"
+    )
+    .bind(doc_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(role_str.as_deref().map(SessionRole::from_db))
+}
+
+/// Оновлює роль учасника документа в БД.
+pub async fn set_member_role<'c, E>(doc_id: Uuid, user_id: Uuid, role: &str, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query(
+        "UPDATE document_members SET role = $3 WHERE document_id = $1 AND user_id = $2"
+    )
+    .bind(doc_id)
+    .bind(user_id)
+    .bind(role)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Змінює роль учасника з `from` на `to`. Повертає `false`, якщо учасника немає або його роль уже інша.
+pub async fn change_member_role_from<'c, E>(doc_id: Uuid, user_id: Uuid, from: &str, to: &str, executor: E) -> RequestResult<bool>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query(
+        "UPDATE document_members SET role = $4 WHERE document_id = $1 AND user_id = $2 AND role = $3"
+    )
+    .bind(doc_id)
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// ─────────────────────────── Invites ─────────────────────────────────────────
+
+const INVITE_COLUMNS: &str =
+    "i.id, i.document_id, i.token, i.role, i.max_uses, i.uses, i.expires_at, i.revoked_at,
+     i.created_by, u.username AS created_by_username, i.created_at";
+
+/// Створює запрошення до документа.
+pub async fn insert_invite<'c, E>(
+    doc_id: Uuid,
+    token: &str,
+    role: &str,
+    max_uses: Option<i32>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_by: Uuid,
+    executor: E,
+) -> RequestResult<InviteRow>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, InviteRow>(&format!(
+        "WITH i AS (
+             INSERT INTO document_invites (document_id, token, role, max_uses, expires_at, created_by)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             RETURNING *
+         )
+         SELECT {INVITE_COLUMNS} FROM i LEFT JOIN users u ON u.id = i.created_by"
+    ))
+    .bind(doc_id)
+    .bind(token)
+    .bind(role)
+    .bind(max_uses)
+    .bind(expires_at)
+    .bind(created_by)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Повертає чинні запрошення документа: не відкликані, не прострочені та не вичерпані.
+pub async fn list_active_invites<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<Vec<InviteRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, InviteRow>(&format!(
+        "SELECT {INVITE_COLUMNS}
+         FROM document_invites i
+         LEFT JOIN users u ON u.id = i.created_by
+         WHERE i.document_id = $1
+           AND i.revoked_at IS NULL
+           AND (i.expires_at IS NULL OR i.expires_at > NOW())
+           AND (i.max_uses IS NULL OR i.uses < i.max_uses)
+         ORDER BY i.created_at DESC"
+    ))
+    .bind(doc_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Відкликає запрошення. Повертає кількість змінених рядків.
+pub async fn revoke_invite<'c, E>(doc_id: Uuid, invite_id: Uuid, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query(
+        "UPDATE document_invites SET revoked_at = NOW()
+         WHERE document_id = $1 AND id = $2 AND revoked_at IS NULL"
+    )
+    .bind(doc_id)
+    .bind(invite_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Знаходить запрошення за токеном і блокує його рядок до кінця транзакції.
+pub async fn lock_invite_by_token<'c, E>(token: &str, executor: E) -> RequestResult<Option<InviteRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, InviteRow>(&format!(
+        "SELECT {INVITE_COLUMNS}
+         FROM document_invites i
+         LEFT JOIN users u ON u.id = i.created_by
+         WHERE i.token = $1
+         FOR UPDATE OF i"
+    ))
+    .bind(token)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Збільшує лічильник використань запрошення.
+pub async fn consume_invite<'c, E>(invite_id: Uuid, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("UPDATE document_invites SET uses = uses + 1 WHERE id = $1")
+        .bind(invite_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+// ─────────────────────────── Access requests ─────────────────────────────────
+
+const ACCESS_REQUEST_COLUMNS: &str =
+    "r.id, r.user_id, u.username, r.message, r.status, r.created_at";
+
+/// Створює запит на право редагування. Повертає `None`, якщо нерозглянутий запит користувача вже існує.
+pub async fn insert_access_request<'c, E>(
+    doc_id: Uuid,
+    user_id: Uuid,
+    message: Option<&str>,
+    executor: E,
+) -> RequestResult<Option<AccessRequestRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, AccessRequestRow>(&format!(
+        "WITH r AS (
+             INSERT INTO access_requests (document_id, user_id, message)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (document_id, user_id) WHERE status = 'pending' DO NOTHING
+             RETURNING *
+         )
+         SELECT {ACCESS_REQUEST_COLUMNS} FROM r LEFT JOIN users u ON u.id = r.user_id"
+    ))
+    .bind(doc_id)
+    .bind(user_id)
+    .bind(message)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Повертає нерозглянуті запити документа від старіших до новіших.
+pub async fn list_pending_access_requests<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<Vec<AccessRequestRow>>
+where
+    E: PgExecutor<'c>,
+{
+    let rows = sqlx::query_as::<_, AccessRequestRow>(&format!(
+        "SELECT {ACCESS_REQUEST_COLUMNS}
+         FROM access_requests r
+         LEFT JOIN users u ON u.id = r.user_id
+         WHERE r.document_id = $1 AND r.status = 'pending'
+         ORDER BY r.created_at"
+    ))
+    .bind(doc_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Позначає нерозглянутий запит як схвалений або відхилений.
+/// Повертає ідентифікатор автора запиту або `None`, якщо запит не знайдено чи вже розглянуто.
+pub async fn resolve_access_request<'c, E>(
+    doc_id: Uuid,
+    request_id: Uuid,
+    status: &str,
+    resolved_by: Uuid,
+    executor: E,
+) -> RequestResult<Option<Uuid>>
+where
+    E: PgExecutor<'c>,
+{
+    let user_id = sqlx::query_scalar::<_, Uuid>(
+        "UPDATE access_requests
+         SET status = $3, resolved_by = $4, resolved_at = now()
+         WHERE id = $2 AND document_id = $1 AND status = 'pending'
+         RETURNING user_id"
+    )
+    .bind(doc_id)
+    .bind(request_id)
+    .bind(status)
+    .bind(resolved_by)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(user_id)
+}
+
+// ─────────────────────────── Room events ─────────────────────────────────────
+
+/// Надсилає сповіщення `NOTIFY` в канал подій кімнат.
+pub async fn notify_room_event<'c, E>(channel: &str, payload: &str, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(channel)
+        .bind(payload)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Зберігає подію кімнати, завелику для `NOTIFY`, та повертає її ідентифікатор.
+pub async fn insert_room_event_payload<'c, E>(doc_id: Uuid, payload: &str, executor: E) -> RequestResult<Uuid>
+where
+    E: PgExecutor<'c>,
+{
+    let id = sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO room_event_payloads (document_id, payload) VALUES ($1, $2) RETURNING id"
+    )
+    .bind(doc_id)
+    .bind(payload)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Повертає збережену подію кімнати або `None`, якщо її вже прибрано.
+pub async fn get_room_event_payload<'c, E>(id: Uuid, executor: E) -> RequestResult<Option<String>>
+where
+    E: PgExecutor<'c>,
+{
+    let payload = sqlx::query_scalar::<_, String>("SELECT payload FROM room_event_payloads WHERE id = $1")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(payload)
+}
+
+/// Видаляє збережені події кімнат, старші за `max_age_secs`. Повертає кількість видалених.
+pub async fn purge_room_event_payloads<'c, E>(max_age_secs: f64, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query("DELETE FROM room_event_payloads WHERE created_at < NOW() - make_interval(secs => $1)")
+        .bind(max_age_secs)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// ─────────────────────────── Sessions ────────────────────────────────────────
+
+/// Записує або оновлює присутність підключення з терміном дії `ttl_secs`.
+pub async fn upsert_presence<'c, E>(doc_id: Uuid, conn_id: Uuid, info: &str, ttl_secs: f64, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query(
+        "INSERT INTO room_presence (document_id, conn_id, info, expires_at)
+         VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+         ON CONFLICT (document_id, conn_id) DO UPDATE SET info = EXCLUDED.info, expires_at = EXCLUDED.expires_at"
+    )
+    .bind(doc_id)
+    .bind(conn_id)
+    .bind(info)
+    .bind(ttl_secs)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Видаляє присутність підключення.
+pub async fn delete_presence<'c, E>(doc_id: Uuid, conn_id: Uuid, executor: E) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query("DELETE FROM room_presence WHERE document_id = $1 AND conn_id = $2")
+        .bind(doc_id)
+        .bind(conn_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Повертає непрострочений запис присутності підключення.
+pub async fn get_presence<'c, E>(doc_id: Uuid, conn_id: Uuid, executor: E) -> RequestResult<Option<String>>
+where
+    E: PgExecutor<'c>,
+{
+    let info = sqlx::query_scalar::<_, String>(
+        "SELECT info FROM room_presence WHERE document_id = $1 AND conn_id = $2 AND expires_at > NOW()"
+    )
+    .bind(doc_id)
+    .bind(conn_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(info)
+}
+
+/// Повертає непрострочені записи присутності кімнати, упорядковані за підключенням.
+pub async fn list_presence<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<Vec<String>>
+where
+    E: PgExecutor<'c>,
+{
+    let entries = sqlx::query_scalar::<_, String>(
+        "SELECT info FROM room_presence WHERE document_id = $1 AND expires_at > NOW() ORDER BY conn_id"
+    )
+    .bind(doc_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Видаляє прострочені записи присутності кімнати. Повертає кількість видалених.
+pub async fn delete_expired_presence<'c, E>(doc_id: Uuid, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query("DELETE FROM room_presence WHERE document_id = $1 AND expires_at <= NOW()")
+        .bind(doc_id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Подовжує присутність підключень кімнати. Повертає підключення, записи яких ще існували.
+pub async fn touch_presence<'c, E>(doc_id: Uuid, conn_ids: &[Uuid], ttl_secs: f64, executor: E) -> RequestResult<Vec<Uuid>>
+where
+    E: PgExecutor<'c>,
+{
+    let touched = sqlx::query_scalar::<_, Uuid>(
+        "UPDATE room_presence SET expires_at = NOW() + make_interval(secs => $3)
+         WHERE document_id = $1 AND conn_id = ANY($2)
+         RETURNING conn_id"
+    )
+    .bind(doc_id)
+    .bind(conn_ids)
+    .bind(ttl_secs)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(touched)
+}
+
+/// Зберігає квиток підключення до WebSocket з терміном дії `ttl_secs`.
+pub async fn insert_ws_ticket<'c, E>(
+    ticket: &str,
+    doc_id: Uuid,
+    user_id: Uuid,
+    username: &str,
+    ttl_secs: f64,
+    executor: E,
+) -> RequestResult<()>
+where
+    E: PgExecutor<'c>,
+{
+    sqlx::query(
+        "INSERT INTO ws_tickets (ticket, document_id, user_id, username, expires_at)
+         VALUES ($1, $2, $3, $4, NOW() + make_interval(secs => $5))"
+    )
+    .bind(ticket)
+    .bind(doc_id)
+    .bind(user_id)
+    .bind(username)
+    .bind(ttl_secs)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Видаляє квиток і повертає його документ, користувача та ім'я, якщо квиток ще дійсний.
+///
+/// `DELETE ... RETURNING` атомарний, тож одночасне використання одного квитка вдається лише раз.
+pub async fn take_ws_ticket<'c, E>(ticket: &str, executor: E) -> RequestResult<Option<(Uuid, Uuid, String)>>
+where
+    E: PgExecutor<'c>,
+{
+    let row = sqlx::query_as::<_, (Uuid, Uuid, String)>(
+        "DELETE FROM ws_tickets WHERE ticket = $1 AND expires_at > NOW() RETURNING document_id, user_id, username"
+    )
+    .bind(ticket)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(row)
+}
+
+/// Видаляє записи присутності всіх кімнат, прострочені понад `grace_secs`. Повертає кількість видалених.
+pub async fn purge_expired_presence<'c, E>(grace_secs: f64, executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query("DELETE FROM room_presence WHERE expires_at < NOW() - make_interval(secs => $1)")
+        .bind(grace_secs)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Видаляє прострочені квитки підключення. Повертає кількість видалених.
+pub async fn purge_expired_ws_tickets<'c, E>(executor: E) -> RequestResult<u64>
+where
+    E: PgExecutor<'c>,
+{
+    let result = sqlx::query("DELETE FROM ws_tickets WHERE expires_at <= NOW()")
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
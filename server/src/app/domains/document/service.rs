@@ -1,15 +1,25 @@
 use actix_web::web::Bytes;
 use actix_ws::Session;
 use automerge::{AutoCommit, sync::SyncDoc};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use std::{collections::HashMap, time::Duration};
 use tokio::time;
 use uuid::Uuid;
 
-use super::models::{ChangeRow, DocumentResponse, DocumentSummary, PubSubMessage};
+use super::models::{
+    AcceptInviteResponse, AccessRequestEvent, WsTicket, WsTicketResponse, AccessRequestRow, ChangeRow, ChatEvent, CreateInviteRequest, InviteRow, ChatMessageRow, DocumentResponse, DocumentSummary, FileHistoryEntry, FileRevisionRow, FileSystemEvent,
+    PubSubMessage, RestoreConflict, ServerMessage, RestoreTrashRequest, RevisionKind, RunPolicy, SessionRole, TrashEntry,
+};
+use super::fanout;
 use super::repository;
 use crate::core::app_data::AppData;
 use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::app::domains::execution::models::RunEvent;
+use crate::app::domains::execution::service::is_path_safe;
+use crate::constants::ws::WS_TICKET_TTL_SECONDS;
+use crate::constants::document::{
+    ACCESS_REQUEST_MAX_MESSAGE_CHARS, CHAT_HISTORY_MAX_PAGE_SIZE, CHAT_HISTORY_PAGE_SIZE, CHAT_MAX_MESSAGE_CHARS, INVITE_MAX_EXPIRES_IN_HOURS,
+};
 
 // ─────────────────────────── Document CRUD ───────────────────────────────────
 
@@ -26,11 +36,17 @@ where
     let doc_id = repository::create(title, content, owner_id, ctx.db_pool).await?;
 
     // Створюємо дефолтний main.rs для нового проекту
-    repository::upsert_file(
+    let main_path = "src/main.rs";
+    let main_content = "fn main() {\n    println!(\"Hello, world!\");\n}";
+    let file_id = repository::upsert_file(doc_id, main_path, main_content, false, ctx.db_pool).await?;
+    repository::insert_revision(
         doc_id,
-        "src/main.rs",
-        "fn main() {\n    println!(\"Hello, world!\");\n}",
-        false,
+        file_id,
+        main_path,
+        None,
+        main_content,
+        RevisionKind::Edit.as_str(),
+        Some(owner_id),
         ctx.db_pool,
     )
     .await?;
@@ -73,34 +89,418 @@ pub async fn get_project_files(
     Ok(map)
 }
 
-/// Зберігає подію файлової системи у БД.
+/// Повертає файли проекту у стані одразу після ревізії `version` (`None` — поточний стан).
+///
+/// Минулий стан відновлюється з історії файлів, тому порожніх директорій у ньому немає.
+pub async fn get_project_files_at(
+    doc_id: Uuid,
+    version: Option<Uuid>,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<HashMap<String, String>> {
+    let Some(revision_id) = version else {
+        return get_project_files(doc_id, ctx).await;
+    };
+    if repository::get_revision(doc_id, revision_id, ctx.db_pool).await?.is_none() {
+        return Err(RequestError::not_found("Версію проекту не знайдено"));
+    }
+    let rows = repository::get_files_at_revision(doc_id, revision_id, ctx.db_pool).await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Зберігає подію файлової системи у БД та записує ревізії змінених файлів від імені `user_id`.
+/// Видалені файли потрапляють до кошика.
 pub async fn save_fs_event(
     doc_id: Uuid,
-    event: &super::models::FileSystemEvent,
+    event: &FileSystemEvent,
+    user_id: Uuid,
     ctx: &ServiceContext<'_>,
 ) -> RequestResult<()> {
-    use super::models::FileSystemEvent;
+    let mut tx = ctx.db_pool.begin().await?;
+    let author_id = Some(user_id);
 
     match event {
         FileSystemEvent::Upsert { path, content, is_dir } => {
-            repository::upsert_file(doc_id, path, content, *is_dir, ctx.db_pool).await?;
+            let file_id = repository::upsert_file(doc_id, path, content, *is_dir, &mut *tx).await?;
+            if !*is_dir {
+                record_revision(&mut tx, doc_id, file_id, path, None, content, RevisionKind::Edit, author_id).await?;
+            }
         }
         FileSystemEvent::Delete { path } => {
-            repository::delete_file(doc_id, path, ctx.db_pool).await?;
+            let rows = repository::delete_file(doc_id, path, author_id, &mut *tx).await?;
+            for row in rows.iter().filter(|r| !r.is_dir) {
+                record_revision(&mut tx, doc_id, row.id, &row.path, None, &row.content, RevisionKind::Delete, author_id).await?;
+            }
         }
         FileSystemEvent::Rename { old_path, new_path } => {
-            repository::rename_file(doc_id, old_path, new_path, ctx.db_pool).await?;
+            let rows = repository::rename_file(doc_id, old_path, new_path, &mut *tx).await?;
+            for row in rows.iter().filter(|r| !r.is_dir) {
+                let previous = reroot_path(&row.path, new_path, old_path);
+                record_revision(
+                    &mut tx, doc_id, row.id, &row.path, Some(&previous), &row.content, RevisionKind::Rename, author_id,
+                )
+                .await?;
+            }
         }
         FileSystemEvent::Snapshot { files } => {
             for (path, content) in files {
-                repository::upsert_file(doc_id, path, content, false, ctx.db_pool).await?;
+                let file_id = repository::upsert_file(doc_id, path, content, false, &mut *tx).await?;
+                record_revision(&mut tx, doc_id, file_id, path, None, content, RevisionKind::Edit, author_id).await?;
             }
         }
     }
 
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Розсилає подію файлової системи клієнтам усіх реплік, окрім підключення-ініціатора `origin`.
+pub async fn publish_fs_event(
+    doc_id: Uuid,
+    origin: Option<Uuid>,
+    event: FileSystemEvent,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<()> {
+    let pubsub_msg = PubSubMessage::FileSystemEvent { event };
+    fanout::publish(ctx, doc_id, origin, pubsub_msg).await
+}
+
+// ─────────────────────────── Trash ───────────────────────────────────────────
+
+/// Переносить шлях `path` з кореня `old_root` під новий корінь `new_root`.
+pub(crate) fn reroot_path(path: &str, old_root: &str, new_root: &str) -> String {
+    match path.strip_prefix(old_root) {
+        Some(rest) => format!("{}{}", new_root, rest),
+        None => path.to_string(),
+    }
+}
+
+/// Будує вільне ім'я для відновленого файлу: `src/parser.rs` → `src/parser (restored).rs`,
+/// для наступних спроб — `src/parser (restored 2).rs`.
+pub(crate) fn restored_path(path: &str, attempt: u32) -> String {
+    let suffix = if attempt <= 1 {
+        " (restored)".to_string()
+    } else {
+        format!(" (restored {})", attempt)
+    };
+
+    let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = name_start + dot;
+            format!("{}{}{}", &path[..dot], suffix, &path[dot..])
+        }
+        _ => format!("{}{}", path, suffix),
+    }
+}
+
+/// Визначає роль користувача в документі: власник — Manager, інакше збережена роль учасника.
+/// `None` — користувач не є ні власником, ні учасником.
+pub async fn get_user_role(
+    doc_id: Uuid,
+    user_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<Option<SessionRole>> {
+    let doc = repository::read(doc_id, ctx.db_pool).await?;
+    if doc.owner_id == Some(user_id) {
+        return Ok(Some(SessionRole::Manager));
+    }
+    repository::get_member_role(doc_id, user_id, ctx.db_pool).await
+}
+
+/// Повертає вміст кошика проекту.
+pub async fn list_trash(doc_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<Vec<TrashEntry>> {
+    repository::list_trash(doc_id, ctx.db_pool).await
+}
+
+/// Відновлює запис кошика та повідомляє учасників кімнати про відновлені файли.
+///
+/// Якщо цільовий шлях уже зайнятий, поведінка визначається `req.on_conflict`.
+pub async fn restore_from_trash(
+    doc_id: Uuid,
+    trash_id: Uuid,
+    req: RestoreTrashRequest,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<Vec<FileSystemEvent>> {
+    let can_edit = get_user_role(doc_id, requester_id, ctx).await?
+        .map(|r| r.can_edit())
+        .unwrap_or(false);
+    if !can_edit {
+        return Err(RequestError::forbidden("Недостатньо прав для відновлення файлів"));
+    }
+
+    let mut tx = ctx.db_pool.begin().await?;
+
+    let rows = repository::get_trash_files(doc_id, trash_id, &mut *tx).await?;
+    let old_root = rows
+        .first()
+        .map(|r| r.path.clone())
+        .ok_or_else(|| RequestError::not_found("Запис кошика не знайдено"))?;
+
+    let mut new_root = req.new_path.clone().unwrap_or_else(|| old_root.clone());
+    if new_root.is_empty() || !is_path_safe(&new_root) {
+        return Err(RequestError::bad_request(format!("Неприпустимий шлях для відновлення: {}", new_root)));
+    }
+
+    let target_paths = |root: &str| -> Vec<String> {
+        rows.iter().map(|r| reroot_path(&r.path, &old_root, root)).collect()
+    };
+
+    let mut events = Vec::new();
+    let conflicts = repository::find_live_paths(doc_id, &target_paths(&new_root), &mut *tx).await?;
+    if !conflicts.is_empty() {
+        match req.on_conflict {
+            RestoreConflict::Fail => {
+                return Err(RequestError::conflict(format!(
+                    "Шлях '{}' уже зайнятий: {}",
+                    new_root,
+                    conflicts.join(", ")
+                )));
+            }
+            RestoreConflict::Rename => {
+                let base = new_root.clone();
+                let mut attempt = 1;
+                loop {
+                    let candidate = restored_path(&base, attempt);
+                    let taken = repository::find_live_paths(doc_id, &target_paths(&candidate), &mut *tx).await?;
+                    if taken.is_empty() {
+                        new_root = candidate;
+                        break;
+                    }
+                    attempt += 1;
+                    if attempt > 100 {
+                        return Err(RequestError::conflict("Не вдалося підібрати вільне ім'я для відновлення"));
+                    }
+                }
+            }
+            RestoreConflict::Overwrite => {
+                for path in conflicts {
+                    let removed = repository::delete_file(doc_id, &path, Some(requester_id), &mut *tx).await?;
+                    for row in removed.iter().filter(|r| !r.is_dir) {
+                        record_revision(
+                            &mut tx, doc_id, row.id, &row.path, None, &row.content, RevisionKind::Delete, Some(requester_id),
+                        )
+                        .await?;
+                    }
+                    events.push(FileSystemEvent::Delete { path });
+                }
+            }
+        }
+    }
+
+    for row in rows {
+        let path = reroot_path(&row.path, &old_root, &new_root);
+        repository::restore_file(row.id, &path, &mut *tx).await?;
+        if !row.is_dir {
+            let previous = (path != row.path).then_some(row.path.as_str());
+            record_revision(
+                &mut tx, doc_id, row.id, &path, previous, &row.content, RevisionKind::Restore, Some(requester_id),
+            )
+            .await?;
+        }
+        events.push(FileSystemEvent::Upsert { path, content: row.content, is_dir: row.is_dir });
+    }
+
+    tx.commit().await?;
+
+    for event in &events {
+        if let Err(e) = publish_fs_event(doc_id, None, event.clone(), ctx).await {
+            tracing::error!("Не вдалося опублікувати відновлений файл: {e}");
+        }
+    }
+
+    Ok(events)
+}
+
+/// Остаточно видаляє запис кошика (тільки Manager).
+pub async fn purge_trash_entry(
+    doc_id: Uuid,
+    trash_id: Uuid,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<()> {
+    let can_manage = get_user_role(doc_id, requester_id, ctx).await?
+        .map(|r| r.can_manage())
+        .unwrap_or(false);
+    if !can_manage {
+        return Err(RequestError::forbidden("Тільки Manager може очищати кошик"));
+    }
+
+    let removed = repository::purge_trash_entry(doc_id, trash_id, ctx.db_pool).await?;
+    if removed == 0 {
+        return Err(RequestError::not_found("Запис кошика не знайдено"));
+    }
     Ok(())
 }
 
+/// Запуск фонового процесу, що остаточно видаляє файли, які пролежали в кошику довше `retention`.
+pub fn run_trash_purge(app_data: &AppData, retention: Duration) {
+    let interval = Duration::from_secs(crate::constants::document::TRASH_PURGE_INTERVAL_SECONDS);
+    let cancel_token = app_data.token().child_token();
+    let pool = app_data.pool.clone();
+
+    actix_rt::spawn(async move {
+        tracing::info!("Запущено фонове очищення кошика (зберігання: {} с)", retention.as_secs());
+
+        loop {
+            match repository::purge_expired_trash(retention.as_secs_f64(), &pool).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!("З кошика остаточно видалено {removed} записів"),
+                Err(err) => tracing::error!("Помилка очищення кошика: {err:?}"),
+            }
+
+            tokio::select! {
+                _ = cancel_token.cancelled() => {
+                    tracing::info!("Зупинка фонового очищення кошика");
+                    break;
+                }
+                _ = time::sleep(interval) => {}
+            }
+        }
+    });
+}
+
+// ─────────────────────────── File History ────────────────────────────────────
+
+/// Що зробити з новою ревізією файлу з огляду на останню збережену ревізію.
+#[derive(Debug, PartialEq)]
+pub(crate) enum RevisionAction {
+    /// Вміст не змінився — ревізія не потрібна.
+    Skip,
+    /// Продовження правок того самого автора — оновити існуючу ревізію.
+    Amend(Uuid),
+    /// Записати нову ревізію.
+    Insert,
+}
+
+/// Визначає, чи потрібна нова ревізія, чи правку можна злити з останньою.
+///
+/// Зливаються лише правки вмісту одного автора, зроблені протягом `REVISION_COALESCE_SECONDS`
+/// від створення останньої ревізії, щоб збереження з клієнта кожні кількасот мілісекунд
+/// не засмічували історію. Вікно не подовжується злитими правками, тож безперервна робота
+/// все одно ділиться на ревізії.
+pub(crate) fn revision_action(
+    last: Option<&FileRevisionRow>,
+    kind: RevisionKind,
+    content: &str,
+    author_id: Option<Uuid>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> RevisionAction {
+    let Some(last) = last else {
+        return RevisionAction::Insert;
+    };
+    if kind != RevisionKind::Edit {
+        return RevisionAction::Insert;
+    }
+    if last.content == content {
+        return RevisionAction::Skip;
+    }
+
+    let window = chrono::Duration::seconds(crate::constants::document::REVISION_COALESCE_SECONDS);
+    let same_session = last.kind == RevisionKind::Edit.as_str()
+        && author_id.is_some()
+        && last.author_id == author_id
+        && now - last.created_at < window;
+
+    if same_session {
+        RevisionAction::Amend(last.id)
+    } else {
+        RevisionAction::Insert
+    }
+}
+
+/// Записує ревізію файлу в межах транзакції.
+#[allow(clippy::too_many_arguments)]
+async fn record_revision(
+    conn: &mut PgConnection,
+    doc_id: Uuid,
+    file_id: Uuid,
+    path: &str,
+    old_path: Option<&str>,
+    content: &str,
+    kind: RevisionKind,
+    author_id: Option<Uuid>,
+) -> RequestResult<()> {
+    let last = repository::last_revision(file_id, &mut *conn).await?;
+    match revision_action(last.as_ref(), kind, content, author_id, chrono::Utc::now()) {
+        RevisionAction::Skip => Ok(()),
+        RevisionAction::Amend(revision_id) => repository::amend_revision(revision_id, content, &mut *conn).await,
+        RevisionAction::Insert => {
+            repository::insert_revision(doc_id, file_id, path, old_path, content, kind.as_str(), author_id, &mut *conn)
+                .await
+        }
+    }
+}
+
+/// Будує unified diff між двома версіями файлу.
+///
+/// Для перейменування без зміни вмісту повертається лише заголовок зі старим і новим шляхом.
+pub(crate) fn unified_diff(old_path: &str, new_path: &str, old: &str, new: &str) -> String {
+    let diff = similar::TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_path, new_path)
+        .to_string();
+
+    if diff.is_empty() && old_path != new_path {
+        return format!("--- {}\n+++ {}\n", old_path, new_path);
+    }
+    diff
+}
+
+/// Будує хронологію ревізій: кожен запис містить diff відносно попередньої ревізії.
+pub(crate) fn build_timeline(revisions: Vec<FileRevisionRow>) -> Vec<FileHistoryEntry> {
+    let mut previous: Option<(String, String)> = None;
+    let mut timeline = Vec::with_capacity(revisions.len());
+
+    for rev in revisions {
+        let (old_path, old_content) = previous
+            .take()
+            .unwrap_or_else(|| ("/dev/null".to_string(), String::new()));
+        let diff = unified_diff(&old_path, &rev.path, &old_content, &rev.content);
+        previous = Some((rev.path.clone(), rev.content));
+
+        timeline.push(FileHistoryEntry {
+            id: rev.id,
+            path: rev.path,
+            old_path: rev.old_path,
+            kind: rev.kind,
+            author_id: rev.author_id,
+            author_username: rev.author_username,
+            created_at: rev.created_at,
+            updated_at: rev.updated_at,
+            diff,
+        });
+    }
+
+    timeline
+}
+
+/// Повертає історію файлу, що зараз (або востаннє) мав шлях `path`, включно з перейменуваннями.
+pub async fn file_history(
+    doc_id: Uuid,
+    path: &str,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<Vec<FileHistoryEntry>> {
+    let file_id = repository::find_file_id_by_path(doc_id, path, ctx.db_pool)
+        .await?
+        .ok_or_else(|| RequestError::not_found(format!("Історію файлу '{}' не знайдено", path)))?;
+
+    let revisions = repository::get_file_revisions(doc_id, file_id, ctx.db_pool).await?;
+    Ok(build_timeline(revisions))
+}
+
+/// Повертає ревізію файлу разом із вмістом на той момент.
+pub async fn get_file_revision(
+    doc_id: Uuid,
+    revision_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<FileRevisionRow> {
+    repository::get_revision(doc_id, revision_id, ctx.db_pool)
+        .await?
+        .ok_or_else(|| RequestError::not_found("Ревізію файлу не знайдено"))
+}
+
 // ─────────────────────────── Members ─────────────────────────────────────────
 
 /// Додає учасника до проекту за username.
@@ -142,9 +542,498 @@ pub async fn remove_member(
     }
 
     repository::remove_member(doc_id, target_user_id, ctx.db_pool).await?;
+    publish_access_change(doc_id, target_user_id, None, ctx).await;
+    Ok(())
+}
+
+/// Змінює збережену роль учасника та застосовує її до його відкритих сесій на всіх репліках.
+pub async fn change_member_role(
+    doc_id: Uuid,
+    target_user_id: Uuid,
+    new_role: SessionRole,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<()> {
+    let doc = repository::read(doc_id, ctx.db_pool).await?;
+    if doc.owner_id == Some(target_user_id) {
+        return Err(RequestError::forbidden("Не можна змінити роль власника документа"));
+    }
+
+    repository::set_member_role(doc_id, target_user_id, new_role.as_str(), ctx.db_pool).await?;
+    publish_access_change(doc_id, target_user_id, Some(new_role), ctx).await;
+    Ok(())
+}
+
+/// Повідомляє всі репліки про зміну доступу користувача: нова роль або `None`, якщо доступ відкликано.
+///
+/// Викликається після того, як зміну вже збережено, тому помилка публікації лише логується.
+pub async fn publish_access_change(
+    doc_id: Uuid,
+    user_id: Uuid,
+    role: Option<SessionRole>,
+    ctx: &ServiceContext<'_>,
+) {
+    let pubsub_msg = PubSubMessage::AccessChanged { user_id, role };
+    if let Err(err) = fanout::publish(ctx, doc_id, None, pubsub_msg).await {
+        tracing::warn!("Не вдалося опублікувати зміну доступу до документа {doc_id} для інших реплік: {err}");
+    }
+}
+
+// ─────────────────────────── Invites ─────────────────────────────────────────
+
+/// Перевіряє, що користувач має роль Manager у документі.
+async fn require_manager(doc_id: Uuid, user_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<()> {
+    match get_user_role(doc_id, user_id, ctx).await? {
+        Some(role) if role.can_manage() => Ok(()),
+        _ => Err(RequestError::forbidden("Дія доступна лише учасникам з роллю Manager")),
+    }
+}
+
+/// Генерує випадковий секрет для посилань-запрошень та квитків підключення.
+fn generate_secret() -> String {
+    use base64::Engine as _;
+    use rand::RngCore as _;
+
+    let mut bytes = [0u8; 24];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Перевіряє, що запрошення ще можна використати.
+pub(crate) fn check_invite(invite: &InviteRow, now: chrono::DateTime<chrono::Utc>) -> RequestResult<()> {
+    if invite.revoked_at.is_some() {
+        return Err(RequestError::forbidden("Запрошення відкликано"));
+    }
+    if invite.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(RequestError::forbidden("Термін дії запрошення минув"));
+    }
+    if invite.max_uses.is_some_and(|max_uses| invite.uses >= max_uses) {
+        return Err(RequestError::forbidden("Запрошення вже використано максимальну кількість разів"));
+    }
+    Ok(())
+}
+
+/// Обчислює момент закінчення запрошення через `hours` годин від `now` (`None` — безстрокове).
+pub(crate) fn invite_expiry(
+    now: chrono::DateTime<chrono::Utc>,
+    hours: Option<i64>,
+) -> RequestResult<Option<chrono::DateTime<chrono::Utc>>> {
+    let Some(hours) = hours else {
+        return Ok(None);
+    };
+    if !(1..=INVITE_MAX_EXPIRES_IN_HOURS).contains(&hours) {
+        return Err(RequestError::bad_request(format!(
+            "Термін дії має бути від 1 до {INVITE_MAX_EXPIRES_IN_HOURS} годин"
+        )));
+    }
+    chrono::TimeDelta::try_hours(hours)
+        .and_then(|delta| now.checked_add_signed(delta))
+        .map(Some)
+        .ok_or_else(|| RequestError::bad_request("Термін дії запрошення завеликий"))
+}
+
+/// Створює запрошення з роллю, обмеженням використань та терміном дії (тільки Manager).
+///
+/// Запрошення з роллю Manager може створити лише власник документа — так само, як лише він
+/// додає та вилучає учасників; інакше кожен менеджер міг би необмежено роздавати права менеджера.
+pub async fn create_invite(
+    doc_id: Uuid,
+    req: CreateInviteRequest,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<InviteRow> {
+    require_manager(doc_id, requester_id, ctx).await?;
+    if req.role.can_manage() {
+        let doc = repository::read(doc_id, ctx.db_pool).await?;
+        if doc.owner_id != Some(requester_id) {
+            return Err(RequestError::forbidden("Запрошення з роллю Manager може створити лише власник документа"));
+        }
+    }
+
+    if req.max_uses.is_some_and(|max_uses| max_uses <= 0) {
+        return Err(RequestError::bad_request("Кількість використань має бути додатною"));
+    }
+    let expires_at = invite_expiry(chrono::Utc::now(), req.expires_in_hours)?;
+
+    let token = generate_secret();
+    repository::insert_invite(
+        doc_id, &token, req.role.as_str(), req.max_uses, expires_at, requester_id, ctx.db_pool,
+    )
+    .await
+}
+
+/// Повертає чинні запрошення документа (тільки Manager).
+pub async fn list_invites(doc_id: Uuid, requester_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<Vec<InviteRow>> {
+    require_manager(doc_id, requester_id, ctx).await?;
+    repository::list_active_invites(doc_id, ctx.db_pool).await
+}
+
+/// Відкликає запрошення (тільки Manager).
+pub async fn revoke_invite(
+    doc_id: Uuid,
+    invite_id: Uuid,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<()> {
+    require_manager(doc_id, requester_id, ctx).await?;
+    if repository::revoke_invite(doc_id, invite_id, ctx.db_pool).await? == 0 {
+        return Err(RequestError::not_found("Запрошення не знайдено"));
+    }
     Ok(())
 }
 
+/// Приймає запрошення: додає користувача до учасників документа з роллю запрошення.
+///
+/// Якщо користувач уже має доступ до документа, його роль не змінюється і використання не рахується.
+pub async fn accept_invite(token: &str, user_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<AcceptInviteResponse> {
+    let mut tx = ctx.db_pool.begin().await?;
+
+    let invite = repository::lock_invite_by_token(token, &mut *tx)
+        .await?
+        .ok_or_else(|| RequestError::not_found("Запрошення не знайдено"))?;
+    check_invite(&invite, chrono::Utc::now())?;
+
+    let doc = repository::read(invite.document_id, &mut *tx).await?;
+    let existing = if doc.owner_id == Some(user_id) {
+        Some(SessionRole::Manager)
+    } else {
+        repository::get_member_role(invite.document_id, user_id, &mut *tx).await?
+    };
+
+    let role = match existing {
+        Some(role) => role,
+        None => {
+            repository::add_member_with_role(invite.document_id, user_id, &invite.role, &mut *tx).await?;
+            repository::consume_invite(invite.id, &mut *tx).await?;
+            SessionRole::from_db(&invite.role)
+        }
+    };
+
+    tx.commit().await?;
+    Ok(AcceptInviteResponse { document_id: invite.document_id, role })
+}
+
+// ─────────────────────────── WebSocket tickets ───────────────────────────────
+
+/// Видає одноразовий короткоживучий квиток для підключення користувача до WebSocket документа.
+pub async fn issue_ws_ticket(
+    doc_id: Uuid,
+    user_id: Uuid,
+    username: &str,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<WsTicketResponse> {
+    if get_user_role(doc_id, user_id, ctx).await?.is_none() {
+        return Err(RequestError::forbidden("Немає доступу до документа"));
+    }
+
+    let ticket = generate_secret();
+    let data = WsTicket { doc_id, user_id, username: username.to_string() };
+    ctx.sessions.put_ticket(&ticket, &data, WS_TICKET_TTL_SECONDS).await?;
+
+    Ok(WsTicketResponse { ticket, expires_in: WS_TICKET_TTL_SECONDS })
+}
+
+/// Використовує квиток підключення: він видаляється зі сховища сесій, тому повторне використання неможливе.
+pub async fn redeem_ws_ticket(doc_id: Uuid, ticket: &str, ctx: &ServiceContext<'_>) -> RequestResult<WsTicket> {
+    let invalid = || RequestError::unauthorized("Квиток недійсний, прострочений або вже використаний");
+
+    let data = ctx.sessions.take_ticket(ticket).await?.ok_or_else(invalid)?;
+    if data.doc_id != doc_id {
+        return Err(RequestError::unauthorized("Квиток видано для іншого документа"));
+    }
+    Ok(data)
+}
+
+// ─────────────────────────── Access requests ─────────────────────────────────
+
+/// Перевіряє коментар до запиту на право редагування; порожній коментар вважається відсутнім.
+pub(crate) fn normalize_access_message(message: Option<&str>) -> RequestResult<Option<&str>> {
+    let Some(message) = message.map(str::trim).filter(|m| !m.is_empty()) else {
+        return Ok(None);
+    };
+    if message.chars().count() > ACCESS_REQUEST_MAX_MESSAGE_CHARS {
+        return Err(RequestError::bad_request(format!(
+            "Коментар довший за {ACCESS_REQUEST_MAX_MESSAGE_CHARS} символів"
+        )));
+    }
+    Ok(Some(message))
+}
+
+/// Зберігає запит читача на право редагування.
+pub async fn request_edit_access(
+    doc_id: Uuid,
+    user_id: Uuid,
+    message: Option<&str>,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<AccessRequestEvent> {
+    match get_user_role(doc_id, user_id, ctx).await? {
+        None => return Err(RequestError::forbidden("Немає доступу до документа")),
+        Some(role) if role.can_edit() => {
+            return Err(RequestError::bad_request("Ви вже маєте право редагування"));
+        }
+        Some(_) => {}
+    }
+
+    let message = normalize_access_message(message)?;
+    let request = repository::insert_access_request(doc_id, user_id, message, ctx.db_pool)
+        .await?
+        .ok_or_else(|| RequestError::conflict("Запит уже очікує на розгляд"))?;
+    Ok(AccessRequestEvent::Created { request })
+}
+
+/// Повертає нерозглянуті запити на право редагування (тільки Manager).
+pub async fn pending_access_requests(
+    doc_id: Uuid,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<Vec<AccessRequestRow>> {
+    require_manager(doc_id, requester_id, ctx).await?;
+    repository::list_pending_access_requests(doc_id, ctx.db_pool).await
+}
+
+/// Схвалює або відхиляє запит (тільки Manager).
+///
+/// Схвалення підвищує роль автора до Editor у БД та в його відкритих сесіях на всіх репліках.
+/// Якщо автор уже не читач документа (його підвищили або вилучили), схвалення відхиляється
+/// з конфліктом, а запит лишається нерозглянутим.
+pub async fn resolve_access_request(
+    doc_id: Uuid,
+    access_request_id: Uuid,
+    manager_id: Uuid,
+    approve: bool,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<AccessRequestEvent> {
+    require_manager(doc_id, manager_id, ctx).await?;
+
+    let status = if approve { "approved" } else { "denied" };
+    let mut tx = ctx.db_pool.begin().await?;
+    let user_id = repository::resolve_access_request(doc_id, access_request_id, status, manager_id, &mut *tx)
+        .await?
+        .ok_or_else(|| RequestError::not_found("Запит не знайдено або його вже розглянуто"))?;
+    // Роль могла змінитися після запиту: підвищений учасник не понижується, а вилучений не повертається
+    if approve {
+        let promoted = repository::change_member_role_from(
+            doc_id, user_id, SessionRole::Reader.as_str(), SessionRole::Editor.as_str(), &mut *tx,
+        )
+        .await?;
+        if !promoted {
+            return Err(RequestError::conflict("Автор запиту вже не є читачем документа; запит можна лише відхилити"));
+        }
+    }
+    tx.commit().await?;
+
+    if approve {
+        publish_access_change(doc_id, user_id, Some(SessionRole::Editor), ctx).await;
+    }
+    Ok(AccessRequestEvent::Resolved { access_request_id, user_id, approved: approve })
+}
+
+/// Публікує зміну запиту на право редагування для всіх реплік.
+pub async fn publish_access_request_event(
+    doc_id: Uuid,
+    event: AccessRequestEvent,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<()> {
+    let pubsub_msg = PubSubMessage::AccessRequest { event };
+    fanout::publish(ctx, doc_id, None, pubsub_msg).await
+}
+
+// ─────────────────────────── Chat ────────────────────────────────────────────
+
+/// Сторінка історії чату.
+pub struct ChatPage {
+    /// Повідомлення від старіших до новіших.
+    pub messages: Vec<ChatMessageRow>,
+    /// Чи є ще старіші повідомлення.
+    pub has_more: bool,
+}
+
+impl From<ChatPage> for ServerMessage {
+    fn from(page: ChatPage) -> Self {
+        ServerMessage::ChatHistory { messages: page.messages, has_more: page.has_more }
+    }
+}
+
+/// Витягує імена згаданих користувачів (`@username`) з тексту повідомлення.
+///
+/// Згадка має стояти на початку тексту або після пробілу; розділові знаки в кінці не входять до імені.
+pub(crate) fn extract_mentions(body: &str) -> Vec<String> {
+    let mut usernames: Vec<String> = Vec::new();
+    for word in body.split_whitespace() {
+        let Some(name) = word.strip_prefix('@') else {
+            continue;
+        };
+        let name = name.trim_end_matches([',', '.', '!', '?', ':', ';', ')']);
+        if !name.is_empty() && !usernames.iter().any(|u| u == name) {
+            usernames.push(name.to_string());
+        }
+    }
+    usernames
+}
+
+/// Перевіряє та нормалізує текст повідомлення чату.
+pub(crate) fn validate_chat_body(body: &str) -> RequestResult<&str> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(RequestError::bad_request("Повідомлення не може бути порожнім"));
+    }
+    if body.chars().count() > CHAT_MAX_MESSAGE_CHARS {
+        return Err(RequestError::bad_request(format!(
+            "Повідомлення довше за {CHAT_MAX_MESSAGE_CHARS} символів"
+        )));
+    }
+    Ok(body)
+}
+
+/// Знаходить згаданих у тексті учасників документа. Згадки сторонніх користувачів ігноруються.
+async fn resolve_mentions(doc_id: Uuid, body: &str, ctx: &ServiceContext<'_>) -> RequestResult<Vec<Uuid>> {
+    let usernames = extract_mentions(body);
+    if usernames.is_empty() {
+        return Ok(Vec::new());
+    }
+    repository::find_member_ids_by_usernames(doc_id, &usernames, ctx.db_pool).await
+}
+
+/// Зберігає нове повідомлення чату.
+pub async fn post_chat_message(
+    doc_id: Uuid,
+    author_id: Uuid,
+    body: &str,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ChatEvent> {
+    let body = validate_chat_body(body)?;
+    let mentions = resolve_mentions(doc_id, body, ctx).await?;
+    let message = repository::insert_chat_message(doc_id, author_id, body, &mentions, ctx.db_pool).await?;
+    Ok(ChatEvent::Posted { message })
+}
+
+/// Повертає власне повідомлення користувача або помилку, якщо воно чуже чи не існує.
+async fn own_chat_message(
+    doc_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ChatMessageRow> {
+    let message = repository::get_chat_message(doc_id, message_id, ctx.db_pool)
+        .await?
+        .ok_or_else(|| RequestError::not_found("Повідомлення не знайдено"))?;
+    if message.author_id != Some(user_id) {
+        return Err(RequestError::forbidden("Можна змінювати лише власні повідомлення"));
+    }
+    Ok(message)
+}
+
+/// Редагує власне повідомлення чату.
+pub async fn edit_chat_message(
+    doc_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    body: &str,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ChatEvent> {
+    let body = validate_chat_body(body)?;
+    own_chat_message(doc_id, message_id, user_id, ctx).await?;
+    let mentions = resolve_mentions(doc_id, body, ctx).await?;
+    let message = repository::update_chat_message(doc_id, message_id, body, &mentions, ctx.db_pool).await?;
+    Ok(ChatEvent::Edited { message })
+}
+
+/// Видаляє власне повідомлення чату.
+pub async fn delete_chat_message(
+    doc_id: Uuid,
+    message_id: Uuid,
+    user_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ChatEvent> {
+    own_chat_message(doc_id, message_id, user_id, ctx).await?;
+    repository::delete_chat_message(doc_id, message_id, ctx.db_pool).await?;
+    Ok(ChatEvent::Deleted { message_id })
+}
+
+/// Повертає сторінку історії чату, старшу за повідомлення `before` (або найновішу).
+pub async fn chat_history(
+    doc_id: Uuid,
+    before: Option<Uuid>,
+    limit: Option<u32>,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ChatPage> {
+    let limit = limit.unwrap_or(CHAT_HISTORY_PAGE_SIZE).clamp(1, CHAT_HISTORY_MAX_PAGE_SIZE) as usize;
+
+    // Беремо на одне повідомлення більше, щоб дізнатися, чи є старіші
+    let mut messages = repository::get_chat_page(doc_id, before, limit as i64 + 1, ctx.db_pool).await?;
+    let has_more = messages.len() > limit;
+    messages.truncate(limit);
+    messages.reverse();
+
+    Ok(ChatPage { messages, has_more })
+}
+
+/// Розсилає подію чату учасникам кімнати на всіх репліках.
+pub async fn publish_chat_event(doc_id: Uuid, event: ChatEvent, ctx: &ServiceContext<'_>) -> RequestResult<()> {
+    let pubsub_msg = PubSubMessage::Chat { event };
+    fanout::publish(ctx, doc_id, None, pubsub_msg).await
+}
+
+// ─────────────────────────── Runs ────────────────────────────────────────────
+
+/// Повертає правило запуску коду в документі.
+pub async fn get_run_policy(doc_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<RunPolicy> {
+    let editors_only = repository::get_runs_editors_only(doc_id, ctx.db_pool).await?;
+    Ok(RunPolicy { editors_only })
+}
+
+/// Змінює правило запуску коду (тільки Manager) та сповіщає учасників кімнати.
+pub async fn set_run_policy(
+    doc_id: Uuid,
+    policy: RunPolicy,
+    requester_id: Uuid,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<RunPolicy> {
+    require_manager(doc_id, requester_id, ctx).await?;
+    repository::set_runs_editors_only(doc_id, policy.editors_only, ctx.db_pool).await?;
+
+    let pubsub_msg = PubSubMessage::RunPolicy { editors_only: policy.editors_only };
+    if let Err(err) = fanout::publish(ctx, doc_id, None, pubsub_msg).await {
+        tracing::warn!("Не вдалося опублікувати правило запуску документа {doc_id} для інших реплік: {err}");
+    }
+    Ok(policy)
+}
+
+/// Перевіряє, чи може учасник з роллю `role` запускати код у документі.
+pub async fn ensure_can_run(doc_id: Uuid, role: &SessionRole, ctx: &ServiceContext<'_>) -> RequestResult<()> {
+    if role.can_edit() || !get_run_policy(doc_id, ctx).await?.editors_only {
+        return Ok(());
+    }
+    Err(RequestError::forbidden("Запускати код у цьому документі можуть лише Editor та Manager"))
+}
+
+/// Розсилає подію запуску учасникам кімнати на всіх репліках.
+pub async fn publish_run_event(doc_id: Uuid, event: RunEvent, ctx: &ServiceContext<'_>) -> RequestResult<()> {
+    let pubsub_msg = PubSubMessage::Run { event };
+    fanout::publish(ctx, doc_id, None, pubsub_msg).await
+}
+
+/// Скасовує запуск `run_id` документа від імені `user_id` (автор запуску або менеджер).
+///
+/// Запуск цієї репліки скасовується одразу, і результат — `true`. Інакше запит передається
+/// через кімнату всім реплікам, а результат — `false`: скасування виконується за можливістю,
+/// бо спільного реєстру запусків немає — репліка, що виконує запуск, перевіряє права сама,
+/// а невідомий запуск просто ігнорується. `origin` — підключення-ініціатор, якщо є.
+pub async fn cancel_run(
+    doc_id: Uuid,
+    run_id: Uuid,
+    user_id: Uuid,
+    is_manager: bool,
+    origin: Option<Uuid>,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<bool> {
+    if ctx.jobs.owner(run_id).is_some() {
+        return ctx.jobs.cancel(run_id, doc_id, user_id, is_manager).map(|()| true);
+    }
+    let pubsub_msg = PubSubMessage::RunCancel { run_id, user_id, is_manager };
+    fanout::publish(ctx, doc_id, origin, pubsub_msg).await?;
+    Ok(false)
+}
+
 // ─────────────────────────── Export ──────────────────────────────────────────
 
 /// Архівує файли проекту у tar.xz та повертає байти.
@@ -194,13 +1083,9 @@ pub async fn apply_sync_message(
 
     repository::push_change_in_db(doc_id, change.clone(), ctx.db_pool).await?;
 
-    let pubsub_msg = PubSubMessage::SyncChange {
-        sender_conn_id: conn_id,
-        change: change.to_vec(),
-    };
-    if let Ok(serialized) = serde_json::to_vec(&pubsub_msg) {
-        let channel_name = format!("document:room:{}", doc_id);
-        let _ = ctx.redis.publish(&channel_name, serialized).await;
+    let pubsub_msg = PubSubMessage::SyncChange { change: change.to_vec() };
+    if let Err(err) = fanout::publish(ctx, doc_id, Some(conn_id), pubsub_msg).await {
+        tracing::warn!("Не вдалося опублікувати зміну документа {doc_id} для інших реплік: {err}");
     }
 
     Ok(())
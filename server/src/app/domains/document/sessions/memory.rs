@@ -0,0 +1,121 @@
+use futures_util::future::{self, BoxFuture, FutureExt as _};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::SessionStore;
+use crate::app::domains::document::models::{ParticipantInfo, WsTicket};
+use crate::app::RequestResult;
+use crate::constants::document::PRESENCE_TTL_SECONDS;
+
+/// Сховище сесій у пам'яті процесу — для одновузлового розгортання та тестів.
+///
+/// Як і брокер у пам'яті, не бачить інших реплік: квиток, виданий однією
+/// репліка, інша не прийме.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    /// Присутність за кімнатами: підключення → (запис, час закінчення).
+    presence: HashMap<Uuid, HashMap<Uuid, (ParticipantInfo, Instant)>>,
+    tickets: HashMap<String, (WsTicket, Instant)>,
+}
+
+impl MemorySessionStore {
+    fn state(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn presence_deadline() -> Instant {
+    Instant::now() + Duration::from_secs(PRESENCE_TTL_SECONDS)
+}
+
+impl SessionStore for MemorySessionStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn save_presence<'a>(&'a self, doc_id: Uuid, info: &'a ParticipantInfo) -> BoxFuture<'a, RequestResult<()>> {
+        self.state()
+            .presence
+            .entry(doc_id)
+            .or_default()
+            .insert(info.conn_id, (info.clone(), presence_deadline()));
+        future::ready(Ok(())).boxed()
+    }
+
+    fn remove_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<()>> {
+        let mut state = self.state();
+        if let Some(room) = state.presence.get_mut(&doc_id) {
+            room.remove(&conn_id);
+            if room.is_empty() {
+                state.presence.remove(&doc_id);
+            }
+        }
+        future::ready(Ok(())).boxed()
+    }
+
+    fn get_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<Option<ParticipantInfo>>> {
+        let now = Instant::now();
+        let info = self.state()
+            .presence
+            .get(&doc_id)
+            .and_then(|room| room.get(&conn_id))
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(info, _)| info.clone());
+        future::ready(Ok(info)).boxed()
+    }
+
+    fn list_presence(&self, doc_id: Uuid) -> BoxFuture<'_, RequestResult<(Vec<ParticipantInfo>, usize)>> {
+        let now = Instant::now();
+        let mut state = self.state();
+        let Some(room) = state.presence.get_mut(&doc_id) else {
+            return future::ready(Ok((Vec::new(), 0))).boxed();
+        };
+
+        let before = room.len();
+        room.retain(|_, (_, expires_at)| *expires_at > now);
+        let pruned = before - room.len();
+        let mut participants: Vec<ParticipantInfo> = room.values().map(|(info, _)| info.clone()).collect();
+        if room.is_empty() {
+            state.presence.remove(&doc_id);
+        }
+
+        participants.sort_by_key(|p| p.conn_id);
+        future::ready(Ok((participants, pruned))).boxed()
+    }
+
+    fn refresh_presence<'a>(&'a self, doc_id: Uuid, local: &'a [ParticipantInfo]) -> BoxFuture<'a, RequestResult<()>> {
+        let mut state = self.state();
+        let room = state.presence.entry(doc_id).or_default();
+        for info in local {
+            let entry = room.entry(info.conn_id).or_insert_with(|| (info.clone(), Instant::now()));
+            entry.1 = presence_deadline();
+        }
+        future::ready(Ok(())).boxed()
+    }
+
+    fn put_ticket<'a>(&'a self, ticket: &'a str, data: &'a WsTicket, ttl_secs: u64) -> BoxFuture<'a, RequestResult<()>> {
+        let now = Instant::now();
+        let mut state = self.state();
+        // Невикористані квитки прибираються тут: окремого фонового завдання сховище не має
+        state.tickets.retain(|_, (_, expires_at)| *expires_at > now);
+        state.tickets.insert(ticket.to_string(), (data.clone(), now + Duration::from_secs(ttl_secs)));
+        future::ready(Ok(())).boxed()
+    }
+
+    fn take_ticket<'a>(&'a self, ticket: &'a str) -> BoxFuture<'a, RequestResult<Option<WsTicket>>> {
+        let now = Instant::now();
+        let data = self.state()
+            .tickets
+            .remove(ticket)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(data, _)| data);
+        future::ready(Ok(data)).boxed()
+    }
+}
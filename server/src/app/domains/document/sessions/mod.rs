@@ -0,0 +1,59 @@
+pub mod memory;
+pub mod postgres;
+pub mod redis;
+
+pub use memory::MemorySessionStore;
+pub use postgres::PostgresSessionStore;
+pub use redis::RedisSessionStore;
+
+use futures_util::future::BoxFuture;
+use uuid::Uuid;
+
+use super::models::{ParticipantInfo, WsTicket};
+use crate::app::RequestResult;
+use crate::core::app_data::AppData;
+
+/// Сховище короткоживучого стану сесій: присутність учасників кімнат та квитки WebSocket.
+///
+/// Реалізація обирається разом із брокером подій (`FANOUT_BROKER`), тож розгортанню
+/// без Redis не потрібне окреме сховище. Записи присутності живуть `PRESENCE_TTL_SECONDS`
+/// без оновлення, щоб учасники зупиненої репліки зникали самі.
+pub trait SessionStore: Send + Sync {
+    /// Назва реалізації для логів.
+    fn name(&self) -> &'static str;
+
+    /// Записує або оновлює присутність підключення, подовжуючи її TTL.
+    fn save_presence<'a>(&'a self, doc_id: Uuid, info: &'a ParticipantInfo) -> BoxFuture<'a, RequestResult<()>>;
+
+    /// Видаляє присутність підключення.
+    fn remove_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<()>>;
+
+    /// Повертає непрострочений запис присутності підключення.
+    fn get_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<Option<ParticipantInfo>>>;
+
+    /// Повертає учасників кімнати, упорядкованих за часом підключення, та прибирає прострочені
+    /// записи; другий елемент результату — кількість прибраних підключень.
+    fn list_presence(&self, doc_id: Uuid) -> BoxFuture<'_, RequestResult<(Vec<ParticipantInfo>, usize)>>;
+
+    /// Подовжує TTL присутності підключень цієї репліки; зниклі записи створюються заново.
+    fn refresh_presence<'a>(&'a self, doc_id: Uuid, local: &'a [ParticipantInfo]) -> BoxFuture<'a, RequestResult<()>>;
+
+    /// Зберігає одноразовий квиток підключення на `ttl_secs` секунд.
+    fn put_ticket<'a>(&'a self, ticket: &'a str, data: &'a WsTicket, ttl_secs: u64) -> BoxFuture<'a, RequestResult<()>>;
+
+    /// Забирає квиток: повторне використання того самого квитка повертає `None`.
+    fn take_ticket<'a>(&'a self, ticket: &'a str) -> BoxFuture<'a, RequestResult<Option<WsTicket>>>;
+
+    /// Запускає фонове обслуговування сховища; за замовчуванням нічого не робить.
+    fn run(&self, _app_data: &AppData) {}
+}
+
+pub(crate) fn serialize<T: serde::Serialize>(value: &T) -> RequestResult<String> {
+    serde_json::to_string(value)
+        .map_err(|e| crate::app::RequestError::internal_server_error(format!("Помилка серіалізації стану сесії: {e}")))
+}
+
+pub(crate) fn deserialize<T: serde::de::DeserializeOwned>(raw: &str) -> RequestResult<T> {
+    serde_json::from_str(raw)
+        .map_err(|e| crate::app::RequestError::internal_server_error(format!("Пошкоджений запис стану сесії: {e}")))
+}
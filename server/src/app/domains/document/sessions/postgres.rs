@@ -0,0 +1,126 @@
+use futures_util::future::{BoxFuture, FutureExt as _};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::time;
+use uuid::Uuid;
+
+use super::{deserialize, serialize, SessionStore};
+use crate::app::domains::document::models::{ParticipantInfo, WsTicket};
+use crate::app::domains::document::repository;
+use crate::app::RequestResult;
+use crate::constants::document::{PRESENCE_TTL_SECONDS, SESSION_PURGE_SECONDS};
+use crate::core::app_data::AppData;
+
+/// Сховище сесій у таблицях Postgres `room_presence` та `ws_tickets` для розгортань без Redis.
+///
+/// Таблиці не журналюються: після збою Postgres присутність відновить heartbeat реплік,
+/// а клієнти просто отримають нові квитки.
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl SessionStore for PostgresSessionStore {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn save_presence<'a>(&'a self, doc_id: Uuid, info: &'a ParticipantInfo) -> BoxFuture<'a, RequestResult<()>> {
+        async move {
+            let payload = serialize(info)?;
+            repository::upsert_presence(doc_id, info.conn_id, &payload, PRESENCE_TTL_SECONDS as f64, &self.pool).await
+        }
+        .boxed()
+    }
+
+    fn remove_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<()>> {
+        repository::delete_presence(doc_id, conn_id, &self.pool).boxed()
+    }
+
+    fn get_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<Option<ParticipantInfo>>> {
+        async move {
+            let raw = repository::get_presence(doc_id, conn_id, &self.pool).await?;
+            raw.as_deref().map(deserialize).transpose()
+        }
+        .boxed()
+    }
+
+    /// Прострочені записи кімнати видаляються перед читанням, щоб heartbeat помітив зниклих учасників.
+    fn list_presence(&self, doc_id: Uuid) -> BoxFuture<'_, RequestResult<(Vec<ParticipantInfo>, usize)>> {
+        async move {
+            let pruned = repository::delete_expired_presence(doc_id, &self.pool).await?;
+            let participants = repository::list_presence(doc_id, &self.pool)
+                .await?
+                .iter()
+                .filter_map(|raw| deserialize(raw).ok())
+                .collect();
+            Ok((participants, pruned as usize))
+        }
+        .boxed()
+    }
+
+    fn refresh_presence<'a>(&'a self, doc_id: Uuid, local: &'a [ParticipantInfo]) -> BoxFuture<'a, RequestResult<()>> {
+        async move {
+            if local.is_empty() {
+                return Ok(());
+            }
+
+            let conn_ids: Vec<Uuid> = local.iter().map(|info| info.conn_id).collect();
+            let alive = repository::touch_presence(doc_id, &conn_ids, PRESENCE_TTL_SECONDS as f64, &self.pool).await?;
+
+            for info in local.iter().filter(|info| !alive.contains(&info.conn_id)) {
+                self.save_presence(doc_id, info).await?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn put_ticket<'a>(&'a self, ticket: &'a str, data: &'a WsTicket, ttl_secs: u64) -> BoxFuture<'a, RequestResult<()>> {
+        repository::insert_ws_ticket(ticket, data.doc_id, data.user_id, &data.username, ttl_secs as f64, &self.pool)
+            .boxed()
+    }
+
+    fn take_ticket<'a>(&'a self, ticket: &'a str) -> BoxFuture<'a, RequestResult<Option<WsTicket>>> {
+        async move {
+            let row = repository::take_ws_ticket(ticket, &self.pool).await?;
+            Ok(row.map(|(doc_id, user_id, username)| WsTicket { doc_id, user_id, username }))
+        }
+        .boxed()
+    }
+
+    /// Запускає періодичне прибирання прострочених записів присутності та квитків.
+    ///
+    /// Присутність видаляється із запасом у `PRESENCE_TTL_SECONDS`, щоб heartbeat кімнати
+    /// встиг сам прибрати запис і розіслати оновлений список учасників.
+    fn run(&self, app_data: &AppData) {
+        let cancel_token = app_data.token().child_token();
+        let interval = Duration::from_secs(SESSION_PURGE_SECONDS);
+        let pool = self.pool.clone();
+
+        actix_rt::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = time::sleep(interval) => {}
+                }
+
+                match repository::purge_expired_presence(PRESENCE_TTL_SECONDS as f64, &pool).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::debug!("Прибрано {purged} прострочених записів присутності"),
+                    Err(err) => tracing::warn!("Не вдалося прибрати прострочені записи присутності: {err}"),
+                }
+                match repository::purge_expired_ws_tickets(&pool).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::debug!("Прибрано {purged} прострочених квитків WebSocket"),
+                    Err(err) => tracing::warn!("Не вдалося прибрати прострочені квитки WebSocket: {err}"),
+                }
+            }
+        });
+    }
+}
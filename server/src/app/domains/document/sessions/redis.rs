@@ -0,0 +1,144 @@
+use futures_util::future::{BoxFuture, FutureExt as _};
+use uuid::Uuid;
+
+use super::{deserialize, serialize, SessionStore};
+use crate::app::domains::document::models::{ParticipantInfo, WsTicket};
+use crate::app::redis::client::RedisClient;
+use crate::app::redis::keys::RedisKey;
+use crate::app::RequestResult;
+use crate::constants::document::PRESENCE_TTL_SECONDS;
+
+/// Сховище сесій у Redis.
+///
+/// Кожне підключення має окремий ключ з TTL, а множина кімнати містить ідентифікатори
+/// підключень; квиток — ключ з TTL, що видаляється під час використання.
+pub struct RedisSessionStore {
+    redis: RedisClient,
+}
+
+impl RedisSessionStore {
+    pub fn new(redis: RedisClient) -> Self {
+        Self { redis }
+    }
+}
+
+impl SessionStore for RedisSessionStore {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn save_presence<'a>(&'a self, doc_id: Uuid, info: &'a ParticipantInfo) -> BoxFuture<'a, RequestResult<()>> {
+        async move {
+            let payload = serialize(info)?;
+            let room_key = RedisKey::DocumentPresence(doc_id).to_string();
+
+            let mut pipe = self.redis.get_pipe();
+            pipe.set_ex(RedisKey::PresenceEntry(doc_id, info.conn_id).to_string(), payload, PRESENCE_TTL_SECONDS).ignore()
+                .sadd(&room_key, info.conn_id.to_string()).ignore()
+                .expire(&room_key, PRESENCE_TTL_SECONDS as i64).ignore();
+            self.redis.exec_pipe::<()>(&pipe).await
+        }
+        .boxed()
+    }
+
+    fn remove_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<()>> {
+        async move {
+            let mut pipe = self.redis.get_pipe();
+            pipe.del(RedisKey::PresenceEntry(doc_id, conn_id).to_string()).ignore()
+                .srem(RedisKey::DocumentPresence(doc_id).to_string(), conn_id.to_string()).ignore();
+            self.redis.exec_pipe::<()>(&pipe).await
+        }
+        .boxed()
+    }
+
+    fn get_presence(&self, doc_id: Uuid, conn_id: Uuid) -> BoxFuture<'_, RequestResult<Option<ParticipantInfo>>> {
+        async move {
+            let raw = self.redis.get(&RedisKey::PresenceEntry(doc_id, conn_id).to_string()).await?;
+            raw.as_deref().map(deserialize).transpose()
+        }
+        .boxed()
+    }
+
+    /// Ідентифікатори, записи яких уже прострочені, прибираються з множини кімнати.
+    fn list_presence(&self, doc_id: Uuid) -> BoxFuture<'_, RequestResult<(Vec<ParticipantInfo>, usize)>> {
+        async move {
+            let redis = &self.redis;
+            let room_key = RedisKey::DocumentPresence(doc_id).to_string();
+
+            let mut pipe = redis.get_pipe();
+            pipe.smembers(&room_key);
+            let (members,): (Vec<String>,) = redis.exec_pipe(&pipe).await?;
+            if members.is_empty() {
+                return Ok((Vec::new(), 0));
+            }
+
+            let keys: Vec<String> = members
+                .iter()
+                .map(|m| format!("{}:{}", room_key, m))
+                .collect();
+            let mut pipe = redis.get_pipe();
+            pipe.mget(&keys);
+            let (entries,): (Vec<Option<String>>,) = redis.exec_pipe(&pipe).await?;
+
+            let mut participants = Vec::with_capacity(members.len());
+            let mut stale = Vec::new();
+            for (member, entry) in members.into_iter().zip(entries) {
+                match entry.as_deref().map(deserialize::<ParticipantInfo>) {
+                    Some(Ok(info)) => participants.push(info),
+                    _ => stale.push(member),
+                }
+            }
+
+            if !stale.is_empty() {
+                let mut pipe = redis.get_pipe();
+                pipe.srem(&room_key, &stale).ignore();
+                redis.exec_pipe::<()>(&pipe).await?;
+            }
+
+            participants.sort_by_key(|p| p.conn_id);
+            Ok((participants, stale.len()))
+        }
+        .boxed()
+    }
+
+    fn refresh_presence<'a>(&'a self, doc_id: Uuid, local: &'a [ParticipantInfo]) -> BoxFuture<'a, RequestResult<()>> {
+        async move {
+            if local.is_empty() {
+                return Ok(());
+            }
+
+            let mut pipe = self.redis.get_pipe();
+            for info in local {
+                pipe.expire(RedisKey::PresenceEntry(doc_id, info.conn_id).to_string(), PRESENCE_TTL_SECONDS as i64);
+            }
+            pipe.expire(RedisKey::DocumentPresence(doc_id).to_string(), PRESENCE_TTL_SECONDS as i64).ignore();
+            let alive: Vec<bool> = self.redis.exec_pipe(&pipe).await?;
+
+            for (info, alive) in local.iter().zip(alive) {
+                if !alive {
+                    self.save_presence(doc_id, info).await?;
+                }
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn put_ticket<'a>(&'a self, ticket: &'a str, data: &'a WsTicket, ttl_secs: u64) -> BoxFuture<'a, RequestResult<()>> {
+        async move {
+            let serialized = serialize(data)?;
+            self.redis
+                .set_ex(&RedisKey::WsTicket(ticket.to_string()).to_string(), &serialized, ttl_secs)
+                .await
+        }
+        .boxed()
+    }
+
+    fn take_ticket<'a>(&'a self, ticket: &'a str) -> BoxFuture<'a, RequestResult<Option<WsTicket>>> {
+        async move {
+            let serialized = self.redis.get_del(&RedisKey::WsTicket(ticket.to_string()).to_string()).await?;
+            Ok(serialized.as_deref().and_then(|raw| deserialize(raw).ok()))
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,982 @@
+/// Модульні тести для домену document.
+/// Усі тести викликають реальні `pub(crate)` функції з `document::service`,
+/// а не їх копії, щоб ловити регресії в production-коді.
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use crate::app::domains::document::models::{
+        AccessRequestEvent, ChatEvent, ChatMessageRow, ClientEnvelope, ClientMessage, CursorState, ErrorCode, FileRevisionRow, FileSystemEvent, InviteRow, ParticipantInfo,
+        PubSubMessage, RevisionKind, RoomEvent, Selection, ServerMessage, SessionRole, Viewport,
+    };
+    use crate::app::domains::document::models::{Outbound, OutboundFrame, PushOutcome};
+    use crate::app::domains::document::broker::redis_streams::start_offset;
+    use crate::app::domains::document::broker::{MemoryBroker, MemoryHub};
+    use crate::app::domains::document::fanout::{next_backoff, Fanout};
+    use crate::app::redis::keys::RedisKey;
+    use crate::app::domains::document::service::{
+        build_timeline, check_invite, extract_mentions, invite_expiry, normalize_access_message, reroot_path, restored_path, revision_action, validate_chat_body,
+        RevisionAction,
+    };
+    use crate::app::domains::document::ws_handler::{
+        bearer_from_protocols, check_liveness, error_code, negotiate_capabilities, negotiate_version, Liveness,
+    };
+    use crate::core::config_builder::WsSettings;
+    use crate::app::RequestError;
+
+    fn revision(path: &str, content: &str, kind: RevisionKind, author: Option<Uuid>, unix: i64) -> FileRevisionRow {
+        FileRevisionRow {
+            id: Uuid::now_v7(),
+            file_id: Uuid::nil(),
+            path: path.to_string(),
+            old_path: None,
+            content: content.to_string(),
+            kind: kind.as_str().to_string(),
+            author_id: author,
+            author_username: None,
+            created_at: Utc.timestamp_opt(unix, 0).unwrap(),
+            updated_at: Utc.timestamp_opt(unix, 0).unwrap(),
+        }
+    }
+
+    mod trash {
+        use super::*;
+
+        /// Тест 1: Відновлений файл з розширенням отримує суфікс перед розширенням.
+        #[test]
+        fn restored_file_keeps_extension() {
+            assert_eq!(restored_path("src/parser.rs", 1), "src/parser (restored).rs");
+            assert_eq!(restored_path("src/parser.rs", 3), "src/parser (restored 3).rs");
+        }
+
+        /// Тест 2: Папки та файли без розширення отримують суфікс у кінці.
+        #[test]
+        fn restored_dir_gets_suffix_at_end() {
+            assert_eq!(restored_path("src/utils", 1), "src/utils (restored)");
+            assert_eq!(restored_path("Makefile", 2), "Makefile (restored 2)");
+        }
+
+        /// Тест 3: Крапка в назві папки не вважається розширенням файлу.
+        #[test]
+        fn dot_in_parent_dir_is_ignored() {
+            assert_eq!(restored_path("v1.2/notes", 1), "v1.2/notes (restored)");
+            assert_eq!(restored_path(".env", 1), ".env (restored)");
+        }
+
+        /// Тест 4: Вкладені шляхи переносяться під новий корінь разом із коренем.
+        #[test]
+        fn reroot_moves_children() {
+            assert_eq!(reroot_path("src/utils", "src/utils", "src/helpers"), "src/helpers");
+            assert_eq!(reroot_path("src/utils/mod.rs", "src/utils", "src/helpers"), "src/helpers/mod.rs");
+        }
+    }
+
+    mod history {
+        use super::*;
+
+        /// Тест 5: Перша правка файлу завжди створює нову ревізію.
+        #[test]
+        fn first_edit_is_inserted() {
+            let action = revision_action(None, RevisionKind::Edit, "a", Some(Uuid::nil()), Utc::now());
+            assert_eq!(action, RevisionAction::Insert);
+        }
+
+        /// Тест 6: Правки одного автора в межах вікна зливаються з останньою ревізією.
+        #[test]
+        fn edits_of_same_author_are_coalesced() {
+            let author = Some(Uuid::now_v7());
+            let last = revision("src/parser.rs", "a", RevisionKind::Edit, author, 1_700_000_000);
+            let now = last.created_at + Duration::seconds(10);
+
+            assert_eq!(revision_action(Some(&last), RevisionKind::Edit, "ab", author, now), RevisionAction::Amend(last.id));
+            assert_eq!(revision_action(Some(&last), RevisionKind::Edit, "a", author, now), RevisionAction::Skip);
+        }
+
+        /// Тест 7: Інший автор, пауза або перейменування відкривають нову ревізію.
+        #[test]
+        fn other_author_or_pause_starts_new_revision() {
+            let author = Some(Uuid::now_v7());
+            let last = revision("src/parser.rs", "a", RevisionKind::Edit, author, 1_700_000_000);
+
+            let soon = last.created_at + Duration::seconds(10);
+            let later = last.created_at + Duration::minutes(10);
+            assert_eq!(revision_action(Some(&last), RevisionKind::Edit, "b", Some(Uuid::now_v7()), soon), RevisionAction::Insert);
+            assert_eq!(revision_action(Some(&last), RevisionKind::Edit, "b", author, later), RevisionAction::Insert);
+            assert_eq!(revision_action(Some(&last), RevisionKind::Rename, "a", author, soon), RevisionAction::Insert);
+        }
+
+        /// Тест 8: Diff кожної ревізії будується відносно попередньої, навіть після перейменування.
+        #[test]
+        fn timeline_diffs_follow_renames() {
+            let author = Some(Uuid::now_v7());
+            let mut renamed = revision("src/lexer.rs", "fn a() {}\n", RevisionKind::Rename, author, 1_700_000_100);
+            renamed.old_path = Some("src/parser.rs".to_string());
+
+            let timeline = build_timeline(vec![
+                revision("src/parser.rs", "fn a() {}\n", RevisionKind::Edit, author, 1_700_000_000),
+                renamed,
+                revision("src/lexer.rs", "fn b() {}\n", RevisionKind::Edit, author, 1_700_000_200),
+            ]);
+
+            assert_eq!(timeline.len(), 3);
+            assert!(timeline[0].diff.contains("+fn a() {}"));
+            assert!(timeline[1].diff.contains("--- src/parser.rs"));
+            assert!(timeline[1].diff.contains("+++ src/lexer.rs"));
+            assert!(!timeline[1].diff.contains("@@"));
+            assert!(timeline[2].diff.contains("-fn a() {}"));
+            assert!(timeline[2].diff.contains("+fn b() {}"));
+        }
+
+        /// Тест 49: Вікно злиття відраховується від створення ревізії, а не від останньої злитої правки.
+        #[test]
+        fn coalesce_window_does_not_slide() {
+            let author = Some(Uuid::now_v7());
+            let mut last = revision("src/parser.rs", "a", RevisionKind::Edit, author, 1_700_000_000);
+            last.updated_at = last.created_at + Duration::seconds(50);
+
+            let after_pause = last.updated_at + Duration::seconds(20);
+            assert_eq!(revision_action(Some(&last), RevisionKind::Edit, "ab", author, after_pause), RevisionAction::Insert);
+        }
+    }
+
+    mod protocol {
+        use super::*;
+
+        /// Тест 9: Команда з request_id розбирається у типізоване повідомлення.
+        #[test]
+        fn parses_typed_command_with_request_id() {
+            let text = r#"{"type":"fs_event","request_id":"r1","event":{"action":"delete","path":"src/a.rs"}}"#;
+            let envelope = ClientEnvelope::parse(text).expect("Команда повинна розібратися");
+
+            assert_eq!(envelope.request_id.as_deref(), Some("r1"));
+            assert!(matches!(
+                envelope.message,
+                ClientMessage::FsEvent { event: FileSystemEvent::Delete { ref path } } if path == "src/a.rs"
+            ));
+        }
+
+        /// Тест 10: Некоректний JSON, невідомий тип та зламані поля дають різні коди помилок.
+        #[test]
+        fn parse_errors_have_distinct_codes() {
+            let malformed = ClientEnvelope::parse("not json").unwrap_err();
+            assert_eq!(malformed.code, ErrorCode::MalformedMessage);
+            assert_eq!(malformed.request_id, None);
+
+            let unsupported = ClientEnvelope::parse(r#"{"type":"teleport","request_id":"r2"}"#).unwrap_err();
+            assert_eq!(unsupported.code, ErrorCode::UnsupportedMessage);
+            assert_eq!(unsupported.request_id.as_deref(), Some("r2"));
+
+            let invalid = ClientEnvelope::parse(r#"{"type":"role_change","request_id":"r3","new_role":"god"}"#).unwrap_err();
+            assert_eq!(invalid.code, ErrorCode::InvalidRequest);
+            assert_eq!(invalid.request_id.as_deref(), Some("r3"));
+
+            let untyped = ClientEnvelope::parse(r#"{"event":{"action":"delete","path":"a"}}"#).unwrap_err();
+            assert_eq!(untyped.code, ErrorCode::MalformedMessage);
+        }
+
+        /// Тест 11: Версія узгоджується вниз до серверної, застарілі клієнти відхиляються.
+        #[test]
+        fn version_negotiation() {
+            assert_eq!(negotiate_version(1), Some(1));
+            assert_eq!(negotiate_version(7), Some(1));
+            assert_eq!(negotiate_version(0), None);
+        }
+
+        /// Тест 12: Клієнт отримує лише ті можливості, які підтримує сервер.
+        #[test]
+        fn capabilities_are_intersected() {
+            let requested = vec!["cursors".to_string(), "telepathy".to_string()];
+            assert_eq!(negotiate_capabilities(&requested), vec!["cursors".to_string()]);
+            assert!(negotiate_capabilities(&[]).is_empty());
+        }
+
+        /// Тест 13: Фрейм помилки містить request_id та код, а помилки сервісу мапляться на коди протоколу.
+        #[test]
+        fn error_frame_shape() {
+            let frame = ServerMessage::Error {
+                request_id: Some("r4".into()),
+                code: error_code(&RequestError::forbidden("x")),
+                message: "x".into(),
+            };
+            let json = serde_json::to_value(&frame).unwrap();
+
+            assert_eq!(json["type"], "error");
+            assert_eq!(json["request_id"], "r4");
+            assert_eq!(json["code"], "forbidden");
+            assert_eq!(error_code(&RequestError::bad_request("x")), ErrorCode::InvalidRequest);
+            assert_eq!(error_code(&RequestError::internal_server_error("x")), ErrorCode::Internal);
+        }
+    }
+
+    mod presence {
+        use super::*;
+
+        /// Тест 14: Ключі присутності кімнати та підключення мають спільний префікс.
+        #[test]
+        fn presence_keys_share_room_prefix() {
+            let doc_id = Uuid::nil();
+            let conn_id = Uuid::now_v7();
+            let room = RedisKey::DocumentPresence(doc_id).to_string();
+
+            assert_eq!(room, format!("document:presence:{doc_id}"));
+            assert_eq!(RedisKey::PresenceEntry(doc_id, conn_id).to_string(), format!("{room}:{conn_id}"));
+        }
+
+        /// Тест 15: Запис присутності без курсора та активного файлу читається з значеннями за замовчуванням.
+        #[test]
+        fn participant_defaults_for_missing_fields() {
+            let raw = format!(
+                r#"{{"conn_id":"{}","user_id":"{}","username":"alice","role":"editor"}}"#,
+                Uuid::nil(),
+                Uuid::nil()
+            );
+            let info: ParticipantInfo = serde_json::from_str(&raw).unwrap();
+
+            assert_eq!(info.role, SessionRole::Editor);
+            assert!(info.cursor.is_none() && info.active_file.is_none() && !info.idle);
+        }
+
+        /// Тест 16: Курсор з виділенням розбирається від клієнта та пласко надсилається іншим.
+        #[test]
+        fn cursor_with_selection_round_trip() {
+            let text = r#"{"type":"cursor","path":"src/main.rs","position":4,"selection":{"anchor":1,"head":4}}"#;
+            let ClientMessage::Cursor { path, position, selection } = ClientEnvelope::parse(text).unwrap().message else {
+                panic!("Очікувалася команда cursor");
+            };
+            assert_eq!(selection, Some(Selection { anchor: 1, head: 4 }));
+
+            let outgoing = ServerMessage::Cursor {
+                conn_id: Uuid::nil(),
+                cursor: CursorState { path, position, selection },
+            };
+            let json = serde_json::to_value(&outgoing).unwrap();
+
+            assert_eq!(json["type"], "cursor");
+            assert_eq!(json["path"], "src/main.rs");
+            assert_eq!(json["position"], 4);
+            assert_eq!(json["selection"]["head"], 4);
+        }
+
+        /// Тест 17: Команда activity передає активний файл та стан неактивності.
+        #[test]
+        fn activity_command_is_parsed() {
+            let text = r#"{"type":"activity","request_id":"r5","active_file":"src/lib.rs","idle":true}"#;
+            let envelope = ClientEnvelope::parse(text).unwrap();
+
+            assert!(matches!(
+                envelope.message,
+                ClientMessage::Activity { active_file: Some(ref f), idle: true } if f == "src/lib.rs"
+            ));
+        }
+
+        /// Тест 51: Сховище сесій у пам'яті оновлює присутність та видає квиток лише один раз.
+        #[actix_rt::test]
+        async fn memory_session_store_presence_and_tickets() {
+            use crate::app::domains::document::presence;
+            use crate::app::domains::document::sessions::{MemorySessionStore, SessionStore};
+
+            let store = MemorySessionStore::default();
+            let doc_id = Uuid::now_v7();
+            let info = ParticipantInfo {
+                conn_id: Uuid::now_v7(),
+                user_id: Uuid::now_v7(),
+                username: "alice".into(),
+                role: SessionRole::Editor,
+                active_file: None,
+                cursor: None,
+                idle: false,
+                following: None,
+                viewport: None,
+            };
+
+            presence::join(doc_id, &info, &store).await.unwrap();
+            let updated = presence::update(doc_id, info.conn_id, &store, |p| p.idle = true).await.unwrap();
+            assert!(updated.is_some_and(|p| p.idle));
+            let (participants, pruned) = presence::list(doc_id, &store).await.unwrap();
+            assert_eq!((participants.len(), pruned), (1, 0));
+            presence::leave(doc_id, info.conn_id, &store).await.unwrap();
+            assert!(presence::list(doc_id, &store).await.unwrap().0.is_empty());
+
+            let ticket = crate::app::domains::document::models::WsTicket { doc_id, user_id: info.user_id, username: "alice".into() };
+            store.put_ticket("t1", &ticket, 30).await.unwrap();
+            assert_eq!(store.take_ticket("t1").await.unwrap(), Some(ticket));
+            assert_eq!(store.take_ticket("t1").await.unwrap(), None);
+        }
+    }
+
+    mod access {
+        use super::*;
+
+        /// Тест 18: Відкликання доступу передається між репліками як зміна без ролі.
+        #[test]
+        fn revoked_access_round_trips_without_role() {
+            let user_id = Uuid::now_v7();
+            let payload = serde_json::to_vec(&PubSubMessage::AccessChanged { user_id, role: None }).unwrap();
+
+            let PubSubMessage::AccessChanged { user_id: parsed, role } = serde_json::from_slice(&payload).unwrap() else {
+                panic!("Очікувалося повідомлення access_changed");
+            };
+            assert_eq!(parsed, user_id);
+            assert_eq!(role, None);
+        }
+
+        /// Тест 19: Пониження ролі доходить до клієнта окремим повідомленням role_changed.
+        #[test]
+        fn demotion_is_sent_to_client() {
+            let payload = serde_json::to_vec(&PubSubMessage::AccessChanged {
+                user_id: Uuid::nil(),
+                role: Some(SessionRole::Reader),
+            })
+            .unwrap();
+            let PubSubMessage::AccessChanged { role: Some(role), .. } = serde_json::from_slice(&payload).unwrap() else {
+                panic!("Очікувалася нова роль");
+            };
+
+            let json = serde_json::to_value(ServerMessage::RoleChanged { role }).unwrap();
+            assert_eq!(json, serde_json::json!({ "type": "role_changed", "role": "reader" }));
+        }
+    }
+
+    mod liveness {
+        use super::*;
+        use std::time::Duration as StdDuration;
+        use tokio::time::Instant;
+
+        fn settings() -> WsSettings {
+            WsSettings { heartbeat_interval_secs: 15, pong_timeout_secs: 10, idle_timeout_secs: 600 }
+        }
+
+        /// Тест 20: Без відповіді на ping довше за дедлайн з'єднання вважається мертвим.
+        #[test]
+        fn missing_pong_is_overdue() {
+            let start = Instant::now();
+            let ping = Some(start);
+
+            assert_eq!(check_liveness(start + StdDuration::from_secs(9), ping, start, &settings()), Liveness::Alive);
+            assert_eq!(
+                check_liveness(start + StdDuration::from_secs(10), ping, start, &settings()),
+                Liveness::PongOverdue
+            );
+        }
+
+        /// Тест 21: Клієнт, що відповідає на ping, але нічого не надсилає, закривається після idle-таймауту.
+        #[test]
+        fn silent_client_becomes_idle() {
+            let start = Instant::now();
+            let later = start + StdDuration::from_secs(600);
+
+            assert_eq!(check_liveness(later, None, start, &settings()), Liveness::Idle);
+            assert_eq!(check_liveness(later, None, start + StdDuration::from_secs(1), &settings()), Liveness::Alive);
+        }
+    }
+
+    mod outbound {
+        use super::*;
+
+        /// Тест 22: Кадри відправляються в тому порядку, в якому їх поставили в чергу.
+        #[test]
+        fn frames_keep_order() {
+            let (outbound, mut rx) = Outbound::channel(8);
+            for i in 0..3 {
+                assert_eq!(outbound.push(OutboundFrame::Text(i.to_string())), PushOutcome::Queued);
+            }
+
+            let received: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|frame| match frame {
+                    OutboundFrame::Text(text) => text,
+                    other => panic!("Неочікуваний кадр {other:?}"),
+                })
+                .collect();
+            assert_eq!(received, ["0", "1", "2"]);
+        }
+
+        /// Тест 23: Коли клієнт відстає, спершу відкидаються оновлення присутності.
+        #[test]
+        fn presence_is_dropped_before_commands() {
+            let (outbound, _rx) = Outbound::channel(8);
+            for _ in 0..6 {
+                outbound.push(OutboundFrame::Text("fs_event".into()));
+            }
+
+            assert_eq!(outbound.push(OutboundFrame::Presence("cursor".into())), PushOutcome::Dropped);
+            assert_eq!(outbound.push(OutboundFrame::Text("ack".into())), PushOutcome::Queued);
+        }
+
+        /// Тест 24: Переповнена черга позначає підключення до відключення.
+        #[test]
+        fn full_queue_overflows() {
+            let (outbound, _rx) = Outbound::channel(2);
+            outbound.push(OutboundFrame::Text("a".into()));
+            outbound.push(OutboundFrame::Text("b".into()));
+
+            assert_eq!(outbound.push(OutboundFrame::Text("c".into())), PushOutcome::Overflow);
+            assert_eq!(outbound.push(OutboundFrame::Binary(Default::default())), PushOutcome::Overflow);
+        }
+    }
+
+    mod chat {
+        use super::*;
+
+        /// Тест 25: Згадки розпізнаються лише як окремі слова, без розділових знаків і повторів.
+        #[test]
+        fn mentions_are_extracted_from_words() {
+            let mentions = extract_mentions("@bob глянь, будь ласка. @alice, @bob! email@example.com @");
+            assert_eq!(mentions, ["bob", "alice"]);
+        }
+
+        /// Тест 26: Порожні та задовгі повідомлення відхиляються, пробіли по краях прибираються.
+        #[test]
+        fn chat_body_is_validated() {
+            assert_eq!(validate_chat_body("  привіт \n").unwrap(), "привіт");
+            assert!(validate_chat_body("   ").is_err());
+
+            let long = "ї".repeat(crate::constants::document::CHAT_MAX_MESSAGE_CHARS + 1);
+            assert!(validate_chat_body(&long).is_err());
+        }
+
+        /// Тест 27: Подія чату з іншої репліки доходить до клієнта як повідомлення свого типу.
+        #[test]
+        fn chat_event_round_trips_to_client_message() {
+            let message = ChatMessageRow {
+                id: Uuid::now_v7(),
+                author_id: Some(Uuid::now_v7()),
+                author_username: Some("alice".into()),
+                body: "@bob привіт".into(),
+                mentions: vec![Uuid::nil()],
+                created_at: Utc::now(),
+                edited_at: None,
+            };
+            let payload = serde_json::to_vec(&PubSubMessage::Chat { event: ChatEvent::Edited { message } }).unwrap();
+            let PubSubMessage::Chat { event } = serde_json::from_slice(&payload).unwrap() else {
+                panic!("Очікувалася подія чату");
+            };
+
+            let json = serde_json::to_value(ServerMessage::from(event)).unwrap();
+            assert_eq!(json["type"], "chat_edited");
+            assert_eq!(json["message"]["body"], "@bob привіт");
+            assert_eq!(json["message"]["mentions"][0], Uuid::nil().to_string());
+        }
+
+        /// Тест 28: Запит історії без курсора просить найновішу сторінку.
+        #[test]
+        fn history_request_defaults_to_latest_page() {
+            let envelope = ClientEnvelope::parse(r#"{"type":"chat_history"}"#).unwrap();
+            assert!(matches!(envelope.message, ClientMessage::ChatHistory { before: None, limit: None }));
+        }
+    }
+
+    mod follow {
+        use super::*;
+
+        /// Тест 29: Записи присутності без полів follow (зі старих реплік) читаються без помилок.
+        #[test]
+        fn presence_without_follow_fields_is_accepted() {
+            let raw = serde_json::json!({
+                "conn_id": Uuid::nil(),
+                "user_id": Uuid::nil(),
+                "username": "alice",
+                "role": "editor",
+                "idle": false,
+            });
+            let info: ParticipantInfo = serde_json::from_value(raw).unwrap();
+            assert_eq!(info.following, None);
+            assert_eq!(info.viewport, None);
+        }
+
+        /// Тест 30: Видима область приходить від клієнта пласкими полями поруч із `type`.
+        #[test]
+        fn viewport_command_is_flat() {
+            let envelope = ClientEnvelope::parse(
+                r#"{"type":"viewport","path":"src/main.rs","from_line":10,"to_line":42}"#,
+            )
+            .unwrap();
+            let ClientMessage::Viewport { viewport } = envelope.message else {
+                panic!("Очікувалася команда viewport");
+            };
+            assert_eq!(viewport, Viewport { path: "src/main.rs".into(), from_line: 10, to_line: 42 });
+
+            let follow = ClientEnvelope::parse(&format!(r#"{{"type":"follow","leader_conn_id":"{}"}}"#, Uuid::nil()));
+            assert!(matches!(follow.unwrap().message, ClientMessage::Follow { .. }));
+            assert!(matches!(ClientEnvelope::parse(r#"{"type":"unfollow"}"#).unwrap().message, ClientMessage::Unfollow));
+        }
+
+        /// Тест 31: Послідовник отримує видиму область лідера разом з його conn_id.
+        #[test]
+        fn viewport_is_sent_with_leader_id() {
+            let leader = Uuid::now_v7();
+            let viewport = Viewport { path: "src/lib.rs".into(), from_line: 1, to_line: 30 };
+            let json = serde_json::to_value(ServerMessage::Viewport { conn_id: leader, viewport }).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "type": "viewport",
+                    "conn_id": leader,
+                    "path": "src/lib.rs",
+                    "from_line": 1,
+                    "to_line": 30,
+                })
+            );
+        }
+    }
+
+    mod invites {
+        use super::*;
+
+        fn invite(max_uses: Option<i32>, uses: i32, expires_at: Option<chrono::DateTime<Utc>>) -> InviteRow {
+            InviteRow {
+                id: Uuid::now_v7(),
+                document_id: Uuid::nil(),
+                token: "token".into(),
+                role: "editor".into(),
+                max_uses,
+                uses,
+                expires_at,
+                revoked_at: None,
+                created_by: None,
+                created_by_username: None,
+                created_at: Utc.timestamp_opt(0, 0).unwrap(),
+            }
+        }
+
+        /// Тест 32: Чинне запрошення без обмежень приймається.
+        #[test]
+        fn valid_invite_is_accepted() {
+            let now = Utc.timestamp_opt(1_000, 0).unwrap();
+            assert!(check_invite(&invite(None, 10, None), now).is_ok());
+            assert!(check_invite(&invite(Some(3), 2, Some(now + Duration::hours(1))), now).is_ok());
+        }
+
+        /// Тест 33: Відкликане, прострочене або вичерпане запрошення відхиляється з 403.
+        #[test]
+        fn unusable_invites_are_rejected() {
+            let now = Utc.timestamp_opt(1_000, 0).unwrap();
+
+            let mut revoked = invite(None, 0, None);
+            revoked.revoked_at = Some(now);
+            assert!(matches!(check_invite(&revoked, now), Err(RequestError::Forbidden(_))));
+
+            let expired = invite(None, 0, Some(now));
+            assert!(matches!(check_invite(&expired, now), Err(RequestError::Forbidden(_))));
+
+            let exhausted = invite(Some(2), 2, None);
+            assert!(matches!(check_invite(&exhausted, now), Err(RequestError::Forbidden(_))));
+        }
+
+        /// Тест 34: Роль із бази читається назад у `SessionRole`, невідомі значення стають Reader.
+        #[test]
+        fn role_round_trips_through_db_value() {
+            for role in [SessionRole::Reader, SessionRole::Editor, SessionRole::Manager] {
+                assert_eq!(SessionRole::from_db(role.as_str()), role);
+            }
+            assert_eq!(SessionRole::from_db("owner"), SessionRole::Reader);
+        }
+
+        /// Тест 54: Термін дії запрошення обмежено зверху, а переповнення дати повертає 400 замість паніки.
+        #[test]
+        fn invite_expiry_is_bounded() {
+            let now = Utc.timestamp_opt(1_000, 0).unwrap();
+            assert_eq!(invite_expiry(now, None).unwrap(), None);
+            assert_eq!(invite_expiry(now, Some(2)).unwrap(), Some(now + Duration::hours(2)));
+
+            for hours in [0, -1, crate::constants::document::INVITE_MAX_EXPIRES_IN_HOURS + 1, i64::MAX] {
+                assert!(matches!(invite_expiry(now, Some(hours)), Err(RequestError::BadRequest(_))));
+            }
+            let end_of_time = chrono::DateTime::<Utc>::MAX_UTC - Duration::hours(1);
+            assert!(matches!(invite_expiry(end_of_time, Some(2)), Err(RequestError::BadRequest(_))));
+        }
+    }
+
+    mod access_requests {
+        use super::*;
+
+        /// Тест 35: Порожній коментар до запиту вважається відсутнім, задовгий відхиляється.
+        #[test]
+        fn access_message_is_normalized() {
+            assert_eq!(normalize_access_message(None).unwrap(), None);
+            assert_eq!(normalize_access_message(Some("   ")).unwrap(), None);
+            assert_eq!(normalize_access_message(Some("  треба правити тести ")).unwrap(), Some("треба правити тести"));
+            let long = "x".repeat(501);
+            assert!(matches!(normalize_access_message(Some(&long)), Err(RequestError::BadRequest(_))));
+        }
+
+        /// Тест 36: Команди запиту та розгляду доступу розбираються з конверта.
+        #[test]
+        fn access_commands_are_parsed() {
+            let request = ClientEnvelope::parse(r#"{"type":"request_access"}"#).unwrap();
+            assert!(matches!(request.message, ClientMessage::RequestAccess { message: None }));
+
+            let id = Uuid::now_v7();
+            let resolve = ClientEnvelope::parse(&format!(
+                r#"{{"type":"resolve_access_request","request_id":"r1","access_request_id":"{id}","approve":true}}"#
+            ))
+            .unwrap();
+            assert_eq!(resolve.request_id.as_deref(), Some("r1"));
+            assert!(matches!(
+                resolve.message,
+                ClientMessage::ResolveAccessRequest { access_request_id, approve: true } if access_request_id == id
+            ));
+        }
+
+        /// Тест 37: Рішення щодо запиту надсилається клієнтам з id запиту та автора.
+        #[test]
+        fn resolved_event_becomes_server_message() {
+            let access_request_id = Uuid::now_v7();
+            let user_id = Uuid::now_v7();
+            let event = AccessRequestEvent::Resolved { access_request_id, user_id, approved: false };
+            let json = serde_json::to_value(ServerMessage::from(event)).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!({
+                    "type": "access_request_resolved",
+                    "access_request_id": access_request_id,
+                    "user_id": user_id,
+                    "approved": false,
+                })
+            );
+        }
+    }
+
+    mod tickets {
+        use super::*;
+        use actix_web::test::TestRequest;
+
+        /// Тест 38: JWT береться з підпротоколу, що йде одразу після `cowrite.bearer`.
+        #[test]
+        fn bearer_is_read_from_subprotocols() {
+            let req = TestRequest::default()
+                .insert_header(("Sec-WebSocket-Protocol", "cowrite.bearer, eyJ.payload.sig"))
+                .to_http_request();
+            assert_eq!(bearer_from_protocols(&req).as_deref(), Some("eyJ.payload.sig"));
+
+            let without_token = TestRequest::default()
+                .insert_header(("Sec-WebSocket-Protocol", "cowrite.bearer"))
+                .to_http_request();
+            assert_eq!(bearer_from_protocols(&without_token), None);
+
+            let other = TestRequest::default()
+                .insert_header(("Sec-WebSocket-Protocol", "graphql-ws"))
+                .to_http_request();
+            assert_eq!(bearer_from_protocols(&other), None);
+            assert_eq!(bearer_from_protocols(&TestRequest::default().to_http_request()), None);
+        }
+
+        /// Тест 39: Квитки зберігаються під окремим префіксом, а не в просторі ключів документів.
+        #[test]
+        fn ticket_key_format() {
+            assert_eq!(RedisKey::WsTicket("abc".into()).to_string(), "ws:ticket:abc");
+        }
+    }
+
+    mod fanout {
+        use super::*;
+        use std::time::Duration as StdDuration;
+
+        /// Тест 40: Початковий id потоку береться з годинника Redis мінус час, поки підписка чекала на читача.
+        #[test]
+        fn stream_offset_uses_redis_clock() {
+            assert_eq!(start_offset(1_700_000_000, 123_456, StdDuration::ZERO), "1700000000123-0");
+            assert_eq!(start_offset(1_700_000_000, 123_456, StdDuration::from_millis(23)), "1700000000100-0");
+        }
+
+        /// Тест 41: Пауза перепідключення починається з секунди, подвоюється й обмежена зверху.
+        #[test]
+        fn reconnect_backoff_doubles_up_to_limit() {
+            assert_eq!(next_backoff(StdDuration::ZERO), StdDuration::from_secs(1));
+            assert_eq!(next_backoff(StdDuration::from_secs(1)), StdDuration::from_secs(2));
+            assert_eq!(next_backoff(StdDuration::from_secs(8)), StdDuration::from_secs(16));
+            assert_eq!(next_backoff(StdDuration::from_secs(20)), StdDuration::from_secs(30));
+            assert_eq!(next_backoff(StdDuration::from_secs(30)), StdDuration::from_secs(30));
+        }
+
+        /// Тест 42: Одна підписка на кімнату незалежно від кількості підключень до неї.
+        #[test]
+        fn room_is_subscribed_once() {
+            let fanout = memory_fanout(&MemoryHub::default());
+            let (first, second) = (Uuid::now_v7(), Uuid::now_v7());
+            fanout.subscribe(first);
+            fanout.subscribe(first);
+            fanout.subscribe(second);
+
+            let metrics = fanout.metrics();
+            assert_eq!(metrics.broker, "memory");
+            assert_eq!(metrics.subscriptions, 2);
+            assert!(!metrics.connected);
+            assert_eq!(metrics.reconnects, 0);
+        }
+
+        /// Тест 55: Метрики розсилки вимкнено без токена, а з ним — видно лише за правильним Bearer.
+        #[test]
+        fn metrics_require_token() {
+            use crate::app::domains::document::controller::check_metrics_token;
+            use actix_web::test::TestRequest;
+
+            let request = |header: Option<&str>| {
+                let req = TestRequest::default();
+                match header {
+                    Some(value) => req.insert_header(("Authorization", value)).to_http_request(),
+                    None => req.to_http_request(),
+                }
+            };
+
+            assert!(matches!(check_metrics_token(&request(Some("Bearer s3cret")), None), Err(RequestError::NotFound(_))));
+            assert!(check_metrics_token(&request(Some("Bearer s3cret")), Some("s3cret")).is_ok());
+            for header in [None, Some("Bearer wrong!"), Some("Bearer s3cre"), Some("s3cret")] {
+                assert!(matches!(check_metrics_token(&request(header), Some("s3cret")), Err(RequestError::Unauthorized(_))));
+            }
+        }
+
+        /// Тест 43: Репліка пропускає з потоку лише власні події — інші доставляє своїм клієнтам.
+        #[test]
+        fn own_events_are_skipped() {
+            let hub = MemoryHub::default();
+            let (local, remote) = (memory_fanout(&hub), memory_fanout(&hub));
+            assert_ne!(local.replica_id(), remote.replica_id());
+
+            let event = RoomEvent {
+                replica_id: local.replica_id(),
+                origin_conn_id: None,
+                message: PubSubMessage::Chat { event: ChatEvent::Deleted { message_id: Uuid::now_v7() } },
+            };
+            assert!(local.is_own(&event));
+            assert!(!remote.is_own(&event));
+        }
+
+        /// Тест 44: Запис потоку зберігає репліку та підключення-ініціатора поряд з подією.
+        #[test]
+        fn room_event_carries_origin() {
+            let (replica_id, conn_id) = (Uuid::now_v7(), Uuid::now_v7());
+            let event = RoomEvent {
+                replica_id,
+                origin_conn_id: Some(conn_id),
+                message: PubSubMessage::SyncChange { change: vec![1, 2, 3] },
+            };
+
+            let json = serde_json::to_value(&event).unwrap();
+            assert_eq!(json["replica_id"], replica_id.to_string());
+            assert_eq!(json["origin_conn_id"], conn_id.to_string());
+            assert_eq!(json["message"]["type"], "sync_change");
+
+            let decoded: RoomEvent = serde_json::from_value(json).unwrap();
+            assert_eq!(decoded.origin_conn_id, Some(conn_id));
+            assert!(matches!(decoded.message, PubSubMessage::SyncChange { change } if change == [1, 2, 3]));
+        }
+
+        fn memory_fanout(hub: &MemoryHub) -> Fanout {
+            Fanout::new(std::sync::Arc::new(MemoryBroker::new(hub)))
+        }
+    }
+
+    mod broker {
+        use super::*;
+        use crate::app::ServiceContext;
+        use crate::app::domains::document::{fanout, service};
+        use crate::app::domains::document::models::{Connection, Rooms};
+        use crate::app::domains::execution::models::{RunEvent, RunKind};
+        use crate::app::domains::document::broker::postgres::Notification;
+        use crate::app::domains::document::sessions::MemorySessionStore;
+        use crate::constants::document::ROOM_NOTIFY_MAX_BYTES;
+        use crate::core::app_data::AppData;
+        use tokio::sync::mpsc::Receiver;
+
+        /// Репліка без зовнішніх з'єднань: пул Postgres ледачий, події йдуть через `hub`,
+        /// а присутність і квитки зберігаються в пам'яті.
+        fn replica(hub: &MemoryHub) -> AppData {
+            let pool = sqlx::PgPool::connect_lazy("postgres://localhost/cowrite").unwrap();
+            AppData::builder()
+                .with_pool(pool)
+                .with_rooms(Rooms::default())
+                .with_session_store(std::sync::Arc::new(MemorySessionStore::default()))
+                .with_cancel_token(Default::default())
+                .with_jwt_secret("secret".into())
+                .with_ws_settings(WsSettings { heartbeat_interval_secs: 15, pong_timeout_secs: 10, idle_timeout_secs: 600 })
+                .with_broker(std::sync::Arc::new(MemoryBroker::new(hub)))
+                .build()
+                .unwrap()
+        }
+
+        fn join(app_data: &AppData, doc_id: Uuid) -> (Uuid, Receiver<OutboundFrame>) {
+            let (outbound, rx) = Outbound::channel(8);
+            let connection = Connection {
+                id: Uuid::now_v7(),
+                user_id: Uuid::now_v7(),
+                username: "user".into(),
+                role: SessionRole::Editor,
+                outbound,
+                capabilities: Vec::new(),
+                following: None,
+                closed: tokio_util::sync::CancellationToken::new(),
+            };
+            let id = connection.id;
+            app_data.rooms.value.entry(doc_id).or_default().push(connection);
+            app_data.fanout.subscribe(doc_id);
+            (id, rx)
+        }
+
+        fn drain(rx: &mut Receiver<OutboundFrame>) -> usize {
+            std::iter::from_fn(|| rx.try_recv().ok()).count()
+        }
+
+        /// Тест 45: Подію отримують усі підключення обох реплік рівно один раз, окрім ініціатора.
+        #[actix_rt::test]
+        async fn memory_broker_fans_out_between_replicas() {
+            let hub = MemoryHub::default();
+            let (first, second) = (replica(&hub), replica(&hub));
+            let doc_id = Uuid::now_v7();
+            let (origin, mut origin_rx) = join(&first, doc_id);
+            let (_, mut neighbour_rx) = join(&first, doc_id);
+            let (_, mut remote_rx) = join(&second, doc_id);
+            fanout::run(&first);
+            fanout::run(&second);
+            actix_rt::task::yield_now().await;
+
+            let event = FileSystemEvent::Delete { path: "src/old.rs".into() };
+            let message = PubSubMessage::FileSystemEvent { event };
+            fanout::publish(&ServiceContext::from(&first), doc_id, Some(origin), message).await.unwrap();
+
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(1), remote_rx.recv()).await;
+            assert!(matches!(frame, Ok(Some(OutboundFrame::Text(text))) if text.contains("src/old.rs")));
+            actix_rt::task::yield_now().await;
+            assert_eq!(drain(&mut remote_rx), 0);
+            assert_eq!(drain(&mut neighbour_rx), 1);
+            assert_eq!(drain(&mut origin_rx), 0);
+            assert_eq!(second.fanout.metrics().delivered, 1);
+            assert_eq!(first.fanout.metrics().delivered, 0);
+        }
+
+        /// Тест 46: Сповіщення Postgres передає подію напряму або посилання на збережений запис.
+        #[test]
+        fn notification_round_trips() {
+            let doc_id = Uuid::now_v7();
+            let inline = Notification::Inline { doc_id, payload: r#"{"replica_id":"x"}"#.into() };
+            let stored = Notification::Stored { doc_id, id: Uuid::now_v7() };
+
+            for notification in [inline, stored] {
+                assert_eq!(Notification::parse(&notification.to_string()), Some(notification));
+            }
+            assert_eq!(Notification::parse("not-a-uuid {}"), None);
+            assert_eq!(Notification::parse(&format!("{doc_id} @broken")), None);
+        }
+
+        /// Тест 47: Подія, що не вміщується в ліміт `NOTIFY`, зберігається в таблиці.
+        #[test]
+        fn large_events_are_stored() {
+            assert!(Notification::fits_inline(&"x".repeat(1_000)));
+            assert!(!Notification::fits_inline(&"x".repeat(ROOM_NOTIFY_MAX_BYTES)));
+        }
+
+        /// Тест 48: Подія запуску доходить до всіх учасників з можливістю `runs` на обох репліках, включно з ініціатором.
+        #[actix_rt::test]
+        async fn run_events_reach_capable_participants() {
+            let hub = MemoryHub::default();
+            let (first, second) = (replica(&hub), replica(&hub));
+            let doc_id = Uuid::now_v7();
+            let (starter, mut starter_rx) = join(&first, doc_id);
+            let (_, mut legacy_rx) = join(&first, doc_id);
+            let (remote, mut remote_rx) = join(&second, doc_id);
+            first.rooms.set_capabilities(&doc_id, starter, vec!["runs".into()]);
+            second.rooms.set_capabilities(&doc_id, remote, vec!["runs".into()]);
+            fanout::run(&first);
+            fanout::run(&second);
+            actix_rt::task::yield_now().await;
+
+            let event = RunEvent::Started {
+                run_id: Uuid::now_v7(),
+                kind: RunKind::Run,
+                interactive: false,
+                conn_id: starter,
+                user_id: Uuid::now_v7(),
+                username: "alice".into(),
+            };
+            service::publish_run_event(doc_id, event, &ServiceContext::from(&first)).await.unwrap();
+
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(1), remote_rx.recv()).await;
+            assert!(matches!(frame, Ok(Some(OutboundFrame::Text(text))) if text.contains("run_started") && text.contains("alice")));
+            assert_eq!(drain(&mut starter_rx), 1);
+            assert_eq!(drain(&mut legacy_rx), 0);
+        }
+
+        /// Тест 50: Відкликання доступу прибирає сесії користувача з кімнати та сигналізує їхнім циклам завершитись.
+        #[actix_rt::test]
+        async fn revoked_access_stops_connection_loops() {
+            let hub = MemoryHub::default();
+            let (first, second) = (replica(&hub), replica(&hub));
+            let doc_id = Uuid::now_v7();
+            let (revoked, _revoked_rx) = join(&second, doc_id);
+            let (_, _other_rx) = join(&second, doc_id);
+            let (user_id, closed) = second.rooms.value.get(&doc_id).unwrap()
+                .iter()
+                .find(|c| c.id == revoked)
+                .map(|c| (c.user_id, c.closed.clone()))
+                .unwrap();
+            fanout::run(&first);
+            fanout::run(&second);
+            actix_rt::task::yield_now().await;
+
+            service::publish_access_change(doc_id, user_id, None, &ServiceContext::from(&first)).await;
+
+            tokio::time::timeout(std::time::Duration::from_secs(1), closed.cancelled()).await
+                .expect("цикл підключення отримує сигнал завершення");
+            let remaining: Vec<Uuid> = second.rooms.value.get(&doc_id).unwrap().iter().map(|c| c.id).collect();
+            assert_eq!(remaining.len(), 1);
+            assert!(!remaining.contains(&revoked));
+        }
+
+        /// Тест 52: Закриття підключення скасовує його інтерактивний запуск, stdin якого лишився відкритим.
+        #[actix_rt::test]
+        async fn closing_connection_cancels_its_run() {
+            use crate::app::domains::document::ws_handler::cancel_connection_run;
+            use crate::app::domains::execution::models::RunStatus;
+            use crate::app::domains::execution::runs::{spawn_run, ActiveRun};
+            use crate::app::domains::execution::service::RunInput;
+            use crate::app::domains::execution::queue::JobOwner;
+
+            let app_data = replica(&MemoryHub::default());
+            let doc_id = Uuid::now_v7();
+            let (conn_id, _rx) = join(&app_data, doc_id);
+            let user_id = Uuid::now_v7();
+            let job = app_data.jobs.submit(Uuid::now_v7(), JobOwner { user_id, doc_id }).unwrap();
+            let (stdin, lines) = tokio::sync::mpsc::channel(4);
+            let run = ActiveRun { doc_id, conn_id, user_id, username: "alice".into(), kind: RunKind::Run, stdin: Some(stdin) };
+            let mut files = std::collections::HashMap::new();
+            files.insert("main.rs".to_string(), "fn main() { let _ = std::io::stdin().read_line(&mut String::new()); }".to_string());
+            let (events, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+            let run_id = spawn_run(&app_data.runs, job, run, files, Some(RunInput::Interactive(lines)), move |event| {
+                let _ = events.send(event);
+            });
+
+            cancel_connection_run(doc_id, conn_id, user_id, &app_data);
+
+            let finished = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                while let Some(event) = events_rx.recv().await {
+                    if let RunEvent::Finished { status, .. } = event {
+                        return Some(status);
+                    }
+                }
+                None
+            })
+            .await
+            .expect("запуск завершується після закриття підключення");
+            assert_eq!(finished, Some(RunStatus::Cancelled));
+            assert!(app_data.runs.get(run_id).is_none());
+        }
+
+        /// Тест 53: Запуск цієї репліки скасовується одразу, а запит на чужий запуск лише передається іншим.
+        #[actix_rt::test]
+        async fn cancel_run_reports_whether_it_was_local() {
+            use crate::app::domains::execution::queue::JobOwner;
+
+            let app_data = replica(&MemoryHub::default());
+            let ctx = ServiceContext::from(&app_data);
+            let doc_id = Uuid::now_v7();
+            let user_id = Uuid::now_v7();
+            let job = app_data.jobs.submit(Uuid::now_v7(), JobOwner { user_id, doc_id }).unwrap();
+
+            let local = service::cancel_run(doc_id, job.id(), user_id, false, None, &ctx).await.unwrap();
+            let remote = service::cancel_run(doc_id, Uuid::now_v7(), user_id, false, None, &ctx).await.unwrap();
+            assert!(local);
+            assert!(!remote);
+            assert!(job.run(|_| {}, async {}).await.is_none());
+        }
+    }
+}
@@ -1,36 +1,54 @@
 use actix_web::{
     HttpRequest, Responder, ResponseError,
-    web::{self, Path, Query},
+    http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL},
+    web::{self, Bytes, Path, Query},
 };
-use actix_ws::{CloseReason, Message, MessageStream, Session};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream};
 use futures_util::StreamExt as _;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
-use redis::Client;
 
 use super::models::{
-    Connection, FileSystemMessage, PubSubMessage, FileSystemEvent,
-    SessionRole, ServerMessage,
+    AccessRequestEvent, ChatEvent, ClientEnvelope, ClientMessage, Connection, CursorState, ErrorCode, FileSystemEvent, Outbound, OutboundFrame,
+    ParticipantInfo, ProtocolError, PubSubMessage, ServerMessage, SessionRole, Viewport, WsTicket,
 };
-use super::{service, repository};
+use super::fanout;
+use super::presence::{self, broadcast_participants};
+use super::service;
 use crate::core::app_data::AppData;
-use crate::app::RequestResult;
+use crate::core::config_builder::WsSettings;
+use crate::app::{RequestError, RequestResult};
 use crate::app::domains::auth::validate_token;
+use crate::app::domains::execution::models::{RunEvent, RunKind};
+use crate::app::domains::execution::queue::JobOwner;
+use crate::app::domains::execution::runs::{self, ActiveRun};
+use crate::app::domains::execution::service::RunInput;
+use crate::constants::execution::RUN_STDIN_QUEUE_CAPACITY;
+use crate::constants::ws::{
+    HANDSHAKE_TIMEOUT_SECONDS, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, SERVER_CAPABILITIES, WS_BEARER_PROTOCOL,
+};
 
 // ─────────────────────────── Query params ────────────────────────────────────
 
 #[derive(Debug, Deserialize)]
-pub(crate) struct WsQuery {
-    token: String,
+pub struct WsQuery {
+    /// Одноразовий квиток з `POST /documents/{id}/ws-ticket`.
+    #[serde(default)]
+    ticket: Option<String>,
 }
 
 // ─────────────────────────── WS Handler ──────────────────────────────────────
 
 /// Обробник WebSocket-з'єднання для спільного редагування документа.
 ///
-/// Валідує JWT з query param `?token=`, призначає роль (власник = Manager, перший чужий = Editor, решта = Reader),
-/// надсилає snapshot файлів з БД, запускає цикл обробки повідомлень.
+/// Автентифікує клієнта одноразовим квитком з `?ticket=` або JWT у `Sec-WebSocket-Protocol`
+/// (підпротокол `cowrite.bearer`, за яким іде токен), призначає роль (власник = Manager, учасник — збережена роль)
+/// та запускає цикл обробки повідомлень. Клієнт має почати з `hello`, після якого отримує `welcome`
+/// та snapshot файлів з БД.
 #[tracing::instrument(
     name = "ws_handler",
     skip(req, stream, app_data),
@@ -49,15 +67,31 @@ pub async fn ws_handler(
     app_data: web::Data<AppData>,
 ) -> RequestResult<impl Responder> {
     let doc_id = doc_id.into_inner();
-
-    // Валідуємо JWT
-    let claims = validate_token(&query.token, &app_data.jwt_secret)
-        .map_err(|_| crate::app::RequestError::unauthorized("Невалідний токен для WS"))?;
-
-    let (res, mut session, msg_stream) = actix_ws::handle(&req, stream)?;
     let app_data = app_data.get_ref().clone();
     let ctx = crate::app::ServiceContext::from(&app_data);
 
+    let bearer = bearer_from_protocols(&req);
+    let identity = match (&query.ticket, &bearer) {
+        (Some(ticket), _) => service::redeem_ws_ticket(doc_id, ticket, &ctx).await?,
+        (None, Some(token)) => {
+            let claims = validate_token(token, &app_data.jwt_secret)
+                .map_err(|_| RequestError::unauthorized("Невалідний токен для WS"))?;
+            WsTicket { doc_id, user_id: claims.sub, username: claims.username }
+        }
+        (None, None) => return Err(RequestError::unauthorized("Відсутній квиток підключення")),
+    };
+
+    // Визначаємо роль: власник = Manager, учасник — збережена роль у DB; решта не має доступу
+    let role = service::get_user_role(doc_id, identity.user_id, &ctx)
+        .await?
+        .ok_or_else(|| RequestError::forbidden("Немає доступу до документа"))?;
+
+    let (mut res, mut session, msg_stream) = actix_ws::handle(&req, stream)?;
+    if bearer.is_some() {
+        // Браузер розриває з'єднання, якщо сервер не підтвердить запропонований підпротокол
+        res.headers_mut().insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static(WS_BEARER_PROTOCOL));
+    }
+
     // Надсилаємо клієнту накопичені Automerge-зміни
     if let Err(err) = service::send_existing_changes(doc_id, &mut session, &ctx).await {
         tracing::error!("Не вдалося надіслати наявні зміни: {err}");
@@ -65,57 +99,41 @@ pub async fn ws_handler(
         return Err(err);
     }
 
-    // Визначаємо роль: власник = Manager, решта — збережена роль у DB або Reader
-    let doc_owner_id = repository::read(doc_id, ctx.db_pool).await
-        .ok()
-        .and_then(|d| d.owner_id);
-    let is_owner = doc_owner_id.map(|oid| oid == claims.sub).unwrap_or(false);
-    let role = if is_owner {
-        SessionRole::Manager
-    } else {
-        repository::get_member_role(doc_id, claims.sub, ctx.db_pool).await
-            .ok()
-            .flatten()
-            .unwrap_or(SessionRole::Reader)
-    };
-
     let connection = Connection {
         id: Uuid::now_v7(),
-        user_id: claims.sub,
-        username: claims.username.clone(),
+        user_id: identity.user_id,
+        username: identity.username.clone(),
         role,
-        session: session.clone(),
+        outbound: Outbound::spawn(session),
+        capabilities: Vec::new(),
+        following: None,
+        closed: CancellationToken::new(),
     };
 
-    // Надсилаємо snapshot файлів з БД новому учаснику
-    if let Ok(files) = service::get_project_files(doc_id, &ctx).await {
-        if !files.is_empty() {
-            let snapshot_msg = serde_json::json!({
-                "event": {
-                    "action": "snapshot",
-                    "files": files
-                }
-            });
-            if let Ok(text) = serde_json::to_string(&snapshot_msg) {
-                let _ = session.clone().text(text).await;
-            }
-        }
-    }
-
     add_connection(&app_data, doc_id, connection.clone());
-    broadcast_participants(&app_data, doc_id).await;
-    tracing::info!("Створено WebSocket підключення для документа {doc_id} (user: {})", claims.username);
+    if let Err(err) = presence::join(doc_id, &ParticipantInfo::from(&connection), app_data.sessions.as_ref()).await {
+        tracing::warn!("Не вдалося зареєструвати присутність {}: {err}", connection.id);
+    }
+    broadcast_participants(&ctx, doc_id).await;
+    tracing::info!("Створено WebSocket підключення для документа {doc_id} (user: {})", identity.username);
 
-    handler_connection(doc_id, session, msg_stream, connection, app_data);
+    handler_connection(doc_id, msg_stream, connection, app_data);
 
     Ok(res)
 }
 
+/// Повертає JWT з `Sec-WebSocket-Protocol: cowrite.bearer, <token>`.
+pub(crate) fn bearer_from_protocols(req: &HttpRequest) -> Option<String> {
+    let header = req.headers().get(SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+    let mut protocols = header.split(',').map(str::trim);
+    protocols.find(|p| *p == WS_BEARER_PROTOCOL)?;
+    protocols.next().filter(|token| !token.is_empty()).map(str::to_string)
+}
+
 // ─────────────────────────── Connection loop ─────────────────────────────────
 
 fn handler_connection(
     doc_id: Uuid,
-    mut session: Session,
     mut msg_stream: MessageStream,
     connection: Connection,
     app_data: AppData,
@@ -123,25 +141,97 @@ fn handler_connection(
     actix_rt::spawn({
         async move {
             let ctx = crate::app::ServiceContext::from(&app_data);
+            let mut handshake_done = false;
+            let mut close_reason = None;
+
+            let handshake_deadline = time::sleep(Duration::from_secs(HANDSHAKE_TIMEOUT_SECONDS));
+            tokio::pin!(handshake_deadline);
+
+            let heartbeat_every = app_data.ws.heartbeat_interval();
+            let mut heartbeat = time::interval_at(Instant::now() + heartbeat_every, heartbeat_every);
+            heartbeat.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            let mut last_activity = Instant::now();
+            let mut ping_sent_at: Option<Instant> = None;
+
             loop {
                 tokio::select! {
+                    // Закриття сервером важливіше за наступні кадри клієнта: після нього команди не виконуються
+                    biased;
+                    _ = connection.closed.cancelled() => {
+                        close_reason = Some(policy_close("Доступ до документа відкликано"));
+                        break;
+                    }
+                    _ = heartbeat.tick() => {
+                        let now = Instant::now();
+                        match check_liveness(now, ping_sent_at, last_activity, &app_data.ws) {
+                            Liveness::Alive => {}
+                            Liveness::PongOverdue => {
+                                tracing::info!("Клієнт {} не відповів на ping, з'єднання прибрано", connection.id);
+                                close_reason = Some(CloseReason {
+                                    code: CloseCode::Away,
+                                    description: Some("Немає відповіді на ping".to_string()),
+                                });
+                                break;
+                            }
+                            Liveness::Idle => {
+                                tracing::info!("Сесію {} закрито через неактивність", connection.id);
+                                close_reason = Some(CloseReason {
+                                    code: CloseCode::Away,
+                                    description: Some("Сесію закрито через неактивність".to_string()),
+                                });
+                                break;
+                            }
+                        }
+
+                        if ping_sent_at.is_none() {
+                            connection.outbound.push(OutboundFrame::Ping(Bytes::new()));
+                            ping_sent_at = Some(now);
+                        }
+                    }
+                    _ = connection.outbound.overflowed() => {
+                        tracing::info!("Клієнт {} не встигає отримувати повідомлення — відключаємо", connection.id);
+                        break;
+                    }
+                    _ = &mut handshake_deadline, if !handshake_done => {
+                        tracing::info!("Клієнт {} не надіслав hello вчасно", connection.id);
+                        let err = ProtocolError::new(None, ErrorCode::HandshakeRequired, "Не отримано hello");
+                        send_message(&connection.outbound, &err.into());
+                        close_reason = Some(policy_close("Не отримано hello"));
+                        break;
+                    }
                     msg = msg_stream.next() => {
+                        if matches!(msg, Some(Ok(Message::Binary(_) | Message::Text(_)))) {
+                            last_activity = Instant::now();
+                        }
+
                         match msg {
+                            // ── Бінарний кадр до рукостискання: версія та можливості ще не узгоджені ──
+                            Some(Ok(Message::Binary(_))) if !handshake_done => {
+                                tracing::info!("Клієнт {} надіслав бінарний кадр до hello", connection.id);
+                                let err = ProtocolError::new(None, ErrorCode::HandshakeRequired, "Спочатку надішліть hello");
+                                send_message(&connection.outbound, &err.into());
+                                close_reason = Some(CloseReason {
+                                    code: CloseCode::Protocol,
+                                    description: Some("Бінарний кадр до hello".to_string()),
+                                });
+                                break;
+                            }
+
                             // ── Бінарний кадр: Automerge sync ──────────────
                             Some(Ok(Message::Binary(bin))) => {
                                 // Перевіряємо роль
-                                let can_edit = app_data.rooms.value
-                                    .get(&doc_id)
-                                    .and_then(|r| r.iter().find(|c| c.id == connection.id).map(|c| c.role.can_edit()))
+                                let can_edit = app_data.rooms
+                                    .get_role(&doc_id, connection.id)
+                                    .map(|r| r.can_edit())
                                     .unwrap_or(false);
 
                                 if !can_edit {
-                                    let denied = ServerMessage::PermissionDenied {
-                                        reason: "Недостатньо прав для редагування (роль: Reader)".into()
-                                    };
-                                    if let Ok(text) = serde_json::to_string(&denied) {
-                                        let _ = session.text(text).await;
-                                    }
+                                    let denied = ProtocolError::new(
+                                        None,
+                                        ErrorCode::Forbidden,
+                                        "Недостатньо прав для редагування (роль: Reader)",
+                                    );
+                                    send_message(&connection.outbound, &denied.into());
                                     continue;
                                 }
 
@@ -151,21 +241,22 @@ fn handler_connection(
 
                                 let response: WsResponse = push_result.into();
                                 let binary_response = serde_json::to_vec(&response).unwrap();
-                                if let Err(err) = session.binary(binary_response).await {
-                                    tracing::warn!("Не вдалося надіслати Automerge-відповідь: {err}");
-                                    break;
-                                }
+                                connection.outbound.push(OutboundFrame::Binary(Bytes::from(binary_response)));
                             }
 
-                            // ── Текстовий кадр: FS-подія або RoleChange ───
+                            // ── Текстовий кадр: команда протоколу ─────────
                             Some(Ok(Message::Text(text))) => {
-                                handle_text_message(
+                                close_reason = handle_text_message(
                                     doc_id,
-                                    connection.id,
-                                    text.to_string(),
+                                    &connection,
+                                    &mut handshake_done,
+                                    &text,
                                     &ctx,
                                     &app_data,
                                 ).await;
+                                if close_reason.is_some() {
+                                    break;
+                                }
                             }
 
                             // ── Закриття з'єднання ─────────────────────────
@@ -179,7 +270,13 @@ fn handler_connection(
                                 break;
                             }
 
-                            Some(_) => (), // ping/pong
+                            Some(Ok(Message::Ping(bytes))) => {
+                                connection.outbound.push(OutboundFrame::Pong(bytes));
+                            }
+
+                            Some(Ok(Message::Pong(_))) => ping_sent_at = None,
+
+                            Some(_) => (),
                             None => {
                                 tracing::info!("WebSocket потік завершився");
                                 break;
@@ -190,9 +287,13 @@ fn handler_connection(
             }
 
             // Видаляємо підключення та оповіщаємо інших
+            cancel_connection_run(doc_id, connection.id, connection.user_id, &app_data);
             app_data.rooms.remove_connection(&doc_id, connection.id);
-            broadcast_participants(&app_data, doc_id).await;
-            close_session(session, None).await;
+            if let Err(err) = presence::leave(doc_id, connection.id, app_data.sessions.as_ref()).await {
+                tracing::warn!("Не вдалося прибрати присутність {}: {err}", connection.id);
+            }
+            broadcast_participants(&ctx, doc_id).await;
+            connection.outbound.close(close_reason);
             tracing::info!("Завершено WebSocket обробник для документа {doc_id}");
         }
     });
@@ -200,130 +301,610 @@ fn handler_connection(
 
 // ─────────────────────────── Text message handler ────────────────────────────
 
+/// Обробляє текстовий фрейм клієнта: розбирає команду, виконує її та відповідає `ack` або `error`.
+///
+/// Повертає причину закриття, якщо після цієї команди з'єднання потрібно закрити.
 async fn handle_text_message(
     doc_id: Uuid,
-    conn_id: Uuid,
-    text: String,
+    connection: &Connection,
+    handshake_done: &mut bool,
+    text: &str,
     ctx: &crate::app::ServiceContext<'_>,
     app_data: &AppData,
-) {
-    // Спробуємо розпарсити як JSON для type-based повідомлень
-    if let Ok(val) = serde_json::from_str::<serde_json::Value>(&text) {
-        // Cursor position broadcast — просто ретранслюємо всім іншим, без persist
-        if val.get("type").and_then(|t| t.as_str()) == Some("cursor") {
-            app_data.rooms.send_text(&doc_id, conn_id, text).await;
-            return;
-        }
-
-        if val.get("type").and_then(|t| t.as_str()) == Some("role_change") {
-            let target = val.get("target_conn_id").and_then(|v| v.as_str()).and_then(|s| Uuid::parse_str(s).ok());
-            let role_str = val.get("new_role").and_then(|v| v.as_str());
-
-            if let (Some(target_conn_id), Some(role_str)) = (target, role_str) {
-                let new_role = match role_str {
-                    "reader"  => SessionRole::Reader,
-                    "editor"  => SessionRole::Editor,
-                    "manager" => SessionRole::Manager,
-                    _ => return,
+) -> Option<CloseReason> {
+    let envelope = match ClientEnvelope::parse(text) {
+        Ok(envelope) => envelope,
+        Err(err) => {
+            tracing::debug!("Відхилено повідомлення від {}: {}", connection.id, err.message);
+            send_message(&connection.outbound, &err.into());
+            return None;
+        }
+    };
+    let request_id = envelope.request_id;
+
+    let result = match envelope.message {
+        ClientMessage::Hello { protocol_version, capabilities } => {
+            if *handshake_done {
+                Err(RequestError::bad_request("Рукостискання вже виконано"))
+            } else {
+                let Some(version) = negotiate_version(protocol_version) else {
+                    let err = ProtocolError::new(
+                        request_id,
+                        ErrorCode::UnsupportedVersion,
+                        format!(
+                            "Версія протоколу {} не підтримується (підтримуються {}..={})",
+                            protocol_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+                        ),
+                    );
+                    send_message(&connection.outbound, &err.into());
+                    return Some(policy_close("Непідтримувана версія протоколу"));
                 };
 
-                let changed = app_data.rooms.set_role(&doc_id, target_conn_id, new_role, conn_id);
-                if changed {
-                    // Зберігаємо нову роль в БД
-                    let target_user_id = app_data.rooms.value.get(&doc_id)
-                        .and_then(|r| r.iter().find(|c| c.id == target_conn_id).map(|c| c.user_id));
-                    if let Some(uid) = target_user_id {
-                        if let Err(e) = repository::set_member_role(doc_id, uid, role_str, ctx.db_pool).await {
-                            tracing::error!("Не вдалося зберегти роль в БД: {e}");
-                        }
-                    }
-                    broadcast_participants(app_data, doc_id).await;
-                } else {
-                    // Відмова
-                    if let Some(room) = app_data.rooms.value.get_mut(&doc_id) {
-                        if let Some(mut requester) = room.iter().find(|c| c.id == conn_id).cloned() {
-                            let denied = ServerMessage::PermissionDenied {
-                                reason: "Недостатньо прав для зміни ролі".into()
-                            };
-                            if let Ok(t) = serde_json::to_string(&denied) {
-                                let _ = requester.session.text(t).await;
-                            }
-                        }
-                    }
+                let capabilities = negotiate_capabilities(&capabilities);
+                app_data.rooms.set_capabilities(&doc_id, connection.id, capabilities.clone());
+                *handshake_done = true;
+                let chat_enabled = capabilities.iter().any(|c| c == "chat");
+                let runs_enabled = capabilities.iter().any(|c| c == "runs");
+                let role = app_data.rooms.get_role(&doc_id, connection.id).unwrap_or(SessionRole::Reader);
+                let is_manager = role.can_manage();
+
+                let welcome = ServerMessage::Welcome {
+                    protocol_version: version,
+                    conn_id: connection.id,
+                    role,
+                    capabilities,
+                };
+                send_message(&connection.outbound, &welcome);
+                send_snapshot(doc_id, &connection.outbound, ctx).await;
+                if chat_enabled {
+                    send_chat_history(doc_id, &connection.outbound, ctx).await;
+                }
+                if runs_enabled {
+                    send_run_policy(doc_id, &connection.outbound, ctx).await;
+                }
+                if is_manager {
+                    send_access_requests(doc_id, connection.user_id, &connection.outbound, ctx).await;
                 }
+
+                // Список учасників надсилаємо напряму: підписка кімнати на Redis могла ще не встановитися
+                let participants = presence::list(doc_id, ctx.sessions)
+                    .await
+                    .map(|(participants, _)| participants)
+                    .unwrap_or_else(|_| app_data.rooms.get_participants(&doc_id));
+                send_message(&connection.outbound, &ServerMessage::ParticipantsUpdate { participants });
+                return None;
             }
-            return;
         }
-    }
-
-    // Інакше — FS-подія
-    let msg: FileSystemMessage = match serde_json::from_str(&text) {
-        Ok(m) => m,
-        Err(err) => {
-            tracing::warn!("Не вдалося розпарсити подію ФС від {conn_id}: {err}");
-            return;
+        _ if !*handshake_done => {
+            let err = ProtocolError::new(request_id, ErrorCode::HandshakeRequired, "Спочатку надішліть hello");
+            send_message(&connection.outbound, &err.into());
+            return None;
         }
+        ClientMessage::FsEvent { event } => {
+            handle_fs_event(doc_id, connection, event, ctx, app_data).await
+        }
+        ClientMessage::RoleChange { target_conn_id, new_role } => {
+            handle_role_change(doc_id, connection.id, target_conn_id, new_role, ctx, app_data).await
+        }
+        ClientMessage::Cursor { path, position, selection } => {
+            let cursor = CursorState { path, position, selection };
+            handle_cursor(doc_id, connection.id, cursor, ctx).await
+        }
+        ClientMessage::Activity { active_file, idle } => {
+            handle_activity(doc_id, connection.id, active_file, idle, ctx).await
+        }
+        ClientMessage::Follow { leader_conn_id } => {
+            handle_follow(doc_id, connection, Some(leader_conn_id), ctx).await
+        }
+        ClientMessage::Unfollow => handle_follow(doc_id, connection, None, ctx).await,
+        ClientMessage::Viewport { viewport } => handle_viewport(doc_id, connection.id, viewport, ctx).await,
+        ClientMessage::ChatHistory { before, limit } => {
+            service::chat_history(doc_id, before, limit, ctx).await.map(|page| {
+                send_message(&connection.outbound, &page.into());
+            })
+        }
+        command @ (ClientMessage::ChatSend { .. } | ClientMessage::ChatEdit { .. } | ClientMessage::ChatDelete { .. }) => {
+            handle_chat(doc_id, connection.user_id, command, ctx).await
+        }
+        ClientMessage::RequestAccess { message } => {
+            match service::request_edit_access(doc_id, connection.user_id, message.as_deref(), ctx).await {
+                Ok(event) => {
+                    publish_access_request(doc_id, event, ctx).await;
+                    Ok(())
+                }
+                Err(err) => Err(err),
+            }
+        }
+        ClientMessage::ResolveAccessRequest { access_request_id, approve } => {
+            handle_resolve_access_request(doc_id, connection, access_request_id, approve, ctx, app_data).await
+        }
+        ClientMessage::RunStart { kind, version, stdin, interactive } => {
+            handle_run_start(doc_id, connection, kind, version, stdin, interactive, ctx, app_data).await
+        }
+        ClientMessage::RunStdin { run_id, data } => app_data.runs.write_stdin(run_id, connection.id, data),
+        ClientMessage::RunStdinClose { run_id } => app_data.runs.close_stdin(run_id, connection.id),
+        ClientMessage::RunCancel { run_id } => handle_run_cancel(doc_id, connection, run_id, ctx, app_data).await,
+    };
+
+    let reply = match result {
+        Ok(()) => request_id.map(|request_id| ServerMessage::Ack { request_id }),
+        Err(err) => Some(ProtocolError::new(request_id, error_code(&err), err.to_string()).into()),
     };
+    if let Some(reply) = reply {
+        send_message(&connection.outbound, &reply);
+    }
+
+    None
+}
 
+/// Зберігає та поширює подію файлової системи від учасника.
+async fn handle_fs_event(
+    doc_id: Uuid,
+    connection: &Connection,
+    event: FileSystemEvent,
+    ctx: &crate::app::ServiceContext<'_>,
+    app_data: &AppData,
+) -> RequestResult<()> {
     // Захист папки src
-    match &msg.event {
-        FileSystemEvent::Delete { path } => {
-            if path == "src" {
-                tracing::warn!("Спроба видалення 'src' відхилена");
-                return;
-            }
+    match &event {
+        FileSystemEvent::Delete { path } if path == "src" => {
+            return Err(RequestError::forbidden("Папку 'src' не можна видалити"));
         }
-        FileSystemEvent::Rename { old_path, new_path } => {
-            if old_path == "src" || new_path == "src" {
-                tracing::warn!("Спроба перейменування 'src' відхилена");
-                return;
-            }
+        FileSystemEvent::Rename { old_path, new_path } if old_path == "src" || new_path == "src" => {
+            return Err(RequestError::forbidden("Папку 'src' не можна перейменувати"));
+        }
+        FileSystemEvent::Snapshot { .. } => {
+            return Err(RequestError::bad_request("Snapshot надсилається лише сервером"));
         }
         _ => {}
     }
 
-    // Перевіряємо роль для FS-подій (крім snapshot — він надходить від сервера)
-    if !matches!(msg.event, FileSystemEvent::Snapshot { .. }) {
-        let can_edit = app_data.rooms.value
-            .get(&doc_id)
-            .and_then(|r| r.iter().find(|c| c.id == conn_id).map(|c| c.role.can_edit()))
-            .unwrap_or(false);
+    let can_edit = app_data.rooms
+        .get_role(&doc_id, connection.id)
+        .map(|r| r.can_edit())
+        .unwrap_or(false);
+    if !can_edit {
+        return Err(RequestError::forbidden("Недостатньо прав для зміни файлів (роль: Reader)"));
+    }
+
+    service::save_fs_event(doc_id, &event, connection.user_id, ctx).await?;
+    tracing::debug!("Подія файлової системи [{:?}] для документа {doc_id}", event);
+
+    if let Err(err) = service::publish_fs_event(doc_id, Some(connection.id), event, ctx).await {
+        tracing::warn!("Не вдалося опублікувати подію файлової системи документа {doc_id} для інших реплік: {err}");
+    }
+    Ok(())
+}
+
+/// Зберігає позицію курсора в присутності та розсилає її учасникам усіх реплік.
+async fn handle_cursor(
+    doc_id: Uuid,
+    conn_id: Uuid,
+    cursor: CursorState,
+    ctx: &crate::app::ServiceContext<'_>,
+) -> RequestResult<()> {
+    presence::update(doc_id, conn_id, ctx.sessions, |p| {
+        if cursor.path.is_some() {
+            p.active_file = cursor.path.clone();
+        }
+        p.cursor = Some(cursor.clone());
+        p.idle = false;
+    })
+    .await?;
+
+    if let Err(err) = fanout::publish(ctx, doc_id, Some(conn_id), PubSubMessage::Cursor { cursor }).await {
+        tracing::warn!("Не вдалося опублікувати курсор {conn_id} для інших реплік: {err}");
+    }
+    Ok(())
+}
+
+/// Надсилає позицію курсора локальним клієнтам з можливістю `cursors`.
+fn relay_cursor(rooms: &super::models::Rooms, doc_id: Uuid, conn_id: Uuid, cursor: CursorState) {
+    let message = ServerMessage::Cursor { conn_id, cursor };
+    if let Ok(text) = serde_json::to_string(&message) {
+        rooms.send_presence_to_capable(&doc_id, conn_id, "cursors", text);
+    }
+}
+
+/// Вмикає або вимикає режим follow для підключення.
+///
+/// Лідер може бути підключений до іншої репліки, тому його шукаємо в присутності кімнати.
+/// Новий послідовник одразу отримує останню відому видиму область лідера.
+async fn handle_follow(
+    doc_id: Uuid,
+    connection: &Connection,
+    leader: Option<Uuid>,
+    ctx: &crate::app::ServiceContext<'_>,
+) -> RequestResult<()> {
+    let leader_info = match leader {
+        Some(leader_id) if leader_id == connection.id => {
+            return Err(RequestError::bad_request("Не можна стежити за власним підключенням"));
+        }
+        Some(leader_id) => Some(
+            presence::get(doc_id, leader_id, ctx.sessions)
+                .await?
+                .ok_or_else(|| RequestError::not_found("Учасника не знайдено в сесії"))?,
+        ),
+        None => None,
+    };
+
+    ctx.rooms.set_following(&doc_id, connection.id, leader);
+    presence::update(doc_id, connection.id, ctx.sessions, |p| p.following = leader).await?;
+    broadcast_participants(ctx, doc_id).await;
+
+    if let Some(ParticipantInfo { conn_id, viewport: Some(viewport), .. }) = leader_info {
+        send_message(&connection.outbound, &ServerMessage::Viewport { conn_id, viewport });
+    }
+    Ok(())
+}
+
+/// Зберігає видиму область учасника та пересилає її його послідовникам на всіх репліках.
+async fn handle_viewport(
+    doc_id: Uuid,
+    conn_id: Uuid,
+    viewport: Viewport,
+    ctx: &crate::app::ServiceContext<'_>,
+) -> RequestResult<()> {
+    if viewport.from_line > viewport.to_line {
+        return Err(RequestError::bad_request("Некоректний діапазон рядків"));
+    }
+
+    presence::update(doc_id, conn_id, ctx.sessions, |p| {
+        p.active_file = Some(viewport.path.clone());
+        p.viewport = Some(viewport.clone());
+        p.idle = false;
+    })
+    .await?;
+
+    if let Err(err) = fanout::publish(ctx, doc_id, Some(conn_id), PubSubMessage::Viewport { viewport }).await {
+        tracing::warn!("Не вдалося опублікувати видиму область {conn_id} для інших реплік: {err}");
+    }
+    Ok(())
+}
+
+/// Надсилає видиму область лідера його послідовникам на цій репліці.
+fn relay_viewport(rooms: &super::models::Rooms, doc_id: Uuid, conn_id: Uuid, viewport: Viewport) {
+    let message = ServerMessage::Viewport { conn_id, viewport };
+    if let Ok(text) = serde_json::to_string(&message) {
+        rooms.send_presence_to_followers(&doc_id, conn_id, text);
+    }
+}
+
+/// Оновлює активний файл та стан неактивності учасника й розсилає новий список учасників.
+async fn handle_activity(
+    doc_id: Uuid,
+    conn_id: Uuid,
+    active_file: Option<String>,
+    idle: bool,
+    ctx: &crate::app::ServiceContext<'_>,
+) -> RequestResult<()> {
+    presence::update(doc_id, conn_id, ctx.sessions, |p| {
+        p.active_file = active_file;
+        p.idle = idle;
+    })
+    .await?;
+    broadcast_participants(ctx, doc_id).await;
+    Ok(())
+}
+
+/// Змінює роль учасника (тільки Manager), зберігає її в БД та застосовує на всіх репліках.
+///
+/// Ціль може бути підключена до іншої репліки, тому її шукаємо в присутності кімнати.
+async fn handle_role_change(
+    doc_id: Uuid,
+    conn_id: Uuid,
+    target_conn_id: Uuid,
+    new_role: SessionRole,
+    ctx: &crate::app::ServiceContext<'_>,
+    app_data: &AppData,
+) -> RequestResult<()> {
+    let is_manager = app_data.rooms
+        .get_role(&doc_id, conn_id)
+        .map(|r| r.can_manage())
+        .unwrap_or(false);
+    if !is_manager {
+        return Err(RequestError::forbidden("Недостатньо прав для зміни ролі"));
+    }
+    // Менеджер не може понизити сам себе
+    if target_conn_id == conn_id {
+        return Err(RequestError::forbidden("Не можна змінити власну роль"));
+    }
+
+    let (participants, _) = presence::list(doc_id, ctx.sessions).await?;
+    let target_user_id = participants
+        .iter()
+        .find(|p| p.conn_id == target_conn_id)
+        .map(|p| p.user_id)
+        .ok_or_else(|| RequestError::not_found("Учасника не знайдено в сесії"))?;
 
-        if !can_edit {
-            tracing::debug!("FS-подія від Reader {conn_id} відхилена");
-            return;
+    service::change_member_role(doc_id, target_user_id, new_role, ctx).await
+}
+
+/// Виконує команду чату та розсилає результат учасникам кімнати.
+async fn handle_chat(
+    doc_id: Uuid,
+    user_id: Uuid,
+    command: ClientMessage,
+    ctx: &crate::app::ServiceContext<'_>,
+) -> RequestResult<()> {
+    let event = match command {
+        ClientMessage::ChatSend { body } => service::post_chat_message(doc_id, user_id, &body, ctx).await?,
+        ClientMessage::ChatEdit { message_id, body } => {
+            service::edit_chat_message(doc_id, message_id, user_id, &body, ctx).await?
+        }
+        ClientMessage::ChatDelete { message_id } => {
+            service::delete_chat_message(doc_id, message_id, user_id, ctx).await?
         }
+        _ => return Ok(()),
+    };
+
+    if let Err(err) = service::publish_chat_event(doc_id, event, ctx).await {
+        tracing::warn!("Не вдалося опублікувати подію чату документа {doc_id} для інших реплік: {err}");
+    }
+    Ok(())
+}
+
+/// Надсилає подію чату локальним клієнтам з можливістю `chat`.
+fn relay_chat(rooms: &super::models::Rooms, doc_id: Uuid, event: ChatEvent) {
+    if let Ok(text) = serde_json::to_string(&ServerMessage::from(event)) {
+        rooms.send_text_to_capable(&doc_id, Uuid::nil(), "chat", text);
     }
+}
 
-    // Зберігаємо у БД
-    if let Err(e) = service::save_fs_event(doc_id, &msg.event, ctx).await {
-        tracing::error!("Не вдалося зберегти FS-подію в БД: {e}");
+/// Розглядає запит на право редагування (тільки Manager поточної сесії).
+async fn handle_resolve_access_request(
+    doc_id: Uuid,
+    connection: &Connection,
+    access_request_id: Uuid,
+    approve: bool,
+    ctx: &crate::app::ServiceContext<'_>,
+    app_data: &AppData,
+) -> RequestResult<()> {
+    let is_manager = app_data.rooms
+        .get_role(&doc_id, connection.id)
+        .map(|r| r.can_manage())
+        .unwrap_or(false);
+    if !is_manager {
+        return Err(RequestError::forbidden("Недостатньо прав для розгляду запиту"));
     }
 
-    tracing::debug!("Подія файлової системи [{:?}] для документа {doc_id}", msg.event);
+    let event = service::resolve_access_request(doc_id, access_request_id, connection.user_id, approve, ctx).await?;
+    publish_access_request(doc_id, event, ctx).await;
+    Ok(())
+}
 
-    // 1. Надсилаємо іншим клієнтам на ЦЬОМУ сервері
-    app_data.rooms.send_text(&doc_id, conn_id, text).await;
+/// Публікує зміну запиту на право редагування; помилку Redis лише логуємо — запит уже збережено.
+async fn publish_access_request(doc_id: Uuid, event: AccessRequestEvent, ctx: &crate::app::ServiceContext<'_>) {
+    if let Err(err) = service::publish_access_request_event(doc_id, event, ctx).await {
+        tracing::warn!("Не вдалося опублікувати запит доступу документа {doc_id} для інших реплік: {err}");
+    }
+}
 
-    // 2. Публікуємо в Redis для інших реплік
-    let pubsub_msg = PubSubMessage::FileSystemEvent {
-        sender_conn_id: Uuid::nil(),
-        event: msg.event,
+/// Надсилає зміну запиту менеджерам цієї репліки, а рішення — ще й автору запиту.
+fn relay_access_request(rooms: &super::models::Rooms, doc_id: Uuid, event: AccessRequestEvent) {
+    let requester = match &event {
+        AccessRequestEvent::Resolved { user_id, .. } => Some(*user_id),
+        AccessRequestEvent::Created { .. } => None,
+    };
+    let Ok(text) = serde_json::to_string(&ServerMessage::from(event)) else {
+        return;
     };
-    if let Ok(serialized) = serde_json::to_vec(&pubsub_msg) {
-        let channel_name = format!("document:room:{}", doc_id);
-        let _ = ctx.redis.publish(&channel_name, serialized).await;
+    rooms.send_text_to_managers(&doc_id, text.clone());
+    if let Some(user_id) = requester {
+        rooms.send_text_to_user(&doc_id, user_id, text);
+    }
+}
+
+/// Надсилає менеджеру нерозглянуті запити на право редагування.
+async fn send_access_requests(doc_id: Uuid, user_id: Uuid, outbound: &Outbound, ctx: &crate::app::ServiceContext<'_>) {
+    match service::pending_access_requests(doc_id, user_id, ctx).await {
+        Ok(requests) => send_message(outbound, &ServerMessage::AccessRequests { requests }),
+        Err(err) => tracing::error!("Не вдалося завантажити запити доступу: {err}"),
+    }
+}
+
+/// Надсилає клієнту останню сторінку історії чату.
+async fn send_chat_history(doc_id: Uuid, outbound: &Outbound, ctx: &crate::app::ServiceContext<'_>) {
+    match service::chat_history(doc_id, None, None, ctx).await {
+        Ok(page) => send_message(outbound, &page.into()),
+        Err(err) => tracing::error!("Не вдалося завантажити історію чату: {err}"),
+    }
+}
+
+/// Надсилає клієнту snapshot файлів проекту з БД.
+async fn send_snapshot(doc_id: Uuid, outbound: &Outbound, ctx: &crate::app::ServiceContext<'_>) {
+    match service::get_project_files(doc_id, ctx).await {
+        Ok(files) => {
+            let snapshot = ServerMessage::FsEvent { event: FileSystemEvent::Snapshot { files } };
+            send_message(outbound, &snapshot);
+        }
+        Err(err) => tracing::error!("Не вдалося завантажити snapshot файлів: {err}"),
+    }
+}
+
+// ─────────────────────────── Runs ────────────────────────────────────────────
+
+/// Запускає збережений стан проекту (або його версію); кадри `run_*` отримують усі учасники кімнати
+/// з можливістю `runs` на всіх репліках.
+///
+/// Файли читаються з БД, тож попередні `fs_event` цього підключення вже враховані.
+/// Одночасно підключення може мати лише один запуск. В інтерактивному режимі stdin програми
+/// лишається відкритим для `run_stdin` ініціатора, доки він не надішле `run_stdin_close`.
+#[allow(clippy::too_many_arguments)]
+async fn handle_run_start(
+    doc_id: Uuid,
+    connection: &Connection,
+    kind: RunKind,
+    version: Option<Uuid>,
+    stdin: Option<String>,
+    interactive: bool,
+    ctx: &crate::app::ServiceContext<'_>,
+    app_data: &AppData,
+) -> RequestResult<()> {
+    if let Some(run_id) = app_data.runs.by_connection(connection.id) {
+        return Err(RequestError::conflict(format!("Попередній запуск {run_id} ще виконується")));
+    }
+    let role = app_data.rooms.get_role(&doc_id, connection.id).unwrap_or(SessionRole::Reader);
+    service::ensure_can_run(doc_id, &role, ctx).await?;
+    let files = service::get_project_files_at(doc_id, version, ctx).await?;
+    let job = ctx.jobs.submit(Uuid::now_v7(), JobOwner { user_id: connection.user_id, doc_id })?;
+
+    let (stdin_tx, input) = if interactive {
+        let (tx, rx) = mpsc::channel(RUN_STDIN_QUEUE_CAPACITY);
+        if let Some(data) = stdin {
+            // Черга щойно створена, тож перші дані завжди вміщуються
+            let _ = tx.try_send(data);
+        }
+        (Some(tx), Some(RunInput::Interactive(rx)))
+    } else {
+        (None, stdin.map(RunInput::Fixed))
+    };
+
+    let run = ActiveRun {
+        doc_id,
+        conn_id: connection.id,
+        user_id: connection.user_id,
+        username: connection.username.clone(),
+        kind,
+        stdin: stdin_tx,
+    };
+    let publish = publish_run_events(doc_id, app_data);
+    // Клієнт без можливості `runs` не отримує подій кімнати, тож вивід власного запуску йому надсилаємо напряму
+    let direct = (!app_data.rooms.has_capability(&doc_id, connection.id, "runs")).then(|| connection.outbound.clone());
+    let run_id = runs::spawn_run(&app_data.runs, job, run, files, input, move |event| {
+        if let Some(outbound) = &direct {
+            send_message(outbound, &ServerMessage::from(event.clone()));
+        }
+        publish(event);
+    });
+    tracing::info!("Підключення {} розпочало запуск {run_id} ({kind:?}) документа {doc_id}", connection.id);
+    Ok(())
+}
+
+/// Скасовує запуск документа (автор або Manager).
+///
+/// Запуск виконується на репліці ініціатора; якщо його немає на цій, запит передається іншим.
+async fn handle_run_cancel(
+    doc_id: Uuid,
+    connection: &Connection,
+    run_id: Uuid,
+    ctx: &crate::app::ServiceContext<'_>,
+    app_data: &AppData,
+) -> RequestResult<()> {
+    let is_manager = app_data.rooms
+        .get_role(&doc_id, connection.id)
+        .map(|r| r.can_manage())
+        .unwrap_or(false);
+    service::cancel_run(doc_id, run_id, connection.user_id, is_manager, Some(connection.id), ctx).await?;
+    Ok(())
+}
+
+/// Скасовує запуск підключення, що закривається.
+///
+/// Інакше інтерактивний запуск, stdin якого більше ніхто не закриє, займав би обробник черги
+/// та ліміти автора до тайм-ауту виконання.
+///
+/// Запуск належить цьому підключенню, тож скасування йде від імені його користувача.
+pub(crate) fn cancel_connection_run(doc_id: Uuid, conn_id: Uuid, user_id: Uuid, app_data: &AppData) {
+    let Some(run_id) = app_data.runs.by_connection(conn_id) else {
+        return;
+    };
+    match app_data.jobs.cancel(run_id, doc_id, user_id, false) {
+        Ok(()) => tracing::info!("Запуск {run_id} скасовано: підключення {conn_id} закрито"),
+        Err(err) => tracing::debug!("Запуск {run_id} підключення {conn_id} не скасовано: {err}"),
+    }
+}
+
+/// Повертає функцію, що публікує події запуску в кімнату по черзі, в окремому завданні.
+fn publish_run_events(doc_id: Uuid, app_data: &AppData) -> impl Fn(RunEvent) + 'static {
+    let (events_tx, mut events) = mpsc::unbounded_channel::<RunEvent>();
+    let app_data = app_data.clone();
+
+    actix_rt::spawn(async move {
+        let ctx = crate::app::ServiceContext::from(&app_data);
+        while let Some(event) = events.recv().await {
+            if let Err(err) = service::publish_run_event(doc_id, event, &ctx).await {
+                tracing::warn!("Не вдалося опублікувати подію запуску документа {doc_id} для інших реплік: {err}");
+            }
+        }
+    });
+
+    move |event| {
+        let _ = events_tx.send(event);
+    }
+}
+
+/// Надсилає подію запуску локальним клієнтам з можливістю `runs`.
+fn relay_run(rooms: &super::models::Rooms, doc_id: Uuid, message: ServerMessage) {
+    if let Ok(text) = serde_json::to_string(&message) {
+        rooms.send_text_to_capable(&doc_id, Uuid::nil(), "runs", text);
+    }
+}
+
+/// Надсилає клієнту поточне правило запуску коду.
+async fn send_run_policy(doc_id: Uuid, outbound: &Outbound, ctx: &crate::app::ServiceContext<'_>) {
+    match service::get_run_policy(doc_id, ctx).await {
+        Ok(policy) => send_message(outbound, &ServerMessage::RunPolicy { editors_only: policy.editors_only }),
+        Err(err) => tracing::error!("Не вдалося завантажити правило запуску: {err}"),
+    }
+}
+
+// ─────────────────────────── Protocol negotiation ────────────────────────────
+
+/// Узгоджує версію протоколу: найвища спільна версія або `None`, якщо клієнт застарий.
+pub(crate) fn negotiate_version(client_version: u32) -> Option<u32> {
+    (client_version >= MIN_PROTOCOL_VERSION).then(|| client_version.min(PROTOCOL_VERSION))
+}
+
+/// Залишає лише ті можливості клієнта, які підтримує сервер.
+pub(crate) fn negotiate_capabilities(requested: &[String]) -> Vec<String> {
+    SERVER_CAPABILITIES
+        .iter()
+        .filter(|cap| requested.iter().any(|r| r == *cap))
+        .map(|cap| cap.to_string())
+        .collect()
+}
+
+/// Визначає код помилки протоколу за HTTP-статусом помилки сервісу.
+pub(crate) fn error_code(err: &RequestError) -> ErrorCode {
+    let status = err.status_code();
+    if status.as_u16() == 401 || status.as_u16() == 403 {
+        ErrorCode::Forbidden
+    } else if status.is_client_error() {
+        ErrorCode::InvalidRequest
+    } else {
+        ErrorCode::Internal
     }
 }
 
-// ─────────────────────────── Broadcast participants ──────────────────────────
+// ─────────────────────────── Liveness ────────────────────────────────────────
 
-/// Надсилає оновлений список учасників усім у кімнаті.
-async fn broadcast_participants(app_data: &AppData, room_id: Uuid) {
-    let participants = app_data.rooms.get_participants(&room_id);
-    let msg = ServerMessage::ParticipantsUpdate { participants };
-    if let Ok(text) = serde_json::to_string(&msg) {
-        app_data.rooms.broadcast_text(&room_id, text).await;
+/// Стан з'єднання на черговому такті heartbeat.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Liveness {
+    Alive,
+    /// Клієнт не відповів на ping за `pong_timeout` — ймовірно, напіввідкрите TCP-з'єднання.
+    PongOverdue,
+    /// Клієнт відповідає на ping, але не надсилав повідомлень довше за `idle_timeout`.
+    Idle,
+}
+
+/// Визначає, чи залишати з'єднання відкритим.
+///
+/// `ping_sent_at` — час останнього ping, на який ще не надійшов pong;
+/// `last_activity` — час останнього текстового чи бінарного кадру клієнта.
+pub(crate) fn check_liveness(
+    now: Instant,
+    ping_sent_at: Option<Instant>,
+    last_activity: Instant,
+    settings: &WsSettings,
+) -> Liveness {
+    if ping_sent_at.is_some_and(|sent| now.duration_since(sent) >= settings.pong_timeout()) {
+        Liveness::PongOverdue
+    } else if now.duration_since(last_activity) >= settings.idle_timeout() {
+        Liveness::Idle
+    } else {
+        Liveness::Alive
     }
 }
 
@@ -337,98 +918,128 @@ fn add_connection(app_data: &AppData, id: Uuid, connection: Connection) {
 
     if is_new_room {
         service::run_merge(id, app_data);
-        start_redis_pubsub(id, app_data.clone());
+        app_data.fanout.subscribe(id);
     }
 }
 
-// ─────────────────────────── Redis PubSub ────────────────────────────────────
-
-fn start_redis_pubsub(doc_id: Uuid, app_data: AppData) {
-    let redis_url = app_data.redis_url.clone();
-    let rooms = app_data.rooms.clone();
-    let cancel_token = app_data.token();
+// ─────────────────────────── Room events ─────────────────────────────────────
 
-    actix_rt::spawn(async move {
-        tracing::info!("Запущено слухач Redis PubSub для кімнати {doc_id}");
-
-        let client = match Client::open(redis_url.as_str()) {
-            Ok(c) => c,
-            Err(err) => {
-                tracing::error!("Помилка створення клієнта Redis PubSub: {err}");
-                return;
+/// Доставляє подію кімнати локальним клієнтам цієї репліки, окрім підключення-ініціатора `origin`.
+pub(super) async fn dispatch_room_message(
+    ctx: &crate::app::ServiceContext<'_>,
+    doc_id: Uuid,
+    origin: Option<Uuid>,
+    message: PubSubMessage,
+) {
+    let rooms = ctx.rooms;
+    let exclude = origin.unwrap_or(Uuid::nil());
+    match message {
+        PubSubMessage::SyncChange { change } => {
+            rooms.send_change(&doc_id, exclude, Bytes::from(change));
+        }
+        PubSubMessage::FileSystemEvent { event } => {
+            let fs_msg = ServerMessage::FsEvent { event };
+            if let Ok(text) = serde_json::to_string(&fs_msg) {
+                rooms.send_text(&doc_id, exclude, text);
             }
-        };
-
-        let mut conn = match client.get_async_pubsub().await {
-            Ok(c) => c,
-            Err(err) => {
-                tracing::error!("Не вдалося отримати PubSub з'єднання Redis: {err}");
-                return;
+        }
+        PubSubMessage::Cursor { cursor } => {
+            if let Some(conn_id) = origin {
+                relay_cursor(rooms, doc_id, conn_id, cursor);
+            }
+        }
+        PubSubMessage::ParticipantsUpdate { participants } => {
+            let message = ServerMessage::ParticipantsUpdate { participants };
+            if let Ok(text) = serde_json::to_string(&message) {
+                rooms.broadcast_presence(&doc_id, text);
+            }
+        }
+        PubSubMessage::AccessChanged { user_id, role } => {
+            apply_access_change(ctx, doc_id, user_id, role).await;
+        }
+        PubSubMessage::Chat { event } => relay_chat(rooms, doc_id, event),
+        PubSubMessage::Viewport { viewport } => {
+            if let Some(conn_id) = origin {
+                relay_viewport(rooms, doc_id, conn_id, viewport);
+            }
+        }
+        PubSubMessage::AccessRequest { event } => relay_access_request(rooms, doc_id, event),
+        PubSubMessage::Run { event } => relay_run(rooms, doc_id, event.into()),
+        PubSubMessage::RunPolicy { editors_only } => relay_run(rooms, doc_id, ServerMessage::RunPolicy { editors_only }),
+        PubSubMessage::RunCancel { run_id, user_id, is_manager } => {
+            if ctx.jobs.owner(run_id).is_some() {
+                if let Err(err) = ctx.jobs.cancel(run_id, doc_id, user_id, is_manager) {
+                    tracing::info!("Відхилено скасування запуску {run_id} з іншої репліки: {err}");
+                }
             }
-        };
-
-        let channel_name = format!("document:room:{}", doc_id);
-        if let Err(err) = conn.subscribe(&channel_name).await {
-            tracing::error!("Не вдалося підписатися на канал Redis {channel_name}: {err}");
-            return;
         }
+    }
+}
 
-        let mut pubsub_stream = conn.into_on_message();
+// ─────────────────────────── Access changes ──────────────────────────────────
 
-        while !cancel_token.is_cancelled() {
-            if rooms.is_empty(&doc_id) {
-                tracing::info!("Кімната {doc_id} порожня — зупиняємо Redis PubSub");
-                break;
+/// Застосовує зміну доступу користувача до його підключень на цій репліці.
+///
+/// Нова роль оновлюється в `Rooms` та присутності й надсилається клієнту;
+/// якщо доступ відкликано — сесії закриваються з причиною.
+async fn apply_access_change(
+    ctx: &crate::app::ServiceContext<'_>,
+    doc_id: Uuid,
+    user_id: Uuid,
+    role: Option<SessionRole>,
+) {
+    let affected = match role {
+        Some(role) => {
+            let conn_ids = ctx.rooms.set_user_role(&doc_id, user_id, &role);
+            for conn_id in &conn_ids {
+                let updated = presence::update(doc_id, *conn_id, ctx.sessions, |p| p.role = role.clone()).await;
+                if let Err(err) = updated {
+                    tracing::warn!("Не вдалося оновити роль у присутності {conn_id}: {err}");
+                }
             }
 
-            tokio::select! {
-                _ = cancel_token.cancelled() => {
-                    tracing::info!("Токен скасування — зупиняємо Redis PubSub для {doc_id}");
-                    break;
+            let message = ServerMessage::RoleChanged { role };
+            if let Some(room) = ctx.rooms.value.get(&doc_id) {
+                for conn in room.iter().filter(|c| conn_ids.contains(&c.id)) {
+                    send_message(&conn.outbound, &message);
                 }
-                Some(msg) = pubsub_stream.next() => {
-                    let payload: Vec<u8> = match msg.get_payload() {
-                        Ok(p) => p,
-                        Err(err) => {
-                            tracing::error!("Помилка читання payload з Redis PubSub: {err}");
-                            continue;
-                        }
-                    };
-
-                    let pubsub_msg: PubSubMessage = match serde_json::from_slice(&payload) {
-                        Ok(m) => m,
-                        Err(err) => {
-                            tracing::error!("Помилка десеріалізації PubSubMessage: {err}");
-                            continue;
-                        }
-                    };
-
-                    match pubsub_msg {
-                        PubSubMessage::SyncChange { sender_conn_id, change } => {
-                            rooms.send_change(
-                                &doc_id,
-                                sender_conn_id,
-                                actix_web::web::Bytes::from(change),
-                            ).await;
-                        }
-                        PubSubMessage::FileSystemEvent { event, .. } => {
-                            let fs_msg = FileSystemMessage { event };
-                            if let Ok(text) = serde_json::to_string(&fs_msg) {
-                                rooms.send_text(&doc_id, Uuid::nil(), text).await;
-                            }
-                        }
-                    }
+            }
+            conn_ids.len()
+        }
+        None => {
+            let connections = ctx.rooms.take_user_connections(&doc_id, user_id);
+            for conn in &connections {
+                if let Err(err) = presence::leave(doc_id, conn.id, ctx.sessions).await {
+                    tracing::warn!("Не вдалося прибрати присутність {}: {err}", conn.id);
                 }
+                tracing::info!("Доступ {} до документа {doc_id} відкликано — закриваємо сесію", conn.id);
+                conn.closed.cancel();
             }
+            connections.len()
         }
-    });
+    };
+
+    if affected > 0 {
+        broadcast_participants(ctx, doc_id).await;
+    }
 }
 
 // ─────────────────────────── Helpers ─────────────────────────────────────────
 
-async fn close_session(session: Session, reason: Option<CloseReason>) {
-    if let Err(err) = session.close(reason).await {
-        tracing::warn!("Не вдалося чисто закрити сесію WebSocket: {err}");
+/// Ставить повідомлення протоколу в чергу вихідних кадрів клієнта.
+fn send_message(outbound: &Outbound, message: &ServerMessage) {
+    match serde_json::to_string(message) {
+        Ok(text) => {
+            outbound.push(OutboundFrame::Text(text));
+        }
+        Err(err) => tracing::error!("Помилка серіалізації повідомлення протоколу: {err}"),
+    }
+}
+
+fn policy_close(description: &str) -> CloseReason {
+    CloseReason {
+        code: CloseCode::Policy,
+        description: Some(description.to_string()),
     }
 }
 
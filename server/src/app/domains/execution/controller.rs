@@ -1,21 +1,26 @@
 use actix_web::{
-    HttpResponse, Responder,
+    HttpRequest, HttpResponse, Responder,
     web::{Path, Data, Json},
 };
 use uuid::Uuid;
 
+use super::queue::JobOwner;
 use super::service;
-use super::models::{ExecutionResponse, ExecuteProjectRequest};
+use super::models::{ExecutionResponse, ExecuteProjectRequest, RunQueueResponse};
 use crate::core::app_data::AppData;
-use crate::app::{RequestResult, ServiceContext};
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::app::domains::document::controller::extract_claims;
+use crate::app::domains::document::service as document_service;
 
 /// Виконання багатофайлового проекту Rust у ізольованому середовищі (пісочниці).
 /// 
-/// Отримує унікальний Uuid документа з URL та структуру файлів проекту у тілі запиту.
-/// Створює відносну структуру папок та файлів, виконує проект і повертає результат (stdout, stderr).
+/// Завантажує файли документа з сервера (поточний стан або версію з тіла запиту),
+/// виконує проект з необов'язковим stdin і повертає результат (статус, stdout, stderr).
+/// Поки запит триває, запуск можна скасувати за його `run_id`, а його позицію в черзі
+/// дізнатися через `GET /api/documents/{id}/runs/{run_id}`.
 #[tracing::instrument(
     name = "execute_code",
-    skip(app_data, body),
+    skip(req, app_data, body),
     fields(request_id, doc_id = %id)
 )]
 #[utoipa::path(
@@ -23,27 +28,40 @@ use crate::app::{RequestResult, ServiceContext};
     path = "/api/documents/{id}/execute",
     params(("id" = Uuid, Path, description = "Uuid документа")),
     request_body(
-        description = "Карта файлів проекту для виконання",
+        description = "Версія проекту та stdin для виконання",
         content_type = "application/json",
         content = ExecuteProjectRequest
     ),
     responses(
-        (status = 200, description = "Результат виконання коду", body = ExecutionResponse)
+        (status = 200, description = "Результат виконання коду", body = ExecutionResponse),
+        (status = 403, description = "Користувач не є учасником документа або запуски дозволені лише редакторам"),
+        (status = 404, description = "Версію проекту не знайдено"),
+        (status = 409, description = "Перевищено ліміт одночасних запусків або запуск з таким run_id уже існує"),
+        (status = 503, description = "Черга запусків заповнена")
     )
 )]
 pub async fn execute_code(
+    req: HttpRequest,
     id: Path<Uuid>,
     body: Json<ExecuteProjectRequest>,
     app_data: Data<AppData>,
 ) -> RequestResult<impl Responder> {
-    let _id = id.into_inner();
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
     let ctx = ServiceContext::from(app_data.get_ref());
-    
+    let body = body.into_inner();
+    let doc_id = id.into_inner();
+    let files = service::load_project_files(doc_id, claims.sub, body.version, &ctx).await?;
+
     // Виконуємо код проекту
-    let result = service::execute_rust_code(&body.files, &ctx).await?;
+    let owner = JobOwner { user_id: claims.sub, doc_id };
+    let run_id = body.run_id.unwrap_or_else(Uuid::now_v7);
+    let result = service::execute_rust_code(&files, body.stdin, run_id, owner, &ctx).await?;
     
     let response = ExecutionResponse {
+        run_id,
         success: result.success,
+        status: result.status,
+        exit_code: result.exit_code,
         stdout: result.stdout,
         stderr: result.stderr,
     };
@@ -53,11 +71,12 @@ pub async fn execute_code(
 
 /// Запуск тестів проекту Rust у ізольованому середовищі.
 /// 
-/// Отримує унікальний Uuid документа з URL та структуру файлів проекту у тілі запиту.
-/// Створює відносну структуру папок та файлів, виконує тести і повертає результат (stdout, stderr).
+/// Завантажує файли документа з сервера (поточний стан або версію з тіла запиту),
+/// виконує тести і повертає результат (статус, stdout, stderr).
+/// Скасування та позиція в черзі — за `run_id`, як і для `execute_code`.
 #[tracing::instrument(
     name = "execute_tests",
-    skip(app_data, body),
+    skip(req, app_data, body),
     fields(request_id, doc_id = %id)
 )]
 #[utoipa::path(
@@ -65,27 +84,40 @@ pub async fn execute_code(
     path = "/api/documents/{id}/test",
     params(("id" = Uuid, Path, description = "Uuid документа")),
     request_body(
-        description = "Карта файлів проекту для запуску тестів",
+        description = "Версія проекту та stdin для запуску тестів",
         content_type = "application/json",
         content = ExecuteProjectRequest
     ),
     responses(
-        (status = 200, description = "Результат виконання тестів", body = ExecutionResponse)
+        (status = 200, description = "Результат виконання тестів", body = ExecutionResponse),
+        (status = 403, description = "Користувач не є учасником документа або запуски дозволені лише редакторам"),
+        (status = 404, description = "Версію проекту не знайдено"),
+        (status = 409, description = "Перевищено ліміт одночасних запусків або запуск з таким run_id уже існує"),
+        (status = 503, description = "Черга запусків заповнена")
     )
 )]
 pub async fn execute_tests(
+    req: HttpRequest,
     id: Path<Uuid>,
     body: Json<ExecuteProjectRequest>,
     app_data: Data<AppData>,
 ) -> RequestResult<impl Responder> {
-    let _id = id.into_inner();
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
     let ctx = ServiceContext::from(app_data.get_ref());
-    
+    let body = body.into_inner();
+    let doc_id = id.into_inner();
+    let files = service::load_project_files(doc_id, claims.sub, body.version, &ctx).await?;
+
     // Виконуємо тести проекту
-    let result = service::execute_rust_tests(&body.files, &ctx).await?;
+    let owner = JobOwner { user_id: claims.sub, doc_id };
+    let run_id = body.run_id.unwrap_or_else(Uuid::now_v7);
+    let result = service::execute_rust_tests(&files, body.stdin, run_id, owner, &ctx).await?;
     
     let response = ExecutionResponse {
+        run_id,
         success: result.success,
+        status: result.status,
+        exit_code: result.exit_code,
         stdout: result.stdout,
         stderr: result.stderr,
     };
@@ -93,6 +125,89 @@ pub async fn execute_tests(
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Скасування запуску документа.
+///
+/// Скасувати запуск (REST чи WebSocket) може його автор або менеджер документа:
+/// запуск у черзі знімається з неї, а процеси компілятора чи програми зупиняються.
+/// Запуск отримує статус `cancelled`.
+///
+/// Якщо запуск виконується на іншій репліці, запит передається їй, і відповідь — `202`:
+/// скасування виконується за можливістю, а його результат приходить подією `run_finished`.
+#[tracing::instrument(
+    name = "cancel_run",
+    skip(req, app_data),
+    fields(request_id)
+)]
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}/runs/{run_id}/cancel",
+    params(
+        ("id" = Uuid, Path, description = "Uuid документа"),
+        ("run_id" = Uuid, Path, description = "Ідентифікатор запуску")
+    ),
+    responses(
+        (status = 200, description = "Запуск скасовано"),
+        (status = 202, description = "Запуск не виконується на цій репліці; запит на скасування передано іншим"),
+        (status = 403, description = "Скасувати запуск може лише його автор або менеджер"),
+        (status = 404, description = "Запуск не знайдено або вже завершено")
+    )
+)]
+pub async fn cancel_run(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, run_id) = path.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    let role = document_service::get_user_role(doc_id, claims.sub, &ctx).await?
+        .ok_or_else(|| RequestError::forbidden("Немає доступу до документа"))?;
+
+    if document_service::cancel_run(doc_id, run_id, claims.sub, role.can_manage(), None, &ctx).await? {
+        Ok(HttpResponse::Ok().body("Запуск скасовано"))
+    } else {
+        Ok(HttpResponse::Accepted().body("Запит на скасування передано репліці, що виконує запуск"))
+    }
+}
+
+/// Позиція запуску в черзі.
+///
+/// REST-запуск не отримує подій черги, тож клієнт, що передав власний `run_id`, може
+/// опитувати цей маршрут, поки чекає на результат. Доступно будь-якому учаснику документа.
+/// Черга в кожної репліки своя: запуск, що виконується на іншій репліці, тут не знайдеться.
+#[tracing::instrument(
+    name = "get_run_queue_position",
+    skip(req, app_data),
+    fields(request_id)
+)]
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}/runs/{run_id}",
+    params(
+        ("id" = Uuid, Path, description = "Uuid документа"),
+        ("run_id" = Uuid, Path, description = "Ідентифікатор запуску")
+    ),
+    responses(
+        (status = 200, description = "Позиція запуску в черзі", body = RunQueueResponse),
+        (status = 403, description = "Користувач не є учасником документа"),
+        (status = 404, description = "Запуск не знайдено на цій репліці або вже завершено")
+    )
+)]
+pub async fn get_run_queue_position(
+    req: HttpRequest,
+    path: Path<(Uuid, Uuid)>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let (doc_id, run_id) = path.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    document_service::get_user_role(doc_id, claims.sub, &ctx).await?
+        .ok_or_else(|| RequestError::forbidden("Немає доступу до документа"))?;
+
+    let position = app_data.jobs.queue_position(run_id, doc_id)?;
+    Ok(HttpResponse::Ok().json(RunQueueResponse { run_id, position }))
+}
+
 /// Форматування коду за допомогою rustfmt.
 /// 
 /// Отримує унікальний Uuid документа з URL та вміст файлу у тілі запиту.
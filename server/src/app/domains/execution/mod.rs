@@ -1,11 +1,18 @@
 pub mod controller;
 pub mod service;
 pub mod models;
+pub mod queue;
+pub mod runs;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
+pub use controller::cancel_run;
 pub use controller::execute_code;
 pub use controller::execute_tests;
 pub use controller::format_code;
+pub use controller::get_run_queue_position;
 pub use models::ExecutionResponse;
+pub use queue::ExecutionQueue;
+pub use runs::RunRegistry;
@@ -1,5 +1,7 @@
 pub mod request;
 pub mod response;
+pub mod run;
 
 pub use request::ExecuteProjectRequest;
-pub use response::ExecutionResponse;
+pub use response::{ExecutionResponse, RunQueueResponse};
+pub use run::{OutputChunk, OutputStream, RunEvent, RunKind, RunPhase, RunStatus};
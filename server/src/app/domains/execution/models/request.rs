@@ -1,9 +1,19 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use uuid::Uuid;
 
-/// Модель запиту на виконання багатофайлового проекту.
-#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+/// Модель запиту на виконання проекту документа.
+///
+/// Файли завантажуються з сервера, тож усі учасники запускають один і той самий стан проекту.
+#[derive(Serialize, Deserialize, Default, utoipa::ToSchema)]
 pub struct ExecuteProjectRequest {
-    /// Карта відносних шляхів файлів до їх текстового вмісту (наприклад, {"src/main.rs": "..."}).
-    pub files: HashMap<String, String>,
+    /// Ідентифікатор запуску, обраний клієнтом, щоб скасувати запуск ще до відповіді
+    /// (за замовчуванням — новий, сервер повертає його у відповіді).
+    #[serde(default)]
+    pub run_id: Option<Uuid>,
+    /// Id ревізії з історії файлів: запуск проекту в стані одразу після неї (за замовчуванням — поточний стан).
+    #[serde(default)]
+    pub version: Option<Uuid>,
+    /// Вміст stdin програми; після нього програма отримує EOF. Без поля stdin порожній.
+    #[serde(default)]
+    pub stdin: Option<String>,
 }
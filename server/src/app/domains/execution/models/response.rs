@@ -1,9 +1,26 @@
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::RunStatus;
 
 /// Модель відповіді з результатом виконання коду в пісочниці.
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 pub struct ExecutionResponse {
+    /// Ідентифікатор запуску (той самий, за яким його можна скасувати).
+    pub run_id: Uuid,
     pub success: bool,
+    /// Підсумок: `succeeded`, `failed`, `timed_out` або `cancelled`.
+    pub status: RunStatus,
+    /// Код виходу останнього процесу (`null`, якщо його зупинено).
+    pub exit_code: Option<i32>,
     pub stdout: String,
     pub stderr: String,
 }
+
+/// Модель відповіді зі станом запуску в черзі.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RunQueueResponse {
+    pub run_id: Uuid,
+    /// Позиція серед запусків, що чекають (з 1); `null`, якщо запуск уже виконується.
+    pub position: Option<usize>,
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Що запускати: програму чи тести проекту.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunKind {
+    Run,
+    Test,
+}
+
+/// Фаза запуску, до якої належить вивід.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPhase {
+    /// Збирання `rustc` або `cargo build`.
+    Compile,
+    /// Виконання програми або тестів.
+    Run,
+}
+
+/// Потік виводу процесу.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Фрагмент виводу процесу в порядку появи.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputChunk {
+    pub phase: RunPhase,
+    pub stream: OutputStream,
+    pub data: String,
+    /// Час отримання (першого) фрагмента сервером.
+    pub ts: DateTime<Utc>,
+}
+
+/// Підсумок запуску.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// Збирання та виконання завершились з кодом 0.
+    Succeeded,
+    /// Помилка збирання, ненульовий код виходу або збій запуску.
+    Failed,
+    /// Перевищено ліміт часу збирання або виконання.
+    TimedOut,
+    /// Запуск скасовано в черзі або під час виконання.
+    Cancelled,
+}
+
+/// Подія запуску, що надсилається клієнту по мірі виконання.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    Started {
+        run_id: Uuid,
+        kind: RunKind,
+        /// Чи приймає програма stdin від клієнта під час виконання.
+        interactive: bool,
+        /// Підключення, що розпочало запуск; лише воно може надсилати stdin.
+        conn_id: Uuid,
+        user_id: Uuid,
+        username: String,
+    },
+    /// Запуск чекає на вільний обробник; надсилається при кожній зміні позиції.
+    Queued {
+        run_id: Uuid,
+        /// Позиція в черзі, починаючи з 1.
+        position: usize,
+    },
+    Output {
+        run_id: Uuid,
+        #[serde(flatten)]
+        chunk: OutputChunk,
+    },
+    Finished {
+        run_id: Uuid,
+        status: RunStatus,
+        /// Код виходу останнього процесу (`None`, якщо його зупинено).
+        exit_code: Option<i32>,
+        duration_ms: u64,
+    },
+}
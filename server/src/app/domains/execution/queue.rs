@@ -0,0 +1,215 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::app::{RequestError, RequestResult};
+use crate::core::config_builder::ExecutionSettings;
+
+// ─────────────────────────── Queue ───────────────────────────────────────────
+
+/// Черга запусків коду на цій репліці: не більше `workers` одночасно, решта чекає в порядку надходження.
+///
+/// Черга також обмежує кількість запусків одного користувача та одного документа
+/// (разом тих, що чекають, і тих, що виконуються).
+#[derive(Clone)]
+pub struct ExecutionQueue {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    settings: ExecutionSettings,
+    state: Mutex<QueueState>,
+    /// Сигнал для завдань у черзі: звільнився обробник або змінилися позиції.
+    changed: watch::Sender<()>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Завдання, що чекають на обробник, у порядку надходження.
+    waiting: VecDeque<Uuid>,
+    /// Усі прийняті завдання: ті, що чекають, і ті, що виконуються.
+    jobs: HashMap<Uuid, JobEntry>,
+    running: usize,
+}
+
+struct JobEntry {
+    owner: JobOwner,
+    cancel: CancellationToken,
+}
+
+/// Хто поставив завдання в чергу.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JobOwner {
+    pub user_id: Uuid,
+    pub doc_id: Uuid,
+}
+
+impl Default for ExecutionQueue {
+    fn default() -> Self {
+        Self::new(ExecutionSettings::default())
+    }
+}
+
+impl ExecutionQueue {
+    pub fn new(settings: ExecutionSettings) -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                settings,
+                state: Mutex::default(),
+                changed: watch::Sender::new(()),
+            }),
+        }
+    }
+
+    /// Приймає завдання `job_id`, якщо черга не заповнена і власник не перевищив ліміти.
+    pub fn submit(&self, job_id: Uuid, owner: JobOwner) -> RequestResult<Job> {
+        let settings = &self.inner.settings;
+        let mut state = self.state();
+
+        if state.jobs.contains_key(&job_id) {
+            return Err(RequestError::conflict("Запуск з таким ідентифікатором уже існує"));
+        }
+        if state.waiting.len() >= settings.queue_capacity {
+            return Err(RequestError::service_unavailable("Черга запусків заповнена, спробуйте пізніше"));
+        }
+        let by_user = state.jobs.values().filter(|job| job.owner.user_id == owner.user_id).count();
+        if by_user >= settings.max_jobs_per_user {
+            return Err(RequestError::conflict(format!(
+                "Одночасно можна мати не більше {} запусків",
+                settings.max_jobs_per_user
+            )));
+        }
+        let by_doc = state.jobs.values().filter(|job| job.owner.doc_id == owner.doc_id).count();
+        if by_doc >= settings.max_jobs_per_document {
+            return Err(RequestError::conflict(format!(
+                "У документі одночасно може бути не більше {} запусків",
+                settings.max_jobs_per_document
+            )));
+        }
+
+        let cancel = CancellationToken::new();
+        state.jobs.insert(job_id, JobEntry { owner, cancel: cancel.clone() });
+        state.waiting.push_back(job_id);
+        Ok(Job {
+            queue: self.clone(),
+            id: job_id,
+            cancel,
+            started: false,
+        })
+    }
+
+    /// Власник завдання, якщо воно ще в черзі або виконується.
+    pub fn owner(&self, job_id: Uuid) -> Option<JobOwner> {
+        self.state().jobs.get(&job_id).map(|job| job.owner)
+    }
+
+    /// Скасовує завдання документа `doc_id`: скасувати може власник або менеджер документа.
+    pub fn cancel(&self, job_id: Uuid, doc_id: Uuid, user_id: Uuid, is_manager: bool) -> RequestResult<()> {
+        let state = self.state();
+        let Some(job) = state.jobs.get(&job_id).filter(|job| job.owner.doc_id == doc_id) else {
+            return Err(RequestError::not_found("Запуск не знайдено або вже завершено"));
+        };
+        if job.owner.user_id != user_id && !is_manager {
+            return Err(RequestError::forbidden("Скасувати запуск може лише його автор або менеджер"));
+        }
+        job.cancel.cancel();
+        Ok(())
+    }
+
+    /// Позиція завдання документа `doc_id` серед тих, що чекають (з 1); `None`, якщо воно вже виконується.
+    pub fn queue_position(&self, job_id: Uuid, doc_id: Uuid) -> RequestResult<Option<usize>> {
+        if self.state().jobs.get(&job_id).is_none_or(|job| job.owner.doc_id != doc_id) {
+            return Err(RequestError::not_found("Запуск не знайдено або вже завершено"));
+        }
+        Ok(self.position(job_id))
+    }
+
+    /// Позиція завдання серед тих, що чекають (з 1); `None`, якщо воно вже виконується.
+    fn position(&self, job_id: Uuid) -> Option<usize> {
+        self.state().waiting.iter().position(|id| *id == job_id).map(|index| index + 1)
+    }
+
+    /// Забирає обробник для завдання, якщо воно перше в черзі і є вільний обробник.
+    fn try_start(&self, job_id: Uuid) -> bool {
+        let mut state = self.state();
+        if state.running >= self.inner.settings.workers.max(1) || state.waiting.front() != Some(&job_id) {
+            return false;
+        }
+        state.waiting.pop_front();
+        state.running += 1;
+        drop(state);
+        self.notify();
+        true
+    }
+
+    fn notify(&self) {
+        self.inner.changed.send_modify(|_| {});
+    }
+
+    fn state(&self) -> MutexGuard<'_, QueueState> {
+        self.inner.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// ─────────────────────────── Job ─────────────────────────────────────────────
+
+/// Завдання, прийняте чергою. Звільняє своє місце в черзі (або обробник), коли його відкидають.
+pub struct Job {
+    queue: ExecutionQueue,
+    id: Uuid,
+    cancel: CancellationToken,
+    started: bool,
+}
+
+impl Job {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Чекає на свою чергу, повідомляючи `on_position` про кожну зміну позиції, і виконує `work`.
+    ///
+    /// Повертає `None`, якщо завдання скасували: у черзі або під час виконання —
+    /// тоді `work` відкидається разом з процесами, які він запустив.
+    pub async fn run<T>(mut self, on_position: impl Fn(usize), work: impl Future<Output = T>) -> Option<T> {
+        let mut changed = self.queue.inner.changed.subscribe();
+        let mut reported = None;
+
+        while !self.queue.try_start(self.id) {
+            let position = self.queue.position(self.id);
+            if let Some(position) = position.filter(|_| position != reported) {
+                on_position(position);
+                reported = Some(position);
+            }
+            // Скасування важливіше за зміну черги: інакше скасоване завдання могло б стартувати
+            tokio::select! {
+                biased;
+                _ = self.cancel.cancelled() => return None,
+                _ = changed.changed() => {}
+            }
+        }
+        self.started = true;
+
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => None,
+            output = work => Some(output),
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let mut state = self.queue.state();
+        state.jobs.remove(&self.id);
+        if self.started {
+            state.running -= 1;
+        } else {
+            state.waiting.retain(|id| *id != self.id);
+        }
+        drop(state);
+        self.queue.notify();
+    }
+}
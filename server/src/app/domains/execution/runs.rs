@@ -0,0 +1,192 @@
+use chrono::Utc;
+use dashmap::DashMap;
+use dashmap::mapref::one::RefMut;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::{self, Instant};
+use uuid::Uuid;
+
+use super::models::{OutputChunk, OutputStream, RunEvent, RunKind, RunPhase, RunStatus};
+use super::queue::Job;
+use super::service::{self, RunInput, StdinSender};
+use crate::app::{RequestError, RequestResult};
+use crate::constants::execution::RUN_OUTPUT_FLUSH_MS;
+
+// ─────────────────────────── Registry ────────────────────────────────────────
+
+/// Запуски, що зараз виконуються на цій репліці.
+#[derive(Clone, Default)]
+pub struct RunRegistry {
+    runs: Arc<DashMap<Uuid, ActiveRun>>,
+}
+
+/// Хто і що запустив.
+#[derive(Clone, Debug)]
+pub struct ActiveRun {
+    pub doc_id: Uuid,
+    /// Підключення, яке отримує вивід запуску.
+    pub conn_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub kind: RunKind,
+    /// stdin інтерактивного запуску (`None` — запуск неінтерактивний або EOF уже надіслано).
+    pub stdin: Option<StdinSender>,
+}
+
+impl RunRegistry {
+    /// Id запуску, що виконується для підключення.
+    pub fn by_connection(&self, conn_id: Uuid) -> Option<Uuid> {
+        self.runs.iter().find(|run| run.conn_id == conn_id).map(|run| *run.key())
+    }
+
+    /// Дані запуску, якщо він ще виконується.
+    pub fn get(&self, run_id: Uuid) -> Option<ActiveRun> {
+        self.runs.get(&run_id).map(|run| run.clone())
+    }
+
+    /// Передає дані в stdin інтерактивного запуску, розпочатого підключенням `conn_id`.
+    pub fn write_stdin(&self, run_id: Uuid, conn_id: Uuid, data: String) -> RequestResult<()> {
+        let run = self.owned_run(run_id, conn_id)?;
+        let Some(stdin) = run.stdin.as_ref() else {
+            return Err(RequestError::bad_request("Запуск не приймає stdin"));
+        };
+        stdin.try_send(data).map_err(|err| match err {
+            TrySendError::Full(_) => RequestError::conflict("Програма не встигає читати stdin"),
+            TrySendError::Closed(_) => RequestError::bad_request("Програма більше не читає stdin"),
+        })
+    }
+
+    /// Закриває stdin інтерактивного запуску (EOF).
+    pub fn close_stdin(&self, run_id: Uuid, conn_id: Uuid) -> RequestResult<()> {
+        let mut run = self.owned_run(run_id, conn_id)?;
+        run.stdin = None;
+        Ok(())
+    }
+
+    fn owned_run(&self, run_id: Uuid, conn_id: Uuid) -> RequestResult<RefMut<'_, Uuid, ActiveRun>> {
+        match self.runs.get_mut(&run_id) {
+            Some(run) if run.conn_id == conn_id => Ok(run),
+            Some(_) => Err(RequestError::forbidden("Запуск розпочато іншим підключенням")),
+            None => Err(RequestError::not_found("Запуск не знайдено або вже завершено")),
+        }
+    }
+}
+
+// ─────────────────────────── Runner ──────────────────────────────────────────
+
+/// Виконує завдання черги `job` у фоновому завданні, передаючи події запуску в `emit`, і повертає id запуску.
+///
+/// Поки завдання чекає на обробник, надходять `RunEvent::Queued` з його позицією; далі вивід
+/// надходить фрагментами `RunEvent::Output`, об'єднаними за `RUN_OUTPUT_FLUSH_MS`,
+/// а останньою подією завжди є `RunEvent::Finished`.
+pub fn spawn_run(
+    registry: &RunRegistry,
+    job: Job,
+    run: ActiveRun,
+    files: HashMap<String, String>,
+    input: Option<RunInput>,
+    emit: impl Fn(RunEvent) + 'static,
+) -> Uuid {
+    let run_id = job.id();
+    let started = RunEvent::Started {
+        run_id,
+        kind: run.kind,
+        interactive: run.stdin.is_some(),
+        conn_id: run.conn_id,
+        user_id: run.user_id,
+        username: run.username.clone(),
+    };
+    let kind = run.kind;
+    registry.runs.insert(run_id, run);
+    let registry = registry.clone();
+
+    actix_rt::spawn(async move {
+        emit(started);
+        // Тривалість рахуємо від отримання обробника, без часу в черзі
+        let began = Cell::new(None);
+
+        let work = async {
+            began.set(Some(Instant::now()));
+            let (sink, chunks) = mpsc::unbounded_channel();
+            let (result, ()) = tokio::join!(
+                service::run_project(&files, kind, input, Some(sink)),
+                forward_output(run_id, chunks, &emit),
+            );
+            result
+        };
+        let outcome = job.run(|position| emit(RunEvent::Queued { run_id, position }), work).await;
+
+        let (status, exit_code) = match outcome {
+            Some(Ok(result)) => (result.status, result.exit_code),
+            Some(Err(err)) => {
+                // Помилка підготовки (некоректний шлях, немає точки входу) — показуємо її у виводі
+                let chunk = OutputChunk {
+                    phase: RunPhase::Compile,
+                    stream: OutputStream::Stderr,
+                    data: err.to_string(),
+                    ts: Utc::now(),
+                };
+                emit(RunEvent::Output { run_id, chunk });
+                (RunStatus::Failed, None)
+            }
+            None => (RunStatus::Cancelled, None),
+        };
+
+        registry.runs.remove(&run_id);
+        emit(RunEvent::Finished {
+            run_id,
+            status,
+            exit_code,
+            duration_ms: began.get().map_or(0, |began| began.elapsed().as_millis() as u64),
+        });
+    });
+
+    run_id
+}
+
+/// Пересилає вивід запуску в `emit`, об'єднуючи фрагменти, що надійшли за `RUN_OUTPUT_FLUSH_MS`.
+///
+/// Завершується, коли процеси запуску закрили всі потоки виводу.
+async fn forward_output(
+    run_id: Uuid,
+    mut chunks: mpsc::UnboundedReceiver<OutputChunk>,
+    emit: &impl Fn(RunEvent),
+) {
+    let mut flush = time::interval(Duration::from_millis(RUN_OUTPUT_FLUSH_MS));
+    flush.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    let mut pending = Vec::new();
+
+    loop {
+        tokio::select! {
+            chunk = chunks.recv() => match chunk {
+                Some(chunk) => pending.push(chunk),
+                None => break,
+            },
+            _ = flush.tick(), if !pending.is_empty() => {
+                for chunk in coalesce(std::mem::take(&mut pending)) {
+                    emit(RunEvent::Output { run_id, chunk });
+                }
+            }
+        }
+    }
+
+    for chunk in coalesce(pending) {
+        emit(RunEvent::Output { run_id, chunk });
+    }
+}
+
+/// Об'єднує сусідні фрагменти однієї фази та потоку; час береться з першого фрагмента.
+pub(crate) fn coalesce(chunks: Vec<OutputChunk>) -> Vec<OutputChunk> {
+    let mut merged: Vec<OutputChunk> = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        match merged.last_mut() {
+            Some(last) if last.phase == chunk.phase && last.stream == chunk.stream => last.data.push_str(&chunk.data),
+            _ => merged.push(chunk),
+        }
+    }
+    merged
+}
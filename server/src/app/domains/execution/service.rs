@@ -1,19 +1,49 @@
-use std::process::Stdio;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tokio::fs;
-use tokio::process::Command;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::process::{ChildStdin, Command};
+use tokio::sync::mpsc;
 use tokio::time::timeout;
 use tempfile::TempDir;
+use uuid::Uuid;
 
-use crate::app::{RequestError, RequestResult};
+use super::models::{OutputChunk, OutputStream, RunKind, RunPhase, RunStatus};
+use super::queue::JobOwner;
+use crate::app::domains::document::service as document_service;
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::constants::execution::{
+    COMPILE_TIMEOUT_SECONDS, RUN_INTERACTIVE_TIMEOUT_SECONDS, RUN_OUTPUT_MAX_BYTES, RUN_TIMEOUT_SECONDS,
+    TEST_TIMEOUT_SECONDS,
+};
 
 /// Результат виконання коду в ізольованому середовищі.
 pub struct ExecutionResult {
     pub success: bool,
+    pub status: RunStatus,
+    /// Код виходу останнього процесу (`None`, якщо його зупинено).
+    pub exit_code: Option<i32>,
     pub stdout: String,
     pub stderr: String,
 }
 
+impl ExecutionResult {
+    /// Результат запуску, скасованого в черзі або під час виконання.
+    pub fn cancelled() -> Self {
+        Self {
+            success: false,
+            status: RunStatus::Cancelled,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: "Запуск скасовано".to_string(),
+        }
+    }
+}
+
 /// Перевіряє, що відносний шлях файлу не виходить за межі робочої директорії
 /// (захист від Directory Traversal через `..` або абсолютних шляхів).
 pub(crate) fn is_path_safe(path: &str) -> bool {
@@ -27,7 +57,7 @@ pub(crate) fn should_skip_entry(path: &str) -> bool {
 }
 
 /// Знаходить точку входу для збірки чистим `rustc` (без Cargo).
-pub(crate) fn find_entrypoint(files: &std::collections::HashMap<String, String>) -> Option<&'static str> {
+pub(crate) fn find_entrypoint(files: &HashMap<String, String>) -> Option<&'static str> {
     if files.contains_key("src/main.rs") {
         Some("src/main.rs")
     } else if files.contains_key("main.rs") {
@@ -53,8 +83,12 @@ pub(crate) fn parse_package_name(cargo_toml_content: &str) -> String {
 #[cfg(unix)]
 unsafe extern "C" {
     fn getuid() -> u32;
+    fn killpg(pgrp: i32, sig: i32) -> i32;
 }
 
+#[cfg(unix)]
+const SIGKILL: i32 = 9;
+
 #[cfg(unix)]
 fn get_sandbox_command(exe_path: &std::path::Path, temp_dir_path: &std::path::Path) -> Command {
     // Перевіряємо, чи запущено процес від імені root і чи доступний prlimit
@@ -96,18 +130,89 @@ fn set_sandbox_permissions(temp_dir_path: &std::path::Path, exe_path: &std::path
     Ok(())
 }
 
+/// Завантажує файли проекту для запуску у стані `version` (`None` — поточний стан).
+///
+/// Запускати може будь-який учасник документа, зокрема Reader, якщо менеджер не обмежив
+/// запуски правилом `runs_editors_only`.
+pub async fn load_project_files(
+    doc_id: Uuid,
+    user_id: Uuid,
+    version: Option<Uuid>,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<HashMap<String, String>> {
+    let Some(role) = document_service::get_user_role(doc_id, user_id, ctx).await? else {
+        return Err(RequestError::forbidden("Немає доступу до документа"));
+    };
+    document_service::ensure_can_run(doc_id, &role, ctx).await?;
+    document_service::get_project_files_at(doc_id, version, ctx).await
+}
+
 /// Компілює та виконує багатофайловий проект Rust у ізольованому середовищі (пісочниці) з обмеженнями ресурсів.
+///
+/// `stdin`, якщо задано, передається програмі повністю, після чого її stdin закривається.
+/// Запуск проходить через чергу виконання з лімітами користувача `owner` та його документа;
+/// поки він у черзі чи виконується, його можна скасувати за `run_id`.
 pub async fn execute_rust_code(
-    files: &std::collections::HashMap<String, String>,
-    _ctx: &crate::app::ServiceContext<'_>,
+    files: &HashMap<String, String>,
+    stdin: Option<String>,
+    run_id: Uuid,
+    owner: JobOwner,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ExecutionResult> {
+    execute_queued(files, RunKind::Run, stdin, run_id, owner, ctx).await
+}
+
+/// Компілює та виконує тести Rust у проекті (з підтримкою як Cargo, так і rustc --test).
+pub async fn execute_rust_tests(
+    files: &HashMap<String, String>,
+    stdin: Option<String>,
+    run_id: Uuid,
+    owner: JobOwner,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ExecutionResult> {
+    execute_queued(files, RunKind::Test, stdin, run_id, owner, ctx).await
+}
+
+/// Ставить запуск у чергу та чекає на його результат.
+async fn execute_queued(
+    files: &HashMap<String, String>,
+    kind: RunKind,
+    stdin: Option<String>,
+    run_id: Uuid,
+    owner: JobOwner,
+    ctx: &ServiceContext<'_>,
+) -> RequestResult<ExecutionResult> {
+    let job = ctx.jobs.submit(run_id, owner)?;
+    let work = run_project(files, kind, stdin.map(RunInput::Fixed), None);
+    job.run(|_| {}, work).await.unwrap_or_else(|| Ok(ExecutionResult::cancelled()))
+}
+
+/// Збирає та запускає проект або його тести, передаючи вивід у `sink` по мірі появи.
+///
+/// Той самий вивід накопичується в `ExecutionResult` (до `RUN_OUTPUT_MAX_BYTES` на потік).
+/// `input` стає stdin програми (компілятор stdin не отримує); без нього stdin порожній.
+pub async fn run_project(
+    files: &HashMap<String, String>,
+    kind: RunKind,
+    input: Option<RunInput>,
+    sink: Option<OutputSink>,
 ) -> RequestResult<ExecutionResult> {
     // Створюємо тимчасову директорію для збирання та виконання
     let temp_dir = TempDir::new().map_err(|e| {
         tracing::error!("Не вдалося створити тимчасову директорію: {}", e);
         RequestError::internal_server_error("Не вдалося створити тимчасове середовище")
     })?;
+    write_project(temp_dir.path(), files).await?;
 
-    // Записуємо структуру папок та файлів проекту
+    let mut output = CollectedOutput::new(sink);
+    match kind {
+        RunKind::Run => run_program(files, temp_dir.path(), input, &mut output).await,
+        RunKind::Test => run_tests(files, temp_dir.path(), input, &mut output).await,
+    }
+}
+
+/// Записує структуру папок та файлів проекту в робочу директорію.
+async fn write_project(root: &Path, files: &HashMap<String, String>) -> RequestResult<()> {
     for (relative_path, content) in files {
         // Пропускаємо записи директорій (path/to/dir/) та gitkeep-маркери
         if should_skip_entry(relative_path) {
@@ -118,7 +223,7 @@ pub async fn execute_rust_code(
             return Err(RequestError::bad_request(format!("Неприпустимий шлях до файлу: {}", relative_path)));
         }
 
-        let file_path = temp_dir.path().join(relative_path);
+        let file_path = root.join(relative_path);
 
         // Створюємо батьківські папки для файлу, якщо необхідно
         if let Some(parent) = file_path.parent() {
@@ -134,58 +239,43 @@ pub async fn execute_rust_code(
             RequestError::internal_server_error("Не вдалося зберегти файли проекту")
         })?;
     }
+    Ok(())
+}
 
+/// Збирає програму (Cargo або чистий rustc) та виконує її в пісочниці.
+async fn run_program(
+    files: &HashMap<String, String>,
+    root: &Path,
+    input: Option<RunInput>,
+    output: &mut CollectedOutput,
+) -> RequestResult<ExecutionResult> {
     let exe_name = if cfg!(windows) { "program.exe" } else { "program" };
-    let exe_path = temp_dir.path().join(exe_name);
+    let exe_path = root.join(exe_name);
 
     // Визначаємо тип збирання: Cargo чи чистий rustc
     let is_cargo = files.contains_key("Cargo.toml");
-    let compile_timeout = Duration::from_secs(15);
 
     let mut compile_cmd = if is_cargo {
         let mut cmd = Command::new("cargo");
-        cmd.arg("build")
-           .arg("--release")
-           .current_dir(temp_dir.path())
-           .stdout(Stdio::piped())
-           .stderr(Stdio::piped());
+        cmd.arg("build").arg("--release").current_dir(root);
         cmd
     } else {
         let entrypoint = find_entrypoint(files)
             .ok_or_else(|| RequestError::bad_request("Проект повинен містити точку входу: main.rs або src/main.rs"))?;
 
         let mut cmd = Command::new("rustc");
-        cmd.arg(entrypoint)
-           .arg("-o")
-           .arg(&exe_path)
-           .current_dir(temp_dir.path())
-           .stdout(Stdio::piped())
-           .stderr(Stdio::piped());
+        cmd.arg(entrypoint).arg("-o").arg(&exe_path).current_dir(root);
         cmd
     };
 
-    let compile_future = compile_cmd.output();
-    let compile_output = match timeout(compile_timeout, compile_future).await {
-        Ok(Ok(output)) => output,
-        Ok(Err(e)) => {
-            tracing::error!("Не вдалося запустити компілятор: {}", e);
-            return Err(RequestError::internal_server_error("Не вдалося скомпілювати проект"));
-        }
-        Err(_) => {
-            return Ok(ExecutionResult {
-                success: false,
-                stdout: "".to_string(),
-                stderr: "Перевищено ліміт часу компіляції (ліміт 15 секунд).".to_string(),
-            });
-        }
+    let compile_timeout = Duration::from_secs(COMPILE_TIMEOUT_SECONDS);
+    let compiled = run_phase(&mut compile_cmd, RunPhase::Compile, compile_timeout, None, output, "Не вдалося скомпілювати проект").await?;
+    let Some(status) = compiled else {
+        output.note(RunPhase::Compile, &format!("Перевищено ліміт часу компіляції (ліміт {COMPILE_TIMEOUT_SECONDS} секунд)."));
+        return Ok(output.finish(RunStatus::TimedOut, None));
     };
-
-    if !compile_output.status.success() {
-        return Ok(ExecutionResult {
-            success: false,
-            stdout: String::from_utf8_lossy(&compile_output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&compile_output.stderr).to_string(),
-        });
+    if !status.success() {
+        return Ok(output.finish(RunStatus::Failed, status.code()));
     }
 
     // Для збірок Cargo, копіюємо отриманий бінарний файл до exe_path
@@ -195,7 +285,7 @@ pub async fn execute_rust_code(
             .map(|content| parse_package_name(content))
             .unwrap_or_else(|| "app".to_string());
 
-        let target_bin = temp_dir.path().join("target").join("release").join(&package_name);
+        let target_bin = root.join("target").join("release").join(&package_name);
         if target_bin.exists() {
             fs::copy(&target_bin, &exe_path).await.map_err(|e| {
                 tracing::error!("Не вдалося скопіювати збірку Cargo: {}", e);
@@ -206,229 +296,389 @@ pub async fn execute_rust_code(
         }
     }
 
+    let mut child_cmd = sandbox_command(root, &exe_path)?;
+
+    // Інтерактивна програма більшість часу чекає на введення, тому й ліміт часу більший
+    let run_timeout_secs = match input {
+        Some(RunInput::Interactive(_)) => RUN_INTERACTIVE_TIMEOUT_SECONDS,
+        _ => RUN_TIMEOUT_SECONDS,
+    };
+    let finished = run_phase(
+        &mut child_cmd,
+        RunPhase::Run,
+        Duration::from_secs(run_timeout_secs),
+        input,
+        output,
+        "Не вдалося виконати код в ізольованому середовищі (пісочниці)",
+    )
+    .await?;
+    match finished {
+        Some(status) => Ok(output.finish(RunStatus::from(status), status.code())),
+        None => {
+            output.note(RunPhase::Run, &format!("Перевищено ліміт часу виконання (ліміт {run_timeout_secs} секунд)."));
+            Ok(output.finish(RunStatus::TimedOut, None))
+        }
+    }
+}
+
+/// Запускає тести: `cargo test` для Cargo-проектів, інакше `rustc --test` та виконання в пісочниці.
+async fn run_tests(
+    files: &HashMap<String, String>,
+    root: &Path,
+    input: Option<RunInput>,
+    output: &mut CollectedOutput,
+) -> RequestResult<ExecutionResult> {
+    let exe_name = if cfg!(windows) { "test_program.exe" } else { "test_program" };
+    let exe_path = root.join(exe_name);
+    let test_timeout = Duration::from_secs(TEST_TIMEOUT_SECONDS);
+    let timed_out = format!("Перевищено ліміт часу виконання тестів (ліміт {TEST_TIMEOUT_SECONDS} секунд).");
+
+    if files.contains_key("Cargo.toml") {
+        let mut cmd = Command::new("cargo");
+        cmd.arg("test").current_dir(root);
+
+        return match run_phase(&mut cmd, RunPhase::Run, test_timeout, input, output, "Не вдалося запустити тести").await? {
+            Some(status) => Ok(output.finish(RunStatus::from(status), status.code())),
+            None => {
+                output.note(RunPhase::Run, &timed_out);
+                Ok(output.finish(RunStatus::TimedOut, None))
+            }
+        };
+    }
+
+    let entrypoint = find_entrypoint(files)
+        .ok_or_else(|| RequestError::bad_request("Проект повинен містити точку входу: main.rs або src/main.rs"))?;
+
+    let mut compile_cmd = Command::new("rustc");
+    compile_cmd.arg("--test").arg(entrypoint).arg("-o").arg(&exe_path).current_dir(root);
+
+    let compiled = run_phase(&mut compile_cmd, RunPhase::Compile, test_timeout, None, output, "Не вдалося скомпілювати тести").await?;
+    let Some(status) = compiled else {
+        output.note(RunPhase::Compile, &format!("Перевищено ліміт часу компіляції тестів (ліміт {TEST_TIMEOUT_SECONDS} секунд)."));
+        return Ok(output.finish(RunStatus::TimedOut, None));
+    };
+    if !status.success() {
+        return Ok(output.finish(RunStatus::Failed, status.code()));
+    }
+
+    let mut child_cmd = sandbox_command(root, &exe_path)?;
+
+    let finished = run_phase(
+        &mut child_cmd,
+        RunPhase::Run,
+        test_timeout,
+        input,
+        output,
+        "Не вдалося виконати тести в ізольованому середовищі",
+    )
+    .await?;
+    match finished {
+        Some(status) => Ok(output.finish(RunStatus::from(status), status.code())),
+        None => {
+            output.note(RunPhase::Run, &timed_out);
+            Ok(output.finish(RunStatus::TimedOut, None))
+        }
+    }
+}
+
+/// Команда запуску скомпільованого файлу з обмеженнями ресурсів та ізоляцією привілеїв.
+fn sandbox_command(root: &Path, exe_path: &Path) -> RequestResult<Command> {
     // Налаштовуємо дозволи для ізольованого середовища пісочниці (тільки для Unix)
     #[cfg(unix)]
     {
-        if let Err(e) = set_sandbox_permissions(temp_dir.path(), &exe_path) {
+        if let Err(e) = set_sandbox_permissions(root, exe_path) {
             tracing::error!("Не вдалося налаштувати дозволи для пісочниці: {}", e);
             return Err(RequestError::internal_server_error("Не вдалося підготувати дозволи для пісочниці"));
         }
+        Ok(get_sandbox_command(exe_path, root))
     }
+    #[cfg(not(unix))]
+    {
+        let mut cmd = Command::new(exe_path);
+        cmd.current_dir(root);
+        Ok(cmd)
+    }
+}
 
-    // Будуємо команду виконання з обмеженнями ресурсів та ізоляцією привілеїв
-    let mut child_cmd = {
-        #[cfg(unix)]
-        {
-            get_sandbox_command(&exe_path, temp_dir.path())
+// ─────────────────────────── Streaming output ────────────────────────────────
+
+/// Отримувач фрагментів виводу запуску.
+pub type OutputSink = mpsc::UnboundedSender<OutputChunk>;
+
+/// Відправник рядків stdin інтерактивного запуску; його закриття означає EOF.
+pub type StdinSender = mpsc::Sender<String>;
+
+/// Звідки програма отримує stdin.
+pub enum RunInput {
+    /// Увесь stdin відомий заздалегідь; після нього — EOF.
+    Fixed(String),
+    /// Дані надходять від клієнта під час виконання, доки відправник не закрито.
+    Interactive(mpsc::Receiver<String>),
+}
+
+impl From<ExitStatus> for RunStatus {
+    fn from(status: ExitStatus) -> Self {
+        if status.success() { RunStatus::Succeeded } else { RunStatus::Failed }
+    }
+}
+
+/// Вивід усіх фаз запуску: накопичується для підсумку та водночас передається в `sink`.
+struct CollectedOutput {
+    sink: Option<OutputSink>,
+    stdout: String,
+    stderr: String,
+}
+
+impl CollectedOutput {
+    fn new(sink: Option<OutputSink>) -> Self {
+        Self { sink, stdout: String::new(), stderr: String::new() }
+    }
+
+    /// Додає службове повідомлення сервера (наприклад, про перевищення ліміту часу) до stderr.
+    fn note(&mut self, phase: RunPhase, message: &str) {
+        if !self.stderr.is_empty() && !self.stderr.ends_with('\n') {
+            self.stderr.push('\n');
         }
-        #[cfg(not(unix))]
-        {
-            let mut cmd = Command::new(&exe_path);
-            cmd.current_dir(temp_dir.path());
-            cmd
+        self.stderr.push_str(message);
+        send_chunk(self.sink.as_ref(), phase, OutputStream::Stderr, message.to_string());
+    }
+
+    fn finish(&mut self, status: RunStatus, exit_code: Option<i32>) -> ExecutionResult {
+        ExecutionResult {
+            success: status == RunStatus::Succeeded,
+            status,
+            exit_code,
+            stdout: std::mem::take(&mut self.stdout),
+            stderr: std::mem::take(&mut self.stderr),
         }
-    };
+    }
+}
 
-    let child = child_cmd
+/// Запускає процес однієї фази та чекає на його завершення не довше `limit`,
+/// передаючи stdout та stderr по мірі появи, а `input` — у stdin.
+///
+/// Повертає `None`, якщо процес перевищив ліміт часу і його зупинено.
+async fn run_phase(
+    cmd: &mut Command,
+    phase: RunPhase,
+    limit: Duration,
+    input: Option<RunInput>,
+    output: &mut CollectedOutput,
+    spawn_error: &'static str,
+) -> RequestResult<Option<ExitStatus>> {
+    let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+    // Власна група процесів: так разом із процесом зупиняються й усі його нащадки
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let mut child = cmd
+        .stdin(stdin)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .kill_on_drop(true)
         .spawn()
         .map_err(|e| {
-            tracing::error!("Не вдалося запустити програму: {}", e);
-            RequestError::internal_server_error("Не вдалося виконати код в ізольованому середовищі (пісочниці)")
+            tracing::error!("Не вдалося запустити процес ({phase:?}): {}", e);
+            RequestError::internal_server_error(spawn_error)
         })?;
-
-    // Виконуємо з жорстким таймаутом у 5 секунд
-    let run_timeout = Duration::from_secs(5);
-    
-    match timeout(run_timeout, child.wait_with_output()).await {
-        Ok(Ok(run_output)) => {
-            Ok(ExecutionResult {
-                success: run_output.status.success(),
-                stdout: String::from_utf8_lossy(&run_output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&run_output.stderr).to_string(),
-            })
+    // Оголошена після `child`, тож під час скасування спрацьовує раніше за `kill_on_drop`
+    let group = ProcessGroup::new(child.id());
+
+    let stdin = child.stdin.take();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let sink = output.sink.as_ref();
+    let finished = timeout(limit, async {
+        let outputs = async {
+            let leader = async {
+                let status = child.wait().await;
+                group.leader_reaped();
+                status
+            };
+            tokio::join!(
+                leader,
+                pump(stdout, phase, OutputStream::Stdout, sink, &mut output.stdout),
+                pump(stderr, phase, OutputStream::Stderr, sink, &mut output.stderr),
+            )
+        };
+        // Завершення процесу не чекає на клієнта: недочитаний stdin просто відкидається
+        let feeding = feed(stdin, input);
+        tokio::pin!(outputs, feeding);
+        let mut fed = false;
+        loop {
+            tokio::select! {
+                (status, _, _) = &mut outputs => break status,
+                _ = &mut feeding, if !fed => fed = true,
+            }
         }
+    })
+    .await;
+
+    match finished {
+        Ok(Ok(status)) => Ok(Some(status)),
         Ok(Err(e)) => {
-            tracing::error!("Не вдалося дочекатися завершення програми: {}", e);
+            tracing::error!("Не вдалося дочекатися завершення процесу ({phase:?}): {}", e);
             Err(RequestError::internal_server_error("Помилка під час виконання програми"))
         }
         Err(_) => {
-            Ok(ExecutionResult {
-                success: false,
-                stdout: "".to_string(),
-                stderr: "Перевищено ліміт часу виконання (ліміт 5 секунд).".to_string(),
-            })
+            group.kill();
+            if let Err(e) = child.kill().await {
+                tracing::warn!("Не вдалося зупинити процес після перевищення ліміту часу: {}", e);
+            }
+            Ok(None)
         }
     }
 }
 
-/// Компілює та виконує тести Rust у проекті (з підтримкою як Cargo, так і rustc --test).
-pub async fn execute_rust_tests(
-    files: &std::collections::HashMap<String, String>,
-    _ctx: &crate::app::ServiceContext<'_>,
-) -> RequestResult<ExecutionResult> {
-    // Створюємо тимчасову директорію для збирання та виконання
-    let temp_dir = TempDir::new().map_err(|e| {
-        tracing::error!("Не вдалося створити тимчасову директорію для тестів: {}", e);
-        RequestError::internal_server_error("Не вдалося створити тимчасове середовище")
-    })?;
-
-    // Записуємо структуру папок та файлів проекту
-    for (relative_path, content) in files {
-        // Пропускаємо записи директорій та gitkeep-маркери
-        if should_skip_entry(relative_path) {
-            continue;
-        }
-        if !is_path_safe(relative_path) {
-            return Err(RequestError::bad_request(format!("Неприпустимий шлях до файлу: {}", relative_path)));
-        }
-
-        let file_path = temp_dir.path().join(relative_path);
-
-        if let Some(parent) = file_path.parent() {
-            fs::create_dir_all(parent).await.map_err(|e| {
-                tracing::error!("Не вдалося створити директорію {:?}: {}", parent, e);
-                RequestError::internal_server_error("Не вдалося підготувати структуру папок проекту")
-            })?;
-        }
+/// Група процесів фази, яку очолює запущений процес.
+///
+/// Коли фазу зупиняють за лімітом часу чи через скасування запуску, уся група отримує SIGKILL:
+/// `kill_on_drop` зупиняє лише сам процес, а не процеси, які запустили `cargo` чи програма
+/// користувача. Сигнал надсилається лише доки лідера групи не дочекались: після цього його
+/// pid, а з ним і pgid, може отримати інша група, а сервер працює від root.
+pub(crate) struct ProcessGroup {
+    pgid: Option<i32>,
+    leader_alive: AtomicBool,
+}
 
-        fs::write(&file_path, content).await.map_err(|e| {
-            tracing::error!("Не вдалося записати файл {:?}: {}", file_path, e);
-            RequestError::internal_server_error("Не вдалося зберегти файли проекту")
-        })?;
+impl ProcessGroup {
+    pub(crate) fn new(leader: Option<u32>) -> Self {
+        let pgid = leader.and_then(|pid| i32::try_from(pid).ok());
+        Self { pgid, leader_alive: AtomicBool::new(pgid.is_some()) }
     }
 
-    let exe_name = if cfg!(windows) { "test_program.exe" } else { "test_program" };
-    let exe_path = temp_dir.path().join(exe_name);
-
-    let is_cargo = files.contains_key("Cargo.toml");
-    let run_timeout = Duration::from_secs(10);
+    /// Лідера групи дочекались: надсилати сигнал групі більше не можна.
+    pub(crate) fn leader_reaped(&self) {
+        self.leader_alive.store(false, Ordering::SeqCst);
+    }
 
-    if is_cargo {
-        let mut cmd = Command::new("cargo");
-        cmd.arg("test")
-           .current_dir(temp_dir.path())
-           .stdout(Stdio::piped())
-           .stderr(Stdio::piped());
-
-        let fut = cmd.output();
-        match timeout(run_timeout, fut).await {
-            Ok(Ok(output)) => {
-                Ok(ExecutionResult {
-                    success: output.status.success(),
-                    stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                    stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                })
-            }
-            Ok(Err(e)) => {
-                tracing::error!("Не вдалося запустити cargo test: {}", e);
-                Err(RequestError::internal_server_error("Не вдалося запустити тести"))
-            }
-            Err(_) => {
-                Ok(ExecutionResult {
-                    success: false,
-                    stdout: "".to_string(),
-                    stderr: "Перевищено ліміт часу виконання тестів (ліміт 10 секунд).".to_string(),
-                })
+    /// Надсилає SIGKILL усій групі, якщо її лідера ще не дочекались.
+    fn kill(&self) {
+        if !self.leader_alive.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        #[cfg(unix)]
+        if let Some(pgid) = self.pgid {
+            // Лідер міг уже завершитись, але доки його не дочекались, pgid належить цій групі
+            unsafe {
+                killpg(pgid, SIGKILL);
             }
         }
-    } else {
-        let entrypoint = find_entrypoint(files)
-            .ok_or_else(|| RequestError::bad_request("Проект повинен містити точку входу: main.rs або src/main.rs"))?;
+    }
+}
 
-        let mut compile_cmd = Command::new("rustc");
-        compile_cmd.arg("--test")
-                   .arg(entrypoint)
-                   .arg("-o")
-                   .arg(&exe_path)
-                   .current_dir(temp_dir.path())
-                   .stdout(Stdio::piped())
-                   .stderr(Stdio::piped());
-
-        let compile_future = compile_cmd.output();
-        let compile_output = match timeout(Duration::from_secs(10), compile_future).await {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) => {
-                tracing::error!("Не вдалося запустити rustc --test: {}", e);
-                return Err(RequestError::internal_server_error("Не вдалося скомпілювати тести"));
-            }
-            Err(_) => {
-                return Ok(ExecutionResult {
-                    success: false,
-                    stdout: "".to_string(),
-                    stderr: "Перевищено ліміт часу компіляції тестів (ліміт 10 секунд).".to_string(),
-                });
-            }
-        };
+impl Drop for ProcessGroup {
+    /// Фазу відкинуто до завершення процесу — запуск скасовано.
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
 
-        if !compile_output.status.success() {
-            return Ok(ExecutionResult {
-                success: false,
-                stdout: String::from_utf8_lossy(&compile_output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&compile_output.stderr).to_string(),
-            });
+/// Пише `input` у stdin процесу та закриває його (EOF), коли дані скінчилися.
+async fn feed(stdin: Option<ChildStdin>, input: Option<RunInput>) {
+    let (Some(mut stdin), Some(input)) = (stdin, input) else {
+        return;
+    };
+    match input {
+        RunInput::Fixed(data) => {
+            // Програма може завершитись, не дочитавши stdin, — це не помилка
+            let _ = stdin.write_all(data.as_bytes()).await;
         }
-
-        #[cfg(unix)]
-        {
-            if let Err(e) = set_sandbox_permissions(temp_dir.path(), &exe_path) {
-                tracing::error!("Не вдалося налаштувати дозволи для пісочниці тестів: {}", e);
-                return Err(RequestError::internal_server_error("Не вдалося підготувати дозволи для пісочниці"));
+        RunInput::Interactive(mut lines) => {
+            while let Some(line) = lines.recv().await {
+                if stdin.write_all(line.as_bytes()).await.is_err() || stdin.flush().await.is_err() {
+                    break;
+                }
             }
         }
+    }
+}
 
-        let mut child_cmd = {
-            #[cfg(unix)]
-            {
-                get_sandbox_command(&exe_path, temp_dir.path())
-            }
-            #[cfg(not(unix))]
-            {
-                let mut cmd = Command::new(&exe_path);
-                cmd.current_dir(temp_dir.path());
-                cmd
-            }
+/// Читає потік виводу до кінця, дописуючи його в `collected` та передаючи фрагменти в `sink`.
+///
+/// Після `RUN_OUTPUT_MAX_BYTES` вивід лише дочитується, щоб процес не заблокувався на записі.
+async fn pump<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    phase: RunPhase,
+    stream: OutputStream,
+    sink: Option<&OutputSink>,
+    collected: &mut String,
+) {
+    let Some(mut reader) = reader else {
+        return;
+    };
+    let mut buf = vec![0u8; 8192];
+    let mut pending = Vec::new();
+    let mut truncated = false;
+
+    loop {
+        let read = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
         };
+        if truncated {
+            continue;
+        }
+        pending.extend_from_slice(&buf[..read]);
+        let text = take_utf8(&mut pending);
+        truncated = !append_output(collected, text, phase, stream, sink);
+    }
 
-        let child = child_cmd
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .kill_on_drop(true)
-            .spawn()
-            .map_err(|e| {
-                tracing::error!("Не вдалося запустити тестову програму: {}", e);
-                RequestError::internal_server_error("Не вдалося виконати тести в ізольованому середовищі")
-            })?;
+    if !truncated && !pending.is_empty() {
+        let text = String::from_utf8_lossy(&pending).into_owned();
+        append_output(collected, text, phase, stream, sink);
+    }
+}
 
-        match timeout(run_timeout, child.wait_with_output()).await {
-            Ok(Ok(run_output)) => {
-                Ok(ExecutionResult {
-                    success: run_output.status.success(),
-                    stdout: String::from_utf8_lossy(&run_output.stdout).to_string(),
-                    stderr: String::from_utf8_lossy(&run_output.stderr).to_string(),
-                })
-            }
-            Ok(Err(e)) => {
-                tracing::error!("Не вдалося дочекатися завершення тестів: {}", e);
-                Err(RequestError::internal_server_error("Помилка під час виконання тестів"))
-            }
-            Err(_) => {
-                Ok(ExecutionResult {
-                    success: false,
-                    stdout: "".to_string(),
-                    stderr: "Перевищено ліміт часу виконання тестів (ліміт 10 секунд).".to_string(),
-                })
-            }
+/// Дописує текст до виводу в межах ліміту; повертає `false`, якщо ліміт вичерпано.
+fn append_output(
+    collected: &mut String,
+    mut text: String,
+    phase: RunPhase,
+    stream: OutputStream,
+    sink: Option<&OutputSink>,
+) -> bool {
+    let room = RUN_OUTPUT_MAX_BYTES.saturating_sub(collected.len());
+    let fits = text.len() <= room;
+    if !fits {
+        let mut cut = room;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
         }
+        text.truncate(cut);
+        text.push_str(&format!("\n[Вивід обрізано: перевищено ліміт {} КБ]\n", RUN_OUTPUT_MAX_BYTES / 1024));
+    }
+    if !text.is_empty() {
+        collected.push_str(&text);
+        send_chunk(sink, phase, stream, text);
+    }
+    fits
+}
+
+fn send_chunk(sink: Option<&OutputSink>, phase: RunPhase, stream: OutputStream, data: String) {
+    if let Some(sink) = sink {
+        // Отримувач зникає, лише коли запуск уже нікому не потрібен
+        let _ = sink.send(OutputChunk { phase, stream, data, ts: Utc::now() });
     }
 }
 
+/// Забирає з буфера найдовший префікс, що є завершеним UTF-8 текстом.
+///
+/// Неповний багатобайтовий символ у кінці лишається в буфері до наступного читання,
+/// а некоректні байти замінюються на `U+FFFD`.
+pub(crate) fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(err) if err.error_len().is_none() => err.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(complete);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
 /// Форматує переданий Rust код за допомогою `rustfmt`.
 pub async fn format_rust_code(code: &str) -> RequestResult<String> {
-    use tokio::io::AsyncWriteExt;
-
     let mut child = Command::new("rustfmt")
         .arg("--edition")
         .arg("2021")
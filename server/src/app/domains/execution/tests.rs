@@ -5,8 +5,16 @@
 mod tests {
     use std::collections::HashMap;
 
+    use crate::app::domains::execution::models::{OutputChunk, OutputStream, RunKind, RunPhase, RunStatus};
+    use actix_web::ResponseError as _;
+    use actix_web::http::StatusCode;
+    use uuid::Uuid;
+
+    use crate::app::domains::execution::queue::{ExecutionQueue, JobOwner};
+    use crate::app::domains::execution::runs::{coalesce, spawn_run, ActiveRun, RunRegistry};
+    use crate::core::config_builder::ExecutionSettings;
     use crate::app::domains::execution::service::{
-        find_entrypoint, is_path_safe, parse_package_name, should_skip_entry,
+        find_entrypoint, is_path_safe, parse_package_name, run_project, should_skip_entry, take_utf8, ProcessGroup, RunInput,
     };
 
     mod path_safety {
@@ -147,4 +155,396 @@ edition = "2021"
             assert_eq!(parse_package_name(""), "app");
         }
     }
+
+    mod streaming {
+        use super::*;
+
+        fn chunk(phase: RunPhase, stream: OutputStream, data: &str) -> OutputChunk {
+            OutputChunk { phase, stream, data: data.to_string(), ts: chrono::Utc::now() }
+        }
+
+        /// Тест 14: Неповний багатобайтовий символ лишається в буфері до наступного читання.
+        #[test]
+        fn split_utf8_char_waits_for_rest() {
+            let bytes = "Привіт".as_bytes();
+            let mut pending = bytes[..3].to_vec();
+
+            assert_eq!(take_utf8(&mut pending), "П");
+            assert_eq!(pending, bytes[2..3], "Перший байт 'р' чекає на другий");
+
+            pending.extend_from_slice(&bytes[3..]);
+            assert_eq!(take_utf8(&mut pending), "ривіт");
+            assert!(pending.is_empty());
+        }
+
+        /// Тест 15: Некоректні байти замінюються, а не затримують вивід.
+        #[test]
+        fn invalid_bytes_are_replaced() {
+            let mut pending = vec![b'a', 0xFF, b'b'];
+            assert_eq!(take_utf8(&mut pending), "a\u{FFFD}b");
+            assert!(pending.is_empty());
+        }
+
+        /// Тест 16: Сусідні фрагменти одного потоку об'єднуються, зміна потоку чи фази — ні.
+        #[test]
+        fn coalesce_merges_adjacent_chunks_of_same_stream() {
+            let merged = coalesce(vec![
+                chunk(RunPhase::Compile, OutputStream::Stderr, "warning: "),
+                chunk(RunPhase::Compile, OutputStream::Stderr, "unused\n"),
+                chunk(RunPhase::Run, OutputStream::Stdout, "1\n"),
+                chunk(RunPhase::Run, OutputStream::Stderr, "oops\n"),
+                chunk(RunPhase::Run, OutputStream::Stdout, "2\n"),
+                chunk(RunPhase::Run, OutputStream::Stdout, "3\n"),
+            ]);
+
+            let data: Vec<_> = merged.iter().map(|c| (c.phase, c.stream, c.data.as_str())).collect();
+            assert_eq!(
+                data,
+                vec![
+                    (RunPhase::Compile, OutputStream::Stderr, "warning: unused\n"),
+                    (RunPhase::Run, OutputStream::Stdout, "1\n"),
+                    (RunPhase::Run, OutputStream::Stderr, "oops\n"),
+                    (RunPhase::Run, OutputStream::Stdout, "2\n3\n"),
+                ]
+            );
+        }
+
+        /// Тест 17: Вивід програми надходить у sink з фазою `run` і збігається з підсумком.
+        #[actix_rt::test]
+        async fn program_output_is_streamed() {
+            let mut files = HashMap::new();
+            files.insert(
+                "main.rs".to_string(),
+                r#"fn main() { println!("one"); eprintln!("two"); std::process::exit(3); }"#.to_string(),
+            );
+            let (sink, mut chunks) = tokio::sync::mpsc::unbounded_channel();
+
+            let result = run_project(&files, RunKind::Run, None, Some(sink)).await.expect("rustc доступний");
+
+            let mut streamed = Vec::new();
+            while let Some(chunk) = chunks.recv().await {
+                streamed.push(chunk);
+            }
+            let run_output: Vec<_> = coalesce(streamed)
+                .into_iter()
+                .filter(|c| c.phase == RunPhase::Run)
+                .map(|c| (c.stream, c.data))
+                .collect();
+            assert!(run_output.contains(&(OutputStream::Stdout, "one\n".to_string())));
+            assert!(run_output.contains(&(OutputStream::Stderr, "two\n".to_string())));
+            assert_eq!(result.status, RunStatus::Failed);
+            assert_eq!(result.exit_code, Some(3));
+            assert_eq!(result.stdout, "one\n");
+        }
+
+        /// Програма, що повторює кожен рядок stdin з префіксом і рахує рядки до EOF.
+        fn echo_project() -> HashMap<String, String> {
+            let mut files = HashMap::new();
+            files.insert(
+                "main.rs".to_string(),
+                r#"use std::io::BufRead;
+fn main() {
+    let mut count = 0;
+    for line in std::io::stdin().lock().lines() {
+        println!("> {}", line.unwrap());
+        count += 1;
+    }
+    println!("lines: {count}");
+}"#
+                .to_string(),
+            );
+            files
+        }
+
+        /// Тест 18: Заданий заздалегідь stdin передається програмі, після нього — EOF.
+        #[actix_rt::test]
+        async fn fixed_stdin_is_fed_to_program() {
+            let input = RunInput::Fixed("a\nb\n".to_string());
+            let result = run_project(&echo_project(), RunKind::Run, Some(input), None).await.expect("rustc доступний");
+
+            assert_eq!(result.status, RunStatus::Succeeded);
+            assert_eq!(result.stdout, "> a\n> b\nlines: 2\n");
+        }
+
+        /// Тест 19: Інтерактивний stdin: відповідь на рядок приходить до EOF, закриття відправника завершує програму.
+        #[actix_rt::test]
+        async fn interactive_stdin_streams_until_eof() {
+            let (stdin, lines) = tokio::sync::mpsc::channel(4);
+            let (sink, mut chunks) = tokio::sync::mpsc::unbounded_channel();
+            let run = actix_rt::spawn(async move {
+                run_project(&echo_project(), RunKind::Run, Some(RunInput::Interactive(lines)), Some(sink)).await
+            });
+
+            stdin.send("hello\n".to_string()).await.unwrap();
+            let echoed = loop {
+                let chunk = chunks.recv().await.expect("програма відповідає до EOF");
+                if chunk.phase == RunPhase::Run {
+                    break chunk.data;
+                }
+            };
+            assert_eq!(echoed, "> hello\n");
+
+            drop(stdin);
+            let result = run.await.unwrap().expect("rustc доступний");
+            assert_eq!(result.status, RunStatus::Succeeded);
+            assert_eq!(result.stdout, "> hello\nlines: 1\n");
+        }
+
+        /// Тест 20: stdin запуску приймається лише від підключення, що його розпочало, і лише до EOF.
+        #[actix_rt::test]
+        async fn stdin_belongs_to_starting_connection() {
+            let registry = RunRegistry::default();
+            let (stdin, lines) = tokio::sync::mpsc::channel(4);
+            let owner = Uuid::now_v7();
+            let job_owner = JobOwner { user_id: Uuid::now_v7(), doc_id: Uuid::now_v7() };
+            let job = ExecutionQueue::default().submit(Uuid::now_v7(), job_owner).unwrap();
+            let run = ActiveRun {
+                doc_id: job_owner.doc_id,
+                conn_id: owner,
+                user_id: job_owner.user_id,
+                username: "tester".to_string(),
+                kind: RunKind::Run,
+                stdin: Some(stdin),
+            };
+            let run_id = spawn_run(&registry, job, run, echo_project(), Some(RunInput::Interactive(lines)), |_| {});
+
+            let stranger = registry.write_stdin(run_id, Uuid::now_v7(), "x\n".to_string());
+            assert_eq!(stranger.unwrap_err().status_code(), StatusCode::FORBIDDEN);
+
+            registry.close_stdin(run_id, owner).unwrap();
+            let after_eof = registry.write_stdin(run_id, owner, "x\n".to_string());
+            assert_eq!(after_eof.unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    mod queue {
+        use super::*;
+        use std::cell::RefCell;
+
+        fn queue(workers: usize, queue_capacity: usize, max_jobs_per_user: usize, max_jobs_per_document: usize) -> ExecutionQueue {
+            ExecutionQueue::new(ExecutionSettings { workers, queue_capacity, max_jobs_per_user, max_jobs_per_document })
+        }
+
+        fn owner(doc_id: Uuid) -> JobOwner {
+            JobOwner { user_id: Uuid::now_v7(), doc_id }
+        }
+
+        /// Тест 21: Завдання понад кількість обробників чекають у порядку надходження та дізнаються свою позицію.
+        #[actix_rt::test]
+        async fn jobs_wait_in_order_and_report_positions() {
+            let queue = queue(1, 8, 8, 8);
+            let doc_id = Uuid::now_v7();
+            let first = queue.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let second = queue.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let third = queue.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+
+            let (release_first, first_released) = tokio::sync::oneshot::channel::<()>();
+            let (release_second, second_released) = tokio::sync::oneshot::channel::<()>();
+            let order = RefCell::new(Vec::new());
+            let (second_positions, third_positions) = (RefCell::new(Vec::new()), RefCell::new(Vec::new()));
+            let all = async {
+                tokio::join!(
+                    first.run(|_| panic!("Перше завдання не чекає"), async {
+                        first_released.await.unwrap();
+                        order.borrow_mut().push(1);
+                    }),
+                    second.run(|p| second_positions.borrow_mut().push(p), async {
+                        second_released.await.unwrap();
+                        order.borrow_mut().push(2);
+                    }),
+                    third.run(|p| third_positions.borrow_mut().push(p), async { order.borrow_mut().push(3) }),
+                    async {
+                        actix_rt::task::yield_now().await;
+                        release_first.send(()).unwrap();
+                        // Друге завдання тримає обробник, доки третє не дізнається, що воно перше в черзі
+                        while third_positions.borrow().len() < 2 {
+                            actix_rt::task::yield_now().await;
+                        }
+                        release_second.send(()).unwrap();
+                    },
+                )
+            };
+            tokio::time::timeout(std::time::Duration::from_secs(5), all).await.expect("Черга не просувається");
+
+            assert_eq!(order.into_inner(), [1, 2, 3]);
+            assert_eq!(second_positions.into_inner(), [1]);
+            assert_eq!(third_positions.into_inner(), [2, 1]);
+        }
+
+        /// Тест 22: Черга відхиляє завдання понад місткість та ліміти користувача й документа, а звільнене місце знову доступне.
+        #[test]
+        fn limits_are_enforced() {
+            let limits = queue(1, 8, 2, 3);
+            let doc_id = Uuid::now_v7();
+            let user = owner(doc_id);
+            let _first = limits.submit(Uuid::now_v7(), user).unwrap();
+            let second = limits.submit(Uuid::now_v7(), user).unwrap();
+            let by_user = limits.submit(Uuid::now_v7(), user).err().unwrap();
+            assert_eq!(by_user.status_code(), StatusCode::CONFLICT);
+
+            let _other = limits.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let by_doc = limits.submit(Uuid::now_v7(), owner(doc_id)).err().unwrap();
+            assert_eq!(by_doc.status_code(), StatusCode::CONFLICT);
+            drop(second);
+            assert!(limits.submit(Uuid::now_v7(), user).is_ok());
+
+            let capacity = queue(1, 1, 8, 8);
+            let waiting = capacity.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let full = capacity.submit(Uuid::now_v7(), owner(doc_id)).err().unwrap();
+            assert_eq!(full.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+            drop(waiting);
+            assert!(capacity.submit(Uuid::now_v7(), owner(doc_id)).is_ok());
+        }
+
+        /// Тест 23: Автор або менеджер скасовує завдання в черзі чи під час виконання; сторонні — ні.
+        #[actix_rt::test]
+        async fn jobs_are_cancelled_by_owner_or_manager() {
+            let queue = queue(1, 8, 8, 8);
+            let doc_id = Uuid::now_v7();
+            let (running_owner, waiting_owner) = (owner(doc_id), owner(doc_id));
+            let running = queue.submit(Uuid::now_v7(), running_owner).unwrap();
+            let waiting = queue.submit(Uuid::now_v7(), waiting_owner).unwrap();
+            let (running_id, waiting_id) = (running.id(), waiting.id());
+
+            let (running_result, waiting_result, ()) = tokio::join!(
+                running.run(|_| {}, std::future::pending::<()>()),
+                waiting.run(|_| {}, async {}),
+                async {
+                    actix_rt::task::yield_now().await;
+                    let stranger = queue.cancel(waiting_id, doc_id, Uuid::now_v7(), false).unwrap_err();
+                    assert_eq!(stranger.status_code(), StatusCode::FORBIDDEN);
+                    let other_doc = queue.cancel(waiting_id, Uuid::now_v7(), waiting_owner.user_id, false).unwrap_err();
+                    assert_eq!(other_doc.status_code(), StatusCode::NOT_FOUND);
+
+                    queue.cancel(waiting_id, doc_id, waiting_owner.user_id, false).unwrap();
+                    queue.cancel(running_id, doc_id, Uuid::now_v7(), true).unwrap();
+                },
+            );
+
+            assert_eq!(running_result, None);
+            assert_eq!(waiting_result, None);
+            assert_eq!(queue.owner(running_id), None);
+            assert_eq!(queue.owner(waiting_id), None);
+        }
+
+        /// Тест 24: Ідентифікатор запуску, що ще в черзі, не можна використати повторно; після завершення — можна.
+        #[test]
+        fn duplicate_run_id_is_rejected() {
+            let queue = queue(1, 8, 8, 8);
+            let run_id = Uuid::now_v7();
+            let job = queue.submit(run_id, owner(Uuid::now_v7())).unwrap();
+
+            let err = queue.submit(run_id, owner(Uuid::now_v7())).err().unwrap();
+            assert_eq!(err.status_code(), StatusCode::CONFLICT);
+
+            drop(job);
+            assert!(queue.submit(run_id, owner(Uuid::now_v7())).is_ok());
+        }
+
+        /// Тест 27: Позицію запуску видно лише в межах його документа; для запуску, що виконується, її немає.
+        #[actix_rt::test]
+        async fn queue_position_is_scoped_to_document() {
+            let queue = queue(1, 8, 8, 8);
+            let doc_id = Uuid::now_v7();
+            let running = queue.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let waiting = queue.submit(Uuid::now_v7(), owner(doc_id)).unwrap();
+            let (running_id, waiting_id) = (running.id(), waiting.id());
+
+            let (running_result, waiting_result, ()) = tokio::join!(
+                running.run(|_| {}, std::future::pending::<()>()),
+                waiting.run(|_| {}, async {}),
+                async {
+                    actix_rt::task::yield_now().await;
+                    assert_eq!(queue.queue_position(running_id, doc_id).unwrap(), None);
+                    assert_eq!(queue.queue_position(waiting_id, doc_id).unwrap(), Some(1));
+                    let other_doc = queue.queue_position(waiting_id, Uuid::now_v7()).unwrap_err();
+                    assert_eq!(other_doc.status_code(), StatusCode::NOT_FOUND);
+
+                    queue.cancel(waiting_id, doc_id, Uuid::now_v7(), true).unwrap();
+                    queue.cancel(running_id, doc_id, Uuid::now_v7(), true).unwrap();
+                },
+            );
+
+            assert_eq!((running_result, waiting_result), (None, None));
+            let finished = queue.queue_position(waiting_id, doc_id).unwrap_err();
+            assert_eq!(finished.status_code(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    mod cancellation {
+        use super::*;
+        use std::time::Duration;
+
+        /// Чи живий процес `pid` (зомбі, якого ще не прибрав батько, вже не виконується).
+        fn is_alive(pid: u32) -> bool {
+            std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                .ok()
+                .and_then(|stat| stat.rsplit_once(')').map(|(_, rest)| rest.trim_start().to_string()))
+                .is_some_and(|rest| !rest.starts_with('Z'))
+        }
+
+        /// Тест 25: Скасування запуску зупиняє не лише програму, а й усі процеси, які вона запустила.
+        #[actix_rt::test]
+        async fn cancel_kills_the_whole_process_group() {
+            let mut files = HashMap::new();
+            files.insert(
+                "main.rs".to_string(),
+                r#"
+fn main() {
+    let child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+    println!("{}", child.id());
+    std::thread::sleep(std::time::Duration::from_secs(30));
+}
+"#
+                .to_string(),
+            );
+            let queue = ExecutionQueue::default();
+            let doc_id = Uuid::now_v7();
+            let job = queue.submit(Uuid::now_v7(), JobOwner { user_id: Uuid::now_v7(), doc_id }).unwrap();
+            let run_id = job.id();
+            let (sink, mut chunks) = tokio::sync::mpsc::unbounded_channel::<OutputChunk>();
+
+            let (result, grandchild) = tokio::join!(
+                job.run(|_| {}, run_project(&files, RunKind::Run, None, Some(sink))),
+                async {
+                    let mut stdout = String::new();
+                    while !stdout.ends_with('\n') {
+                        let chunk = chunks.recv().await.expect("програма друкує pid до завершення");
+                        if chunk.phase == RunPhase::Run && chunk.stream == OutputStream::Stdout {
+                            stdout.push_str(&chunk.data);
+                        }
+                    }
+                    let pid: u32 = stdout.trim().parse().unwrap();
+                    assert!(is_alive(pid));
+                    queue.cancel(run_id, doc_id, Uuid::now_v7(), true).unwrap();
+                    pid
+                },
+            );
+
+            assert!(result.is_none());
+            let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+            while is_alive(grandchild) && tokio::time::Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            assert!(!is_alive(grandchild), "процес, запущений програмою, пережив скасування");
+        }
+
+        /// Тест 26: Групі процесів сигнал надсилається лише доки її лідера не дочекались.
+        #[test]
+        fn process_group_is_not_killed_after_leader_is_reaped() {
+            use std::os::unix::process::CommandExt as _;
+
+            let mut leader = std::process::Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+            let pid = leader.id();
+
+            let reaped = ProcessGroup::new(Some(pid));
+            reaped.leader_reaped();
+            drop(reaped);
+            assert!(is_alive(pid), "після leader_reaped група не отримує SIGKILL");
+
+            drop(ProcessGroup::new(Some(pid)));
+            let status = leader.wait().unwrap();
+            assert!(!status.success(), "відкинута група з живим лідером отримує SIGKILL");
+        }
+    }
 }
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use uuid::Uuid;
+
+use crate::constants::git::{REPO_CACHE_MAX_PER_DOCUMENT, REPO_CACHE_TTL_SECONDS};
+
+/// Нещодавно зібрані репозиторії проектів для smart HTTP.
+///
+/// `info/refs` та `git-upload-pack` — окремі запити: якщо між ними з'явилась нова ревізія,
+/// заново зібраний репозиторій уже не містив би оголошених комітів. Тому репозиторій,
+/// зібраний для оголошення, зберігається за хешем HEAD, а upload-pack бере той,
+/// що містить запитані клієнтом коміти.
+#[derive(Clone, Default)]
+pub struct GitRepoCache {
+    repos: Arc<Mutex<HashMap<Uuid, Vec<CachedRepo>>>>,
+}
+
+struct CachedRepo {
+    head: String,
+    repo: Arc<TempDir>,
+    built_at: Instant,
+}
+
+impl GitRepoCache {
+    /// Зберігає репозиторій документа з HEAD `head` та прибирає застарілі записи.
+    pub fn insert(&self, doc_id: Uuid, head: String, repo: Arc<TempDir>) {
+        let ttl = Duration::from_secs(REPO_CACHE_TTL_SECONDS);
+        let mut repos = self.repos.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        repos.retain(|_, entries| {
+            entries.retain(|entry| entry.built_at.elapsed() < ttl);
+            !entries.is_empty()
+        });
+
+        let entries = repos.entry(doc_id).or_default();
+        entries.retain(|entry| entry.head != head);
+        entries.push(CachedRepo { head, repo, built_at: Instant::now() });
+        if entries.len() > REPO_CACHE_MAX_PER_DOCUMENT {
+            entries.remove(0);
+        }
+    }
+
+    /// Знаходить репозиторій документа, HEAD якого є серед запитаних комітів `wants`.
+    pub fn find(&self, doc_id: Uuid, wants: &[String]) -> Option<Arc<TempDir>> {
+        let ttl = Duration::from_secs(REPO_CACHE_TTL_SECONDS);
+        let repos = self.repos.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        repos
+            .get(&doc_id)?
+            .iter()
+            .rev()
+            .find(|entry| entry.built_at.elapsed() < ttl && wants.contains(&entry.head))
+            .map(|entry| entry.repo.clone())
+    }
+}
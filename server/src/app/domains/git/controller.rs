@@ -0,0 +1,156 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder,
+    web::{Bytes, Data, Path, Query},
+};
+use base64::Engine as _;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::service::{self, UPLOAD_PACK_SERVICE};
+use crate::core::app_data::AppData;
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::app::domains::auth::{validate_token, Claims};
+use crate::app::domains::document::controller::extract_claims;
+
+// ─────────────────────────── Helpers ─────────────────────────────────────────
+
+/// Витягує JWT для git-клієнта: `Authorization: Basic` (токен як пароль) або `Bearer`.
+fn extract_git_claims(req: &HttpRequest, jwt_secret: &str) -> RequestResult<Claims> {
+    let header = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .ok_or_else(|| RequestError::unauthorized("Відсутній Authorization заголовок"))?;
+
+    if let Some(token) = header.strip_prefix("Bearer ") {
+        return validate_token(token, jwt_secret);
+    }
+
+    let encoded = header
+        .strip_prefix("Basic ")
+        .ok_or_else(|| RequestError::unauthorized("Неправильний формат Authorization"))?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|_| RequestError::unauthorized("Неправильний формат Basic-автентифікації"))?;
+    let credentials = String::from_utf8_lossy(&decoded);
+    let (_, token) = credentials
+        .split_once(':')
+        .ok_or_else(|| RequestError::unauthorized("Неправильний формат Basic-автентифікації"))?;
+
+    validate_token(token, jwt_secret)
+}
+
+/// Відповідь 401 із запитом Basic-автентифікації, щоб git-клієнт запитав облікові дані.
+fn git_unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .insert_header(("WWW-Authenticate", "Basic realm=\"co-write\""))
+        .body("[401] Потрібна автентифікація")
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InfoRefsQuery {
+    service: Option<String>,
+}
+
+/// Експортує історію проекту як `git bundle`.
+///
+/// Отриманий файл можна клонувати локально: `git clone project.bundle project`.
+#[tracing::instrument(name = "export_git_bundle", skip(req, app_data), fields(doc_id = %doc_id))]
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}/export/git",
+    params(("id" = Uuid, Path, description = "Uuid документа")),
+    responses(
+        (status = 200, description = "Git bundle з історією проекту", content_type = "application/x-git-bundle"),
+        (status = 404, description = "Документ не знайдено або користувач не є його учасником")
+    )
+)]
+pub async fn export_git_bundle(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    app_data: Data<AppData>,
+) -> RequestResult<impl Responder> {
+    let claims = extract_claims(&req, &app_data.jwt_secret)?;
+    let doc_id = doc_id.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    service::ensure_can_read(doc_id, claims.sub, &ctx).await?;
+    let bundle = service::export_bundle(doc_id, &ctx).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-git-bundle")
+        .insert_header(("Content-Disposition", "attachment; filename=\"project.bundle\""))
+        .body(bundle))
+}
+
+// ─────────────────────────── Smart HTTP ──────────────────────────────────────
+
+/// Оголошення посилань репозиторію для `git clone`/`git fetch` (smart HTTP, тільки читання).
+#[tracing::instrument(name = "git_info_refs", skip(req, app_data), fields(doc_id = %doc_id))]
+#[utoipa::path(
+    get,
+    path = "/api/documents/{id}.git/info/refs",
+    params(
+        ("id" = Uuid, Path, description = "Uuid документа"),
+        ("service" = String, Query, description = "Сервіс git (підтримується лише git-upload-pack)"),
+    ),
+    responses(
+        (status = 200, description = "Перелік посилань репозиторію", content_type = "application/x-git-upload-pack-advertisement"),
+        (status = 404, description = "Документ не знайдено або користувач не є його учасником")
+    )
+)]
+pub async fn info_refs(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    query: Query<InfoRefsQuery>,
+    app_data: Data<AppData>,
+) -> RequestResult<HttpResponse> {
+    let Ok(claims) = extract_git_claims(&req, &app_data.jwt_secret) else {
+        return Ok(git_unauthorized());
+    };
+    if query.service.as_deref() != Some(UPLOAD_PACK_SERVICE) {
+        return Err(RequestError::forbidden("Репозиторій доступний лише для читання (git-upload-pack)"));
+    }
+
+    let doc_id = doc_id.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    service::ensure_can_read(doc_id, claims.sub, &ctx).await?;
+    let body = service::advertise_refs(doc_id, &ctx).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-git-upload-pack-advertisement")
+        .insert_header(("Cache-Control", "no-cache"))
+        .body(body))
+}
+
+/// Передача packfile клієнту git (`git-upload-pack`, stateless RPC).
+#[tracing::instrument(name = "git_upload_pack", skip(req, body, app_data), fields(doc_id = %doc_id))]
+#[utoipa::path(
+    post,
+    path = "/api/documents/{id}.git/git-upload-pack",
+    params(("id" = Uuid, Path, description = "Uuid документа")),
+    request_body(description = "Запит git upload-pack (pkt-line)", content_type = "application/x-git-upload-pack-request"),
+    responses(
+        (status = 200, description = "Packfile з історією проекту", content_type = "application/x-git-upload-pack-result"),
+        (status = 404, description = "Документ не знайдено або користувач не є його учасником")
+    )
+)]
+pub async fn upload_pack(
+    req: HttpRequest,
+    doc_id: Path<Uuid>,
+    body: Bytes,
+    app_data: Data<AppData>,
+) -> RequestResult<HttpResponse> {
+    let Ok(claims) = extract_git_claims(&req, &app_data.jwt_secret) else {
+        return Ok(git_unauthorized());
+    };
+
+    let doc_id = doc_id.into_inner();
+    let ctx = ServiceContext::from(app_data.get_ref());
+    service::ensure_can_read(doc_id, claims.sub, &ctx).await?;
+    let pack = service::upload_pack(doc_id, &body, &ctx).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-git-upload-pack-result")
+        .insert_header(("Cache-Control", "no-cache"))
+        .body(pack))
+}
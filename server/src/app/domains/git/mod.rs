@@ -0,0 +1,11 @@
+pub mod cache;
+pub mod controller;
+pub mod models;
+pub mod service;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
+
+pub use cache::GitRepoCache;
+pub use controller::{export_git_bundle, info_refs, upload_pack};
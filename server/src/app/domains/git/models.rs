@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+
+/// Один коміт історії проекту, що відтворюється у git-репозиторії.
+pub struct GitCommit {
+    /// Ім'я автора (username користувача Co-Write).
+    pub author_name: String,
+    /// Email автора, синтезований з username.
+    pub author_email: String,
+    /// Час створення коміту (зберігається як дата автора та комітера).
+    pub timestamp: DateTime<Utc>,
+    /// Повідомлення коміту.
+    pub message: String,
+    /// Повний стан дерева файлів на момент коміту: (відносний шлях, вміст).
+    pub files: Vec<(String, String)>,
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::process::{Output, Stdio};
+use tempfile::TempDir;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use uuid::Uuid;
+
+use super::models::GitCommit;
+use crate::app::domains::auth::repository as auth_repository;
+use crate::app::domains::document::models::{FileRevisionRow, RevisionKind};
+use crate::app::domains::document::repository as doc_repository;
+use crate::app::domains::document::service as doc_service;
+use crate::app::domains::execution::service::is_path_safe;
+use crate::app::{RequestError, RequestResult, ServiceContext};
+use crate::constants::git::{HISTORY_COMMIT_WINDOW_SECONDS, HISTORY_MESSAGE_MAX_FILES};
+
+/// Гілка, в яку записується історія проекту.
+pub(crate) const DEFAULT_BRANCH: &str = "main";
+
+/// Будує email автора для git з імені користувача Co-Write.
+pub(crate) fn author_email(username: &str) -> String {
+    format!("{}@co-write.local", username)
+}
+
+/// Форматує час у внутрішньому форматі дат git (`<unix> +0000`).
+pub(crate) fn git_date(timestamp: &chrono::DateTime<chrono::Utc>) -> String {
+    format!("{} +0000", timestamp.timestamp())
+}
+
+/// Перевіряє, що користувач є учасником документа (будь-яка роль може клонувати проект).
+///
+/// Стороннім відповідаємо 404, щоб не розкривати існування документа за його id.
+pub async fn ensure_can_read(doc_id: Uuid, user_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<()> {
+    match doc_service::get_user_role(doc_id, user_id, ctx).await? {
+        Some(_) => Ok(()),
+        None => Err(RequestError::not_found("Документ не знайдено")),
+    }
+}
+
+/// Завантажує історію проекту у вигляді послідовності git-комітів.
+///
+/// Коміти відтворюються з ревізій файлів (`project_file_revisions`); ревізії без автора
+/// приписуються власнику документа. Проект без жодної ревізії експортується одним комітом
+/// з поточним станом файлів.
+pub async fn load_history(doc_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<Vec<GitCommit>> {
+    let doc = doc_repository::read(doc_id, ctx.db_pool).await?;
+
+    let owner_name = match doc.owner_id {
+        Some(owner_id) => auth_repository::find_by_id(owner_id, ctx.db_pool)
+            .await?
+            .map(|u| u.username)
+            .unwrap_or_else(|| "co-write".to_string()),
+        None => "co-write".to_string(),
+    };
+
+    let revisions = doc_repository::get_document_revisions(doc_id, ctx.db_pool).await?;
+    if !revisions.is_empty() {
+        return Ok(history_from_revisions(&revisions, &owner_name));
+    }
+
+    let rows = doc_repository::get_all_files(doc_id, ctx.db_pool).await?;
+    let files = rows
+        .into_iter()
+        .filter(|r| !r.is_dir)
+        .map(|r| (r.path, r.content))
+        .collect();
+
+    Ok(vec![GitCommit {
+        author_email: author_email(&owner_name),
+        author_name: owner_name,
+        timestamp: doc.updated_at,
+        message: format!("Експорт проекту Co-Write: {}", doc.title),
+        files,
+    }])
+}
+
+/// Відтворює коміти з ревізій файлів, упорядкованих за часом.
+///
+/// Послідовні ревізії одного автора, зроблені протягом `HISTORY_COMMIT_WINDOW_SECONDS`
+/// від першої з них, утворюють один коміт. Кожен коміт містить повний стан проекту
+/// після своїх ревізій; ревізії без автора приписуються `fallback_author`.
+pub(crate) fn history_from_revisions(revisions: &[FileRevisionRow], fallback_author: &str) -> Vec<GitCommit> {
+    let window = chrono::Duration::seconds(HISTORY_COMMIT_WINDOW_SECONDS);
+    // Стан проекту за ідентичністю файлу, щоб перейменування не залишали копій за старим шляхом
+    let mut tree: HashMap<Uuid, (String, String)> = HashMap::new();
+    let mut commits = Vec::new();
+
+    let mut start = 0;
+    while start < revisions.len() {
+        let first = &revisions[start];
+        let end = revisions[start..]
+            .iter()
+            .position(|r| r.author_id != first.author_id || r.created_at - first.created_at >= window)
+            .map_or(revisions.len(), |offset| start + offset);
+        let group = &revisions[start..end];
+
+        for revision in group {
+            if revision.kind == RevisionKind::Delete.as_str() {
+                tree.remove(&revision.file_id);
+            } else {
+                tree.insert(revision.file_id, (revision.path.clone(), revision.content.clone()));
+            }
+        }
+
+        let mut files: Vec<(String, String)> = tree.values().cloned().collect();
+        files.sort();
+        let author_name = first.author_username.clone().unwrap_or_else(|| fallback_author.to_string());
+        commits.push(GitCommit {
+            author_email: author_email(&author_name),
+            author_name,
+            timestamp: group.iter().map(|r| r.updated_at).max().unwrap_or(first.updated_at),
+            message: commit_message(group),
+            files,
+        });
+        start = end;
+    }
+
+    commits
+}
+
+/// Повідомлення коміту: перелік змін його ревізій (без повторів).
+fn commit_message(group: &[FileRevisionRow]) -> String {
+    let mut changes: Vec<String> = Vec::new();
+    for revision in group {
+        let change = match (revision.kind.as_str(), revision.old_path.as_deref()) {
+            ("rename", Some(old_path)) => format!("Перейменовано {old_path} → {}", revision.path),
+            ("delete", _) => format!("Видалено {}", revision.path),
+            ("restore", _) => format!("Відновлено {}", revision.path),
+            _ => format!("Змінено {}", revision.path),
+        };
+        if !changes.contains(&change) {
+            changes.push(change);
+        }
+    }
+
+    let hidden = changes.len().saturating_sub(HISTORY_MESSAGE_MAX_FILES);
+    changes.truncate(HISTORY_MESSAGE_MAX_FILES);
+    let mut message = changes.join("; ");
+    if hidden > 0 {
+        message.push_str(&format!(" та ще {hidden}"));
+    }
+    message
+}
+
+/// Створює тимчасовий git-репозиторій та відтворює в ньому послідовність комітів.
+///
+/// Дати та автори фіксуються явно, тому однакова історія завжди дає однакові хеші комітів.
+pub async fn build_repository(commits: &[GitCommit]) -> RequestResult<TempDir> {
+    let temp_dir = TempDir::new().map_err(|e| {
+        tracing::error!("Не вдалося створити тимчасову директорію для git: {}", e);
+        RequestError::internal_server_error("Не вдалося створити тимчасове середовище")
+    })?;
+    let repo = temp_dir.path();
+
+    run_git(repo, &["init", "--quiet", "--initial-branch", DEFAULT_BRANCH], &[]).await?;
+
+    for commit in commits {
+        clear_worktree(repo).await?;
+
+        for (relative_path, content) in &commit.files {
+            if !is_path_safe(relative_path) {
+                return Err(RequestError::bad_request(format!("Неприпустимий шлях до файлу: {}", relative_path)));
+            }
+
+            let file_path = repo.join(relative_path);
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&file_path, content).await?;
+        }
+
+        let date = git_date(&commit.timestamp);
+        let env = [
+            ("GIT_AUTHOR_NAME", commit.author_name.as_str()),
+            ("GIT_AUTHOR_EMAIL", commit.author_email.as_str()),
+            ("GIT_AUTHOR_DATE", date.as_str()),
+            ("GIT_COMMITTER_NAME", commit.author_name.as_str()),
+            ("GIT_COMMITTER_EMAIL", commit.author_email.as_str()),
+            ("GIT_COMMITTER_DATE", date.as_str()),
+        ];
+
+        run_git(repo, &["add", "--all"], &[]).await?;
+        run_git(
+            repo,
+            &["commit", "--quiet", "--allow-empty", "--no-verify", "-m", &commit.message],
+            &env,
+        )
+        .await?;
+    }
+
+    Ok(temp_dir)
+}
+
+/// Експортує історію проекту як `git bundle`, з якого можна виконати `git clone`.
+pub async fn export_bundle(doc_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<Vec<u8>> {
+    let commits = load_history(doc_id, ctx).await?;
+    let repo = build_repository(&commits).await?;
+    create_bundle(repo.path()).await
+}
+
+/// Пакує всі гілки репозиторію у bundle-файл та повертає його байти.
+pub(crate) async fn create_bundle(repo: &Path) -> RequestResult<Vec<u8>> {
+    let bundle_path = repo.join(".git").join("project.bundle");
+    let bundle_arg = bundle_path.to_string_lossy().to_string();
+
+    run_git(repo, &["bundle", "create", "--quiet", &bundle_arg, "--all"], &[]).await?;
+
+    let bytes = fs::read(&bundle_path).await?;
+    Ok(bytes)
+}
+
+// ─────────────────────────── Smart HTTP ──────────────────────────────────────
+
+/// Сервіс git, який підтримується через smart HTTP (тільки читання).
+pub(crate) const UPLOAD_PACK_SERVICE: &str = "git-upload-pack";
+
+/// Кодує рядок у формат pkt-line протоколу git (4 hex-символи довжини + дані).
+pub(crate) fn pkt_line(data: &str) -> Vec<u8> {
+    let mut line = format!("{:04x}", data.len() + 4).into_bytes();
+    line.extend_from_slice(data.as_bytes());
+    line
+}
+
+/// Формує відповідь `info/refs` для smart HTTP: заголовок сервісу та перелік посилань репозиторію.
+///
+/// Зібраний репозиторій зберігається в кеші, щоб наступний `git-upload-pack` віддав саме оголошені коміти.
+pub async fn advertise_refs(doc_id: Uuid, ctx: &ServiceContext<'_>) -> RequestResult<Vec<u8>> {
+    let commits = load_history(doc_id, ctx).await?;
+    let repo = build_repository(&commits).await?;
+    let body = advertise_repository_refs(repo.path()).await?;
+
+    let head = run_git(repo.path(), &["rev-parse", "HEAD"], &[]).await?;
+    let head = String::from_utf8_lossy(&head.stdout).trim().to_string();
+    ctx.git_repos.insert(doc_id, head, Arc::new(repo));
+    Ok(body)
+}
+
+/// Оголошує посилання готового репозиторію у форматі `git upload-pack --advertise-refs`.
+pub(crate) async fn advertise_repository_refs(repo: &Path) -> RequestResult<Vec<u8>> {
+    let output = run_git(
+        repo,
+        &["upload-pack", "--stateless-rpc", "--advertise-refs", "."],
+        &[],
+    )
+    .await?;
+
+    let mut body = pkt_line(&format!("# service={}\n", UPLOAD_PACK_SERVICE));
+    body.extend_from_slice(b"0000");
+    body.extend_from_slice(&output.stdout);
+    Ok(body)
+}
+
+/// Обробляє запит `git-upload-pack`: передає тіло запиту клієнта git та повертає packfile.
+///
+/// Використовує репозиторій з кешу, оголошений клієнту в `info/refs`. Якщо його там немає
+/// (оголошення було на іншій репліці або давно), репозиторій збирається заново: історія
+/// детермінована, тож без нових ревізій хеші комітів збігаються.
+pub async fn upload_pack(doc_id: Uuid, request: &[u8], ctx: &ServiceContext<'_>) -> RequestResult<Vec<u8>> {
+    if let Some(repo) = ctx.git_repos.find(doc_id, &wanted_commits(request)) {
+        return repository_upload_pack(repo.path(), request).await;
+    }
+
+    let commits = load_history(doc_id, ctx).await?;
+    let repo = build_repository(&commits).await?;
+    repository_upload_pack(repo.path(), request).await
+}
+
+/// Витягує хеші комітів з рядків `want` запиту upload-pack (pkt-line).
+pub(crate) fn wanted_commits(request: &[u8]) -> Vec<String> {
+    let mut wants = Vec::new();
+    let mut rest = request;
+    while rest.len() >= 4 {
+        let Some(len) = std::str::from_utf8(&rest[..4]).ok().and_then(|hex| usize::from_str_radix(hex, 16).ok()) else {
+            break;
+        };
+        // Службові пакети (flush/delim) не мають даних
+        if len < 4 {
+            rest = &rest[4..];
+            continue;
+        }
+        if len > rest.len() {
+            break;
+        }
+        let line = String::from_utf8_lossy(&rest[4..len]);
+        if let Some(want) = line.strip_prefix("want ") {
+            if let Some(hash) = want.split_whitespace().next() {
+                wants.push(hash.to_string());
+            }
+        }
+        rest = &rest[len..];
+    }
+    wants
+}
+
+/// Запускає `git upload-pack --stateless-rpc` для готового репозиторію.
+pub(crate) async fn repository_upload_pack(repo: &Path, request: &[u8]) -> RequestResult<Vec<u8>> {
+    let mut child = git_command(repo)
+        .args(["upload-pack", "--stateless-rpc", "."])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| {
+            tracing::error!("Не вдалося запустити git upload-pack: {}", e);
+            RequestError::internal_server_error("Не вдалося запустити git")
+        })?;
+
+    let mut stdin = child.stdin.take().ok_or_else(|| {
+        RequestError::internal_server_error("Не вдалося захопити stdin git upload-pack")
+    })?;
+
+    let request = request.to_vec();
+    tokio::spawn(async move {
+        if let Err(e) = stdin.write_all(&request).await {
+            tracing::warn!("Не вдалося передати запит у git upload-pack: {}", e);
+        }
+    });
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        tracing::error!(
+            "git upload-pack завершився з помилкою: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(RequestError::bad_request("Некоректний запит git upload-pack"));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Видаляє з робочої директорії все, крім `.git`, перед записом наступного коміту.
+async fn clear_worktree(repo: &Path) -> RequestResult<()> {
+    let mut entries = fs::read_dir(repo).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        if entry.file_type().await?.is_dir() {
+            fs::remove_dir_all(entry.path()).await?;
+        } else {
+            fs::remove_file(entry.path()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Запускає `git` з ізольованою конфігурацією (без глобальних та системних налаштувань).
+pub(crate) async fn run_git(repo: &Path, args: &[&str], env: &[(&str, &str)]) -> RequestResult<Output> {
+    let output = git_command(repo)
+        .args(args)
+        .envs(env.iter().copied())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| {
+            tracing::error!("Не вдалося запустити git: {}", e);
+            RequestError::internal_server_error("Не вдалося запустити git")
+        })?;
+
+    if !output.status.success() {
+        tracing::error!(
+            "git {:?} завершився з помилкою: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(RequestError::internal_server_error("Помилка виконання git"));
+    }
+
+    Ok(output)
+}
+
+/// Створює команду `git` з ізольованою конфігурацією у вказаному репозиторії.
+fn git_command(repo: &Path) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.env("GIT_CONFIG_NOSYSTEM", "1")
+       .env("GIT_CONFIG_GLOBAL", "/dev/null")
+       .env("GIT_TERMINAL_PROMPT", "0")
+       .current_dir(repo);
+    cmd
+}
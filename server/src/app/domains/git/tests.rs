@@ -0,0 +1,273 @@
+/// Модульні тести для домену git.
+/// Тести викликають реальні функції з `git::service` та локальний бінарний файл `git`,
+/// щоб перевірити, що згенерована історія клонується стандартним клієнтом.
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use uuid::Uuid;
+
+    use crate::app::domains::document::models::FileRevisionRow;
+    use crate::app::domains::git::models::GitCommit;
+    use crate::app::domains::git::service::{
+        advertise_repository_refs, author_email, build_repository, create_bundle, git_date,
+        history_from_revisions, pkt_line, repository_upload_pack, run_git, wanted_commits,
+    };
+    use crate::app::domains::git::GitRepoCache;
+
+    fn commit(author: &str, unix: i64, files: &[(&str, &str)]) -> GitCommit {
+        GitCommit {
+            author_name: author.to_string(),
+            author_email: author_email(author),
+            timestamp: Utc.timestamp_opt(unix, 0).unwrap(),
+            message: format!("commit {unix}"),
+            files: files.iter().map(|(p, c)| (p.to_string(), c.to_string())).collect(),
+        }
+    }
+
+    mod formatting {
+        use super::*;
+
+        /// Тест 1: Email автора синтезується з username.
+        #[test]
+        fn author_email_uses_username() {
+            assert_eq!(author_email("alice"), "alice@co-write.local");
+        }
+
+        /// Тест 2: Дата форматується у внутрішньому форматі git.
+        #[test]
+        fn git_date_is_raw_unix_format() {
+            let ts = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+            assert_eq!(git_date(&ts), "1700000000 +0000");
+        }
+    }
+
+    mod repository {
+        use super::*;
+
+        /// Тест 3: Кожен коміт історії стає окремим git-комітом зі збереженими автором і часом.
+        #[tokio::test]
+        async fn commits_preserve_author_and_time() {
+            let commits = vec![
+                commit("alice", 1_700_000_000, &[("src/main.rs", "fn main() {}")]),
+                commit("bob", 1_700_000_600, &[("src/main.rs", "fn main() { println!(); }")]),
+            ];
+
+            let repo = build_repository(&commits).await.expect("Репозиторій повинен створитися");
+            let log = run_git(repo.path(), &["log", "--format=%an %at"], &[]).await.unwrap();
+            let log = String::from_utf8_lossy(&log.stdout);
+
+            assert_eq!(log.lines().collect::<Vec<_>>(), vec!["bob 1700000600", "alice 1700000000"]);
+        }
+
+        /// Тест 4: Файли, відсутні у наступному коміті, видаляються з дерева.
+        #[tokio::test]
+        async fn removed_files_disappear_from_tree() {
+            let commits = vec![
+                commit("alice", 1_700_000_000, &[("src/main.rs", "a"), ("src/old.rs", "b")]),
+                commit("alice", 1_700_000_100, &[("src/main.rs", "a")]),
+            ];
+
+            let repo = build_repository(&commits).await.unwrap();
+            let tree = run_git(repo.path(), &["ls-tree", "-r", "--name-only", "HEAD"], &[]).await.unwrap();
+
+            assert_eq!(String::from_utf8_lossy(&tree.stdout).trim(), "src/main.rs");
+        }
+
+        /// Тест 5: Однакова історія дає однаковий хеш коміту.
+        #[tokio::test]
+        async fn history_is_deterministic() {
+            let make = || vec![commit("alice", 1_700_000_000, &[("main.rs", "fn main() {}")])];
+
+            let first = build_repository(&make()).await.unwrap();
+            let second = build_repository(&make()).await.unwrap();
+            let head = |out: std::process::Output| String::from_utf8_lossy(&out.stdout).trim().to_string();
+
+            assert_eq!(
+                head(run_git(first.path(), &["rev-parse", "HEAD"], &[]).await.unwrap()),
+                head(run_git(second.path(), &["rev-parse", "HEAD"], &[]).await.unwrap()),
+            );
+        }
+
+        /// Тест 6: Шляхи з обходом директорії відхиляються.
+        #[tokio::test]
+        async fn unsafe_paths_are_rejected() {
+            let commits = vec![commit("alice", 1_700_000_000, &[("../escape.rs", "")])];
+            assert!(build_repository(&commits).await.is_err());
+        }
+
+        /// Тест 7: Згенерований bundle клонується стандартним клієнтом git.
+        #[tokio::test]
+        async fn bundle_can_be_cloned() {
+            let commits = vec![commit("alice", 1_700_000_000, &[("src/main.rs", "fn main() {}")])];
+            let repo = build_repository(&commits).await.unwrap();
+
+            let bytes = create_bundle(repo.path()).await.expect("Bundle повинен створитися");
+            let target = tempfile::TempDir::new().unwrap();
+            let bundle_path = target.path().join("project.bundle");
+            std::fs::write(&bundle_path, bytes).unwrap();
+
+            let path = bundle_path.to_string_lossy().to_string();
+            run_git(target.path(), &["clone", "--quiet", &path, "cloned"], &[])
+                .await
+                .expect("Bundle повинен клонуватися");
+
+            let main_rs = std::fs::read_to_string(target.path().join("cloned/src/main.rs")).unwrap();
+            assert_eq!(main_rs, "fn main() {}");
+        }
+    }
+
+    mod smart_http {
+        use super::*;
+
+        /// Тест 8: pkt-line містить довжину рядка разом із 4-символьним префіксом.
+        #[test]
+        fn pkt_line_prefixes_length() {
+            assert_eq!(pkt_line("# service=git-upload-pack\n"), b"001e# service=git-upload-pack\n".to_vec());
+        }
+
+        /// Тест 9: Оголошення посилань починається із заголовка сервісу та містить гілку main.
+        #[tokio::test]
+        async fn advertisement_lists_main_branch() {
+            let commits = vec![commit("alice", 1_700_000_000, &[("src/main.rs", "fn main() {}")])];
+            let repo = build_repository(&commits).await.unwrap();
+
+            let body = advertise_repository_refs(repo.path()).await.expect("Оголошення повинно сформуватися");
+            let text = String::from_utf8_lossy(&body);
+
+            assert!(text.starts_with("001e# service=git-upload-pack\n0000"));
+            assert!(text.contains("refs/heads/main"));
+        }
+
+        /// Тест 10: Запит `want` на HEAD повертає packfile.
+        #[tokio::test]
+        async fn upload_pack_returns_packfile() {
+            let commits = vec![commit("alice", 1_700_000_000, &[("src/main.rs", "fn main() {}")])];
+            let repo = build_repository(&commits).await.unwrap();
+            let head = run_git(repo.path(), &["rev-parse", "HEAD"], &[]).await.unwrap();
+            let head = String::from_utf8_lossy(&head.stdout).trim().to_string();
+
+            let mut request = pkt_line(&format!("want {head}\n"));
+            request.extend_from_slice(b"0000");
+            request.extend_from_slice(&pkt_line("done\n"));
+
+            let response = repository_upload_pack(repo.path(), &request).await.expect("upload-pack повинен відповісти");
+
+            assert!(response.starts_with(b"0008NAK\n"));
+            assert!(response.windows(4).any(|w| w == b"PACK"));
+        }
+
+        /// Тест 13: З запиту upload-pack витягуються хеші всіх рядків `want`, службові пакети пропускаються.
+        #[test]
+        fn wanted_commits_are_parsed() {
+            let (first, second) = ("a".repeat(40), "b".repeat(40));
+            let mut request = pkt_line(&format!("want {first} multi_ack side-band-64k\n"));
+            request.extend_from_slice(&pkt_line(&format!("want {second}\n")));
+            request.extend_from_slice(b"0000");
+            request.extend_from_slice(&pkt_line("done\n"));
+
+            assert_eq!(wanted_commits(&request), vec![first, second]);
+        }
+
+        /// Тест 14: upload-pack знаходить у кеші саме той репозиторій, HEAD якого оголошено, навіть після нової збірки.
+        #[tokio::test]
+        async fn cache_serves_the_advertised_repository() {
+            let head = |repo: &tempfile::TempDir| {
+                let out = std::process::Command::new("git").args(["rev-parse", "HEAD"]).current_dir(repo.path()).output().unwrap();
+                String::from_utf8_lossy(&out.stdout).trim().to_string()
+            };
+            let doc_id = Uuid::now_v7();
+            let cache = GitRepoCache::default();
+            let advertised = build_repository(&[commit("alice", 1_700_000_000, &[("main.rs", "v1")])]).await.unwrap();
+            let newer = build_repository(&[commit("alice", 1_700_000_100, &[("main.rs", "v2")])]).await.unwrap();
+            let (advertised_head, newer_head) = (head(&advertised), head(&newer));
+            cache.insert(doc_id, advertised_head.clone(), std::sync::Arc::new(advertised));
+            cache.insert(doc_id, newer_head.clone(), std::sync::Arc::new(newer));
+
+            let found = cache.find(doc_id, std::slice::from_ref(&advertised_head)).expect("оголошений репозиторій у кеші");
+            assert_eq!(head(&found), advertised_head);
+            assert!(cache.find(Uuid::now_v7(), &[advertised_head]).is_none());
+            assert!(cache.find(doc_id, &["c".repeat(40)]).is_none());
+        }
+    }
+
+    mod history {
+        use super::*;
+
+        fn revision(
+            file_id: Uuid,
+            author: Option<(Uuid, &str)>,
+            unix: i64,
+            kind: &str,
+            path: &str,
+            old_path: Option<&str>,
+            content: &str,
+        ) -> FileRevisionRow {
+            FileRevisionRow {
+                id: Uuid::now_v7(),
+                file_id,
+                path: path.to_string(),
+                old_path: old_path.map(str::to_string),
+                content: content.to_string(),
+                kind: kind.to_string(),
+                author_id: author.map(|(id, _)| id),
+                author_username: author.map(|(_, name)| name.to_string()),
+                created_at: Utc.timestamp_opt(unix, 0).unwrap(),
+                updated_at: Utc.timestamp_opt(unix, 0).unwrap(),
+            }
+        }
+
+        fn files(commit: &GitCommit) -> Vec<(&str, &str)> {
+            commit.files.iter().map(|(p, c)| (p.as_str(), c.as_str())).collect()
+        }
+
+        /// Тест 11: Ревізії одного автора у вікні коміту зливаються, зміна автора чи вікна починає новий коміт.
+        #[test]
+        fn revisions_are_grouped_by_author_and_window() {
+            let alice = (Uuid::now_v7(), "alice");
+            let bob = (Uuid::now_v7(), "bob");
+            let (main_rs, lib_rs) = (Uuid::now_v7(), Uuid::now_v7());
+            let revisions = vec![
+                revision(main_rs, None, 1_700_000_000, "edit", "main.rs", None, "v0"),
+                revision(main_rs, Some(alice), 1_700_000_100, "edit", "main.rs", None, "v1"),
+                revision(lib_rs, Some(alice), 1_700_000_200, "edit", "lib.rs", None, "lib"),
+                revision(main_rs, Some(bob), 1_700_000_300, "edit", "main.rs", None, "v2"),
+                revision(main_rs, Some(bob), 1_700_001_000, "edit", "main.rs", None, "v3"),
+            ];
+
+            let commits = history_from_revisions(&revisions, "owner");
+
+            let summary: Vec<_> = commits.iter().map(|c| (c.author_name.as_str(), c.timestamp.timestamp())).collect();
+            assert_eq!(
+                summary,
+                vec![("owner", 1_700_000_000), ("alice", 1_700_000_200), ("bob", 1_700_000_300), ("bob", 1_700_001_000)]
+            );
+            assert_eq!(files(&commits[1]), vec![("lib.rs", "lib"), ("main.rs", "v1")]);
+            assert_eq!(commits[1].message, "Змінено main.rs; Змінено lib.rs");
+            assert_eq!(files(&commits[3]), vec![("lib.rs", "lib"), ("main.rs", "v3")]);
+        }
+
+        /// Тест 12: Перейменування та видалення відтворюються за ідентичністю файлу, і історія клонується.
+        #[tokio::test]
+        async fn renames_and_deletes_are_replayed() {
+            let alice = (Uuid::now_v7(), "alice");
+            let bob = (Uuid::now_v7(), "bob");
+            let (main_rs, old_rs) = (Uuid::now_v7(), Uuid::now_v7());
+            let revisions = vec![
+                revision(main_rs, Some(alice), 1_700_000_000, "edit", "main.rs", None, "fn main() {}"),
+                revision(old_rs, Some(alice), 1_700_000_010, "edit", "old.rs", None, "// old"),
+                revision(main_rs, Some(bob), 1_700_000_020, "rename", "src/main.rs", Some("main.rs"), "fn main() {}"),
+                revision(old_rs, Some(bob), 1_700_000_030, "delete", "old.rs", None, "// old"),
+            ];
+
+            let commits = history_from_revisions(&revisions, "owner");
+            assert_eq!(commits.len(), 2);
+            assert_eq!(files(&commits[1]), vec![("src/main.rs", "fn main() {}")]);
+            assert_eq!(commits[1].message, "Перейменовано main.rs → src/main.rs; Видалено old.rs");
+
+            let repo = build_repository(&commits).await.unwrap();
+            let log = run_git(repo.path(), &["log", "--format=%an"], &[]).await.unwrap();
+            assert_eq!(String::from_utf8_lossy(&log.stdout).lines().collect::<Vec<_>>(), vec!["bob", "alice"]);
+        }
+    }
+}
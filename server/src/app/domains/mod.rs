@@ -1,4 +1,5 @@
 pub mod auth;
 pub mod document;
 pub mod execution;
+pub mod git;
 pub mod lsp;
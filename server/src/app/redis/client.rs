@@ -33,6 +33,14 @@ impl RedisClient {
             .map_err(|e| RequestError::internal_server_error(format!("Помилка Redis GET: {}", e)))
     }
 
+    /// Атомарно отримує значення за ключем і видаляє ключ (GETDEL).
+    pub async fn get_del(&self, key: &str) -> RequestResult<Option<String>> {
+        let mut conn = self.conn().await?;
+        conn.get_del(key)
+            .await
+            .map_err(|e| RequestError::internal_server_error(format!("Помилка Redis GETDEL: {}", e)))
+    }
+
     /// Встановлює рядкове значення за ключем.
     pub async fn set(&self, key: &str, value: &str) -> RequestResult<()> {
         let mut conn = self.conn().await?;
@@ -5,8 +5,17 @@ pub enum RedisKey {
     /// Ключ Redis для збереження зліпка (snapshot) документа Automerge: `document:snapshot:<doc_id>`
     DocumentSnapshot(Uuid),
     
-    /// Ключ Redis для каналу/потоку оновлень кімнати: `document:room:<doc_id>`
+    /// Redis Stream з подіями кімнати для всіх реплік: `document:room:<doc_id>`
     DocumentRoom(Uuid),
+
+    /// Множина підключень, присутніх у кімнаті (з усіх реплік): `document:presence:<doc_id>`
+    DocumentPresence(Uuid),
+
+    /// Запис присутності окремого підключення з TTL: `document:presence:<doc_id>:<conn_id>`
+    PresenceEntry(Uuid, Uuid),
+
+    /// Одноразовий квиток для підключення до WebSocket з TTL: `ws:ticket:<ticket>`
+    WsTicket(String),
 }
 
 impl Display for RedisKey {
@@ -14,6 +23,11 @@ impl Display for RedisKey {
         match self {
             RedisKey::DocumentSnapshot(doc_id) => write!(f, "document:snapshot:{}", doc_id),
             RedisKey::DocumentRoom(doc_id) => write!(f, "document:room:{}", doc_id),
+            RedisKey::DocumentPresence(doc_id) => write!(f, "document:presence:{}", doc_id),
+            RedisKey::PresenceEntry(doc_id, conn_id) => {
+                write!(f, "document:presence:{}:{}", doc_id, conn_id)
+            }
+            RedisKey::WsTicket(ticket) => write!(f, "ws:ticket:{}", ticket),
         }
     }
 }
@@ -3,6 +3,7 @@ use actix_web::web::{self, ServiceConfig};
 use crate::app::domains::auth as auth_domain;
 use crate::app::domains::document as doc_domain;
 use crate::app::domains::execution as exec_domain;
+use crate::app::domains::git as git_domain;
 use crate::app::domains::lsp as lsp_domain;
 
 /// Налаштовує роути авторизації.
@@ -20,16 +21,32 @@ pub fn cfg_documents(config: &mut ServiceConfig) {
         web::scope("/documents")
             .route("",                               web::get().to(doc_domain::list_documents))
             .route("/create",                        web::post().to(doc_domain::create_document))
+            .route("/invites/{token}/accept",        web::post().to(doc_domain::accept_invite))
             .route("/{id}",                          web::get().to(doc_domain::get_document))
             .route("/{id}/title",                    web::get().to(doc_domain::get_document_title))
             .route("/{id}/execute",                  web::post().to(exec_domain::execute_code))
             .route("/{id}/test",                     web::post().to(exec_domain::execute_tests))
+            .route("/{id}/runs/{run_id}",            web::get().to(exec_domain::get_run_queue_position))
+            .route("/{id}/runs/{run_id}/cancel",     web::post().to(exec_domain::cancel_run))
+            .route("/{id}/run-policy",               web::put().to(doc_domain::set_run_policy))
             .route("/{id}/format",                   web::post().to(exec_domain::format_code))
             .route("/{id}/complete",                 web::post().to(lsp_domain::complete))
             .route("/{id}/hover",                    web::post().to(lsp_domain::hover))
             .route("/{id}/members",                  web::post().to(doc_domain::add_member))
             .route("/{id}/members/{uid}",            web::delete().to(doc_domain::remove_member))
+            .route("/{id}/invites",                  web::post().to(doc_domain::create_invite))
+            .route("/{id}/invites",                  web::get().to(doc_domain::list_invites))
+            .route("/{id}/invites/{invite_id}",      web::delete().to(doc_domain::revoke_invite))
             .route("/{id}/participants",             web::get().to(doc_domain::get_participants))
+            .route("/{id}/ws-ticket",                web::post().to(doc_domain::issue_ws_ticket))
             .route("/{id}/export",                   web::post().to(doc_domain::export_project))
+            .route("/{id}/trash",                    web::get().to(doc_domain::list_trash))
+            .route("/{id}/trash/{trash_id}/restore", web::post().to(doc_domain::restore_trash))
+            .route("/{id}/trash/{trash_id}",         web::delete().to(doc_domain::purge_trash))
+            .route("/{id}/files/history",            web::get().to(doc_domain::file_history))
+            .route("/{id}/files/revisions/{rev_id}", web::get().to(doc_domain::get_file_revision))
+            .route("/{id}/export/git",               web::post().to(git_domain::export_git_bundle))
+            .route("/{id}.git/info/refs",            web::get().to(git_domain::info_refs))
+            .route("/{id}.git/git-upload-pack",      web::post().to(git_domain::upload_pack))
     );
 }
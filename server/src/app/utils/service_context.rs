@@ -1,12 +1,18 @@
 use sqlx::PgPool;
-use crate::app::redis::client::RedisClient;
+use crate::app::domains::document::fanout::Fanout;
+use crate::app::domains::document::sessions::SessionStore;
+use crate::app::domains::execution::ExecutionQueue;
+use crate::app::domains::git::GitRepoCache;
 use crate::AppData;
 
-/// Контекст сервісу, що містить посилання на пул підключень до бази даних, клієнт Redis та кімнати.
+/// Контекст сервісу, що містить посилання на пул підключень до бази даних, сховище сесій, кімнати, розсилку подій кімнат, чергу запусків та кеш git-репозиторіїв.
 pub struct ServiceContext<'a> {
     pub db_pool: &'a PgPool,
-    pub redis: &'a RedisClient,
+    pub sessions: &'a dyn SessionStore,
     pub rooms: &'a crate::app::domains::document::models::Rooms,
+    pub fanout: &'a Fanout,
+    pub jobs: &'a ExecutionQueue,
+    pub git_repos: &'a GitRepoCache,
 }
 
 impl<'a> From<&'a AppData> for ServiceContext<'a> {
@@ -14,8 +20,11 @@ impl<'a> From<&'a AppData> for ServiceContext<'a> {
     fn from(value: &'a AppData) -> Self {
         Self {
             db_pool: &value.pool,
-            redis: &value.redis,
+            sessions: value.sessions.as_ref(),
             rooms: &value.rooms,
+            fanout: &value.fanout,
+            jobs: &value.jobs,
+            git_repos: &value.git_repos,
         }
     }
 }
@@ -2,4 +2,156 @@
 pub mod document {
     /// Інтервал часу в секундах, з яким фоновий демон виконує злиття змін до документа.
     pub const MERGE_INTERVAL_SECONDS: u64 = 30;
+
+    /// Інтервал часу в секундах між запусками остаточного очищення кошика.
+    pub const TRASH_PURGE_INTERVAL_SECONDS: u64 = 3600;
+
+    /// Кількість днів зберігання файлів у кошику за замовчуванням.
+    pub const DEFAULT_TRASH_RETENTION_DAYS: u64 = 30;
+
+    /// Проміжок часу в секундах, протягом якого послідовні правки одного автора
+    /// зливаються в одну ревізію історії файлу.
+    pub const REVISION_COALESCE_SECONDS: i64 = 60;
+
+    /// Час життя запису присутності учасника у сховищі сесій без оновлення (секунди).
+    pub const PRESENCE_TTL_SECONDS: u64 = 30;
+
+    /// Інтервал, з яким репліка подовжує записи присутності своїх підключень (секунди).
+    pub const PRESENCE_HEARTBEAT_SECONDS: u64 = 10;
+
+    /// Максимальна довжина повідомлення чату (символи).
+    pub const CHAT_MAX_MESSAGE_CHARS: usize = 4000;
+
+    /// Найдовший термін дії запрошення (години); безстрокові запрошення створюються без терміну.
+    pub const INVITE_MAX_EXPIRES_IN_HOURS: i64 = 24 * 365;
+
+    /// Кількість повідомлень чату на сторінці історії за замовчуванням.
+    pub const CHAT_HISTORY_PAGE_SIZE: u32 = 50;
+
+    /// Найбільша сторінка історії чату, яку може запросити клієнт.
+    pub const CHAT_HISTORY_MAX_PAGE_SIZE: u32 = 200;
+
+    /// Максимальна довжина коментаря до запиту на право редагування (символи).
+    pub const ACCESS_REQUEST_MAX_MESSAGE_CHARS: usize = 500;
+
+    /// Приблизна кількість подій, що зберігаються в потоці кімнати для повторного читання.
+    pub const ROOM_STREAM_MAX_LEN: usize = 10_000;
+
+    /// Час життя потоку кімнати без нових подій (секунди).
+    pub const ROOM_STREAM_TTL_SECONDS: i64 = 86_400;
+
+    /// Скільки мілісекунд читач потоку кімнати чекає на нові події за один запит.
+    pub const ROOM_STREAM_BLOCK_MS: usize = 5_000;
+
+    /// Найбільша кількість подій, що читаються з потоку за один запит.
+    pub const ROOM_STREAM_BATCH_SIZE: usize = 256;
+
+    /// Найдовша пауза між спробами перепідключення читача потоку (секунди).
+    pub const ROOM_STREAM_RECONNECT_MAX_SECONDS: u64 = 30;
+
+    /// Скільки подій може накопичити отримувач брокера в пам'яті, перш ніж почне їх пропускати.
+    pub const MEMORY_BROKER_CAPACITY: usize = 1_024;
+
+    /// Канал Postgres `NOTIFY` для подій кімнат.
+    pub const ROOM_NOTIFY_CHANNEL: &str = "cowrite_room_events";
+
+    /// Найбільший розмір сповіщення Postgres (байти); більші події зберігаються в таблиці.
+    pub const ROOM_NOTIFY_MAX_BYTES: usize = 8_000;
+
+    /// Скільки зберігається подія, що не вмістилася в сповіщення (секунди).
+    pub const ROOM_EVENT_PAYLOAD_TTL_SECONDS: u64 = 300;
+
+    /// Інтервал прибирання збережених подій кімнат (секунди).
+    pub const ROOM_EVENT_PAYLOAD_PURGE_SECONDS: u64 = 60;
+
+    /// Інтервал прибирання прострочених записів присутності та квитків у Postgres (секунди).
+    pub const SESSION_PURGE_SECONDS: u64 = 60;
+}
+
+/// Константи протоколу WebSocket.
+pub mod ws {
+    /// Поточна (максимальна) версія протоколу текстових повідомлень.
+    pub const PROTOCOL_VERSION: u32 = 1;
+
+    /// Мінімальна версія протоколу, яку ще підтримує сервер.
+    pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+    /// Можливості протоколу, які сервер може увімкнути для клієнта.
+    pub const SERVER_CAPABILITIES: &[&str] = &["cursors", "chat", "runs"];
+
+    /// Час у секундах, протягом якого клієнт має надіслати `hello` після підключення.
+    pub const HANDSHAKE_TIMEOUT_SECONDS: u64 = 10;
+
+    /// Інтервал між ping-кадрами сервера за замовчуванням (секунди).
+    pub const DEFAULT_HEARTBEAT_INTERVAL_SECONDS: u64 = 15;
+
+    /// Час очікування pong після ping за замовчуванням (секунди).
+    pub const DEFAULT_PONG_TIMEOUT_SECONDS: u64 = 10;
+
+    /// Час без повідомлень клієнта, після якого сесія закривається, за замовчуванням (секунди).
+    pub const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 1800;
+
+    /// Місткість черги вихідних кадрів одного підключення.
+    pub const OUTBOUND_QUEUE_CAPACITY: usize = 256;
+
+    /// Скільки секунд чекати на закриття сесії клієнта, що не встигає читати.
+    pub const OUTBOUND_CLOSE_TIMEOUT_SECONDS: u64 = 2;
+
+    /// Час життя одноразового квитка для підключення до WebSocket (секунди).
+    pub const WS_TICKET_TTL_SECONDS: u64 = 30;
+
+    /// Підпротокол, з яким клієнт може передати JWT у `Sec-WebSocket-Protocol` замість квитка.
+    pub const WS_BEARER_PROTOCOL: &str = "cowrite.bearer";
+}
+
+/// Константи виконання коду в пісочниці.
+pub mod execution {
+    /// Ліміт часу збирання програми (секунди).
+    pub const COMPILE_TIMEOUT_SECONDS: u64 = 15;
+
+    /// Ліміт часу виконання програми (секунди).
+    pub const RUN_TIMEOUT_SECONDS: u64 = 5;
+
+    /// Кількість запусків, що виконуються одночасно, за замовчуванням.
+    pub const DEFAULT_EXEC_WORKERS: usize = 2;
+
+    /// Скільки запусків може чекати в черзі за замовчуванням.
+    pub const DEFAULT_EXEC_QUEUE_CAPACITY: usize = 32;
+
+    /// Скільки запусків (у черзі та активних) може мати один користувач за замовчуванням.
+    pub const DEFAULT_EXEC_MAX_JOBS_PER_USER: usize = 2;
+
+    /// Скільки запусків (у черзі та активних) може мати один документ за замовчуванням.
+    pub const DEFAULT_EXEC_MAX_JOBS_PER_DOCUMENT: usize = 4;
+
+    /// Ліміт часу виконання інтерактивної програми, що чекає на stdin від клієнта (секунди).
+    pub const RUN_INTERACTIVE_TIMEOUT_SECONDS: u64 = 120;
+
+    /// Скільки ще не переданих програмі повідомлень stdin може чекати в черзі інтерактивного запуску.
+    pub const RUN_STDIN_QUEUE_CAPACITY: usize = 64;
+
+    /// Ліміт часу збирання та виконання тестів (секунди).
+    pub const TEST_TIMEOUT_SECONDS: u64 = 10;
+
+    /// Скільки байтів кожного потоку виводу (stdout/stderr) зберігається та надсилається клієнту.
+    pub const RUN_OUTPUT_MAX_BYTES: usize = 512 * 1024;
+
+    /// Інтервал, з яким фрагменти виводу об'єднуються в один кадр клієнту (мілісекунди).
+    pub const RUN_OUTPUT_FLUSH_MS: u64 = 50;
+}
+
+/// Константи експорту проекту в git.
+pub mod git {
+    /// Проміжок часу в секундах від першої ревізії коміту, протягом якого наступні ревізії
+    /// того самого автора потрапляють у той самий коміт.
+    pub const HISTORY_COMMIT_WINDOW_SECONDS: i64 = 600;
+
+    /// Скільки змінених файлів перелічується в повідомленні коміту.
+    pub const HISTORY_MESSAGE_MAX_FILES: usize = 5;
+
+    /// Скільки секунд зібраний для `info/refs` репозиторій чекає на запит `git-upload-pack`.
+    pub const REPO_CACHE_TTL_SECONDS: u64 = 300;
+
+    /// Скільки зібраних репозиторіїв одного документа зберігається одночасно.
+    pub const REPO_CACHE_MAX_PER_DOCUMENT: usize = 4;
 }
@@ -1,11 +1,17 @@
 use sqlx::PgPool;
+use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 use anyhow::{Error, Result};
 
 use crate::{
+    app::domains::document::broker::Broker,
+    app::domains::document::fanout::Fanout,
     app::domains::document::models::Rooms,
+    app::domains::document::sessions::SessionStore,
+    app::domains::execution::{ExecutionQueue, RunRegistry},
+    app::domains::git::GitRepoCache,
     app::domains::lsp::LspManager,
-    app::redis::client::RedisClient,
+    core::config_builder::{ExecutionSettings, WsSettings},
 };
 
 /// Спільний стан додатка, доступний усім обробникам запитів.
@@ -13,11 +19,22 @@ use crate::{
 pub struct AppData {
     pub pool: PgPool,
     pub rooms: Rooms,
-    pub redis: RedisClient,
-    pub redis_url: String,
+    /// Розсилка подій кімнат між репліками.
+    pub fanout: Fanout,
+    /// Присутність учасників та квитки WebSocket.
+    pub sessions: Arc<dyn SessionStore>,
     pub cancel_token: CancellationToken,
     pub lsp: LspManager,
+    /// Запуски коду, що виконуються на цій репліці.
+    pub runs: RunRegistry,
+    /// Черга запусків коду цієї репліки.
+    pub jobs: ExecutionQueue,
+    /// Зібрані git-репозиторії проектів між запитами smart HTTP.
+    pub git_repos: GitRepoCache,
     pub jwt_secret: String,
+    pub ws: WsSettings,
+    /// Токен доступу до метрик розсилки (`None` — метрики вимкнено).
+    pub metrics_token: Option<String>,
 }
 
 impl AppData {
@@ -44,10 +61,13 @@ impl AppData {
 pub struct AppDataBuilder {
     pool: Option<PgPool>,
     rooms: Option<Rooms>,
-    redis: Option<RedisClient>,
-    redis_url: Option<String>,
+    sessions: Option<Arc<dyn SessionStore>>,
     cancel_token: Option<CancellationToken>,
     jwt_secret: Option<String>,
+    ws: Option<WsSettings>,
+    broker: Option<Arc<dyn Broker>>,
+    execution: Option<ExecutionSettings>,
+    metrics_token: Option<String>,
 }
 
 impl AppDataBuilder {
@@ -63,19 +83,27 @@ impl AppDataBuilder {
             rooms: self
                 .rooms
                 .ok_or_else(|| Error::msg("Помилка створення AppData (відсутні rooms)"))?,
-            redis: self
-                .redis
-                .ok_or_else(|| Error::msg("Помилка створення AppData (відсутній redis)"))?,
-            redis_url: self
-                .redis_url
-                .ok_or_else(|| Error::msg("Помилка створення AppData (відсутній redis_url)"))?,
+            sessions: self
+                .sessions
+                .ok_or_else(|| Error::msg("Помилка створення AppData (відсутнє сховище сесій)"))?,
             cancel_token: self
                 .cancel_token
                 .ok_or_else(|| Error::msg("Помилка створення AppData (відсутній cancel_token)"))?,
             jwt_secret: self
                 .jwt_secret
                 .ok_or_else(|| Error::msg("Помилка створення AppData (відсутній jwt_secret)"))?,
+            ws: self
+                .ws
+                .ok_or_else(|| Error::msg("Помилка створення AppData (відсутні налаштування ws)"))?,
+            fanout: Fanout::new(
+                self.broker
+                    .ok_or_else(|| Error::msg("Помилка створення AppData (відсутній broker)"))?,
+            ),
             lsp,
+            runs: RunRegistry::default(),
+            jobs: ExecutionQueue::new(self.execution.unwrap_or_default()),
+            git_repos: GitRepoCache::default(),
+            metrics_token: self.metrics_token.filter(|token| !token.is_empty()),
         };
 
         Ok(app_data)
@@ -93,15 +121,9 @@ impl AppDataBuilder {
         self
     }
 
-    /// Додає клієнт Redis.
-    pub fn with_redis(mut self, redis: RedisClient) -> Self {
-        self.redis = Some(redis);
-        self
-    }
-
-    /// Додає URL-адресу Redis.
-    pub fn with_redis_url(mut self, redis_url: String) -> Self {
-        self.redis_url = Some(redis_url);
+    /// Додає сховище присутності учасників та квитків WebSocket.
+    pub fn with_session_store(mut self, sessions: Arc<dyn SessionStore>) -> Self {
+        self.sessions = Some(sessions);
         self
     }
 
@@ -116,4 +138,28 @@ impl AppDataBuilder {
         self.jwt_secret = Some(jwt_secret);
         self
     }
+
+    /// Додає налаштування перевірки живості WebSocket-з'єднань.
+    pub fn with_ws_settings(mut self, ws: WsSettings) -> Self {
+        self.ws = Some(ws);
+        self
+    }
+
+    /// Додає брокер подій кімнат.
+    pub fn with_broker(mut self, broker: Arc<dyn Broker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    /// Додає налаштування черги виконання коду (за замовчуванням — значення з `constants::execution`).
+    pub fn with_execution_settings(mut self, execution: ExecutionSettings) -> Self {
+        self.execution = Some(execution);
+        self
+    }
+
+    /// Додає токен доступу до метрик розсилки (за замовчуванням метрики вимкнено).
+    pub fn with_metrics_token(mut self, metrics_token: Option<String>) -> Self {
+        self.metrics_token = metrics_token;
+        self
+    }
 }
@@ -17,6 +17,18 @@ pub struct AppConfig {
 
     #[serde(flatten)]
     pub jwt: JwtSettings,
+
+    #[serde(flatten)]
+    pub trash: TrashSettings,
+
+    #[serde(flatten)]
+    pub ws: WsSettings,
+
+    #[serde(flatten)]
+    pub fanout: FanoutSettings,
+
+    #[serde(flatten)]
+    pub execution: ExecutionSettings,
 }
 
 impl AppConfig {
@@ -84,14 +96,16 @@ impl PostgresSettings {
 }
 
 /// Налаштування підключення до Redis.
+///
+/// Використовуються лише з `FANOUT_BROKER=redis`, тому для інших брокерів змінні можна не задавати.
 #[serde_as]
 #[derive(Deserialize, Clone)]
 pub struct RedisSettings {
-    #[serde(rename = "redis_host")]
+    #[serde(rename = "redis_host", default = "default_redis_host")]
     pub host: String,
 
     #[serde_as(as = "DisplayFromStr")]
-    #[serde(rename = "redis_port")]
+    #[serde(rename = "redis_port", default = "default_redis_port")]
     pub port: u16,
 
     #[serde(default)]
@@ -110,9 +124,155 @@ impl RedisSettings {
     }
 }
 
+fn default_redis_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_redis_port() -> u16 {
+    6379
+}
+
 /// Налаштування JWT.
 #[derive(Deserialize, Clone)]
 pub struct JwtSettings {
     #[serde(rename = "jwt_secret")]
     pub secret: String,
 }
+
+/// Налаштування кошика видалених файлів.
+#[serde_as]
+#[derive(Deserialize, Clone)]
+pub struct TrashSettings {
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "trash_retention_days", default = "default_trash_retention_days")]
+    pub retention_days: u64,
+}
+
+impl TrashSettings {
+    /// Повертає тривалість зберігання файлів у кошику.
+    pub fn retention(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.retention_days * 24 * 3600)
+    }
+}
+
+fn default_trash_retention_days() -> u64 {
+    crate::constants::document::DEFAULT_TRASH_RETENTION_DAYS
+}
+
+/// Налаштування перевірки живості WebSocket-з'єднань.
+#[serde_as]
+#[derive(Deserialize, Clone, Debug)]
+pub struct WsSettings {
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "ws_heartbeat_interval_secs", default = "default_heartbeat_interval")]
+    pub heartbeat_interval_secs: u64,
+
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "ws_pong_timeout_secs", default = "default_pong_timeout")]
+    pub pong_timeout_secs: u64,
+
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "ws_idle_timeout_secs", default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+}
+
+impl WsSettings {
+    /// Інтервал між ping-кадрами сервера.
+    pub fn heartbeat_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.heartbeat_interval_secs.max(1))
+    }
+
+    /// Час, протягом якого клієнт має відповісти pong на ping.
+    pub fn pong_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.pong_timeout_secs)
+    }
+
+    /// Час без повідомлень клієнта, після якого сесія вважається покинутою.
+    pub fn idle_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.idle_timeout_secs)
+    }
+}
+
+fn default_heartbeat_interval() -> u64 {
+    crate::constants::ws::DEFAULT_HEARTBEAT_INTERVAL_SECONDS
+}
+
+fn default_pong_timeout() -> u64 {
+    crate::constants::ws::DEFAULT_PONG_TIMEOUT_SECONDS
+}
+
+fn default_idle_timeout() -> u64 {
+    crate::constants::ws::DEFAULT_IDLE_TIMEOUT_SECONDS
+}
+
+/// Налаштування черги виконання коду.
+#[serde_as]
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExecutionSettings {
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "exec_workers", default = "default_exec_workers")]
+    pub workers: usize,
+
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "exec_queue_capacity", default = "default_exec_queue_capacity")]
+    pub queue_capacity: usize,
+
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "exec_max_jobs_per_user", default = "default_exec_max_jobs_per_user")]
+    pub max_jobs_per_user: usize,
+
+    #[serde_as(as = "DisplayFromStr")]
+    #[serde(rename = "exec_max_jobs_per_document", default = "default_exec_max_jobs_per_document")]
+    pub max_jobs_per_document: usize,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        Self {
+            workers: default_exec_workers(),
+            queue_capacity: default_exec_queue_capacity(),
+            max_jobs_per_user: default_exec_max_jobs_per_user(),
+            max_jobs_per_document: default_exec_max_jobs_per_document(),
+        }
+    }
+}
+
+fn default_exec_workers() -> usize {
+    crate::constants::execution::DEFAULT_EXEC_WORKERS
+}
+
+fn default_exec_queue_capacity() -> usize {
+    crate::constants::execution::DEFAULT_EXEC_QUEUE_CAPACITY
+}
+
+fn default_exec_max_jobs_per_user() -> usize {
+    crate::constants::execution::DEFAULT_EXEC_MAX_JOBS_PER_USER
+}
+
+fn default_exec_max_jobs_per_document() -> usize {
+    crate::constants::execution::DEFAULT_EXEC_MAX_JOBS_PER_DOCUMENT
+}
+
+/// Реалізація брокера подій кімнат.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BrokerKind {
+    /// Redis Streams — для кількох реплік.
+    #[default]
+    Redis,
+    /// Postgres `LISTEN/NOTIFY` — для кількох реплік без Redis Streams.
+    Postgres,
+    /// Шина в пам'яті процесу — для одновузлового розгортання та тестів.
+    Memory,
+}
+
+/// Налаштування розсилки подій кімнат між репліками.
+#[derive(Deserialize, Clone, Debug)]
+pub struct FanoutSettings {
+    #[serde(rename = "fanout_broker", default)]
+    pub broker: BrokerKind,
+
+    /// Токен доступу до `/ws/metrics`; без нього метрики вимкнено.
+    #[serde(rename = "metrics_token", default)]
+    pub metrics_token: Option<String>,
+}
@@ -1,18 +1,25 @@
 use anyhow::Result;
 use std::net::TcpListener;
+use std::sync::Arc;
 use tokio_util::sync::CancellationToken;
 
+use crate::app::domains::document::broker::{Broker, MemoryBroker, MemoryHub, PostgresBroker, RedisStreamsBroker};
+use crate::app::domains::document::fanout;
 use crate::app::domains::document::models::Rooms;
+use crate::app::domains::document::presence;
+use crate::app::domains::document::service as document_service;
+use crate::app::domains::document::sessions::{MemorySessionStore, PostgresSessionStore, RedisSessionStore, SessionStore};
 use crate::app::redis::client::RedisClient;
 use crate::core::app_data::AppData;
-use crate::core::config_builder::AppConfig;
+use crate::core::config_builder::{AppConfig, BrokerKind};
 use crate::core::logger::{self, LogLevel};
 use crate::core::server;
 use crate::core::{pg_connector, redis_connector};
 
 /// Запуск та ініціалізація бекенд-сервера Co-Write.
 /// 
-/// Налаштовує логування, підключається до бази даних PostgreSQL та пулу Redis,
+/// Налаштовує логування, підключається до бази даних PostgreSQL (та до Redis, якщо його
+/// використовує обраний брокер подій),
 /// створює спільний стан додатка (AppData) та запускає веб-сервер Actix-web.
 pub async fn start() -> Result<()> {
     dotenvy::dotenv().ok();
@@ -24,10 +31,28 @@ pub async fn start() -> Result<()> {
         .await
         .unwrap();
 
-    let redis_pool = redis_connector::connect(&config.redis.addr())
-        .await
-        .unwrap();
-    let redis_client = RedisClient::new(redis_pool);
+    // Redis потрібен лише з брокером Redis Streams; решта брокерів мають власне сховище сесій
+    let (broker, sessions): (Arc<dyn Broker>, Arc<dyn SessionStore>) = match config.fanout.broker {
+        BrokerKind::Redis => {
+            let redis_pool = redis_connector::connect(&config.redis.addr())
+                .await
+                .unwrap();
+            let redis_client = RedisClient::new(redis_pool);
+            (
+                Arc::new(RedisStreamsBroker::new(redis_client.clone(), config.redis.addr())),
+                Arc::new(RedisSessionStore::new(redis_client)),
+            )
+        }
+        BrokerKind::Postgres => (
+            Arc::new(PostgresBroker::new(db_pool.clone())),
+            Arc::new(PostgresSessionStore::new(db_pool.clone())),
+        ),
+        BrokerKind::Memory => (
+            Arc::new(MemoryBroker::new(&MemoryHub::default())),
+            Arc::new(MemorySessionStore::default()),
+        ),
+    };
+    tracing::info!("Сховище сесій: {}", sessions.name());
 
     let lst = TcpListener::bind(config.app.addr()).unwrap();
     let cancel_token = CancellationToken::new();
@@ -35,13 +60,21 @@ pub async fn start() -> Result<()> {
     let app_data = AppData::builder()
         .with_pool(db_pool)
         .with_rooms(Rooms::default())
-        .with_redis(redis_client)
-        .with_redis_url(config.redis.addr())
+        .with_session_store(sessions)
         .with_cancel_token(cancel_token)
         .with_jwt_secret(config.jwt.secret)
+        .with_ws_settings(config.ws)
+        .with_broker(broker)
+        .with_metrics_token(config.fanout.metrics_token)
+        .with_execution_settings(config.execution)
         .build()
         .unwrap();
 
+    document_service::run_trash_purge(&app_data, config.trash.retention());
+    presence::run_presence_heartbeat(&app_data);
+    app_data.sessions.run(&app_data);
+    fanout::run(&app_data);
+
     let server = server::run(lst, app_data)?;
     server.await.map_err(Into::into)
 }